@@ -1,5 +1,7 @@
 use async_trait::async_trait;
 use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
 use tracing::info;
 
 ///
@@ -18,3 +20,72 @@ pub async fn background_task<T: BackgroundTask + Send + Sync + 'static>(
     info!("starting background task: {}", name.to_string());
     task.run().await;
 }
+
+///
+/// 后台任务的监督者
+///
+/// 同一代路由创建的后台任务(例如负载均衡器的服务发现和健康检查)由同一个监督者启动,
+/// 路由被替换时取消监督者, 停止这一代的所有后台任务。
+///
+#[derive(Clone, Default)]
+pub struct Supervisor {
+    token: CancellationToken,
+    tracker: TaskTracker,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// 启动后台任务, 监督者被取消时停止
+    ///
+    pub fn spawn<T: BackgroundTask + Send + Sync + 'static>(
+        &self,
+        name: impl ToString,
+        task: Arc<T>,
+    ) {
+        let name = name.to_string();
+        let token = self.token.clone();
+        self.tracker.spawn(async move {
+            if token
+                .run_until_cancelled(background_task(&name, task))
+                .await
+                .is_none()
+            {
+                info!("stopped background task: {}", name);
+            }
+        });
+    }
+
+    ///
+    /// 取消监督者启动的所有后台任务
+    ///
+    pub fn cancel(&self) {
+        self.token.cancel();
+        self.tracker.close();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    ///
+    /// 运行中的后台任务数量
+    ///
+    pub fn len(&self) -> usize {
+        self.tracker.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracker.is_empty()
+    }
+
+    ///
+    /// 等待所有后台任务结束, 需要先调用[Supervisor::cancel]
+    ///
+    pub async fn wait(&self) {
+        self.tracker.wait().await;
+    }
+}
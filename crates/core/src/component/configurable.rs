@@ -1,5 +1,5 @@
-use crate::component::Args;
 use crate::Error;
+use crate::component::Args;
 use serde::de::DeserializeOwned;
 use serde_yaml::Value;
 use std::cmp::min;
@@ -65,26 +65,28 @@ pub trait Configurable: DeserializeOwned {
                         append_k_seq(&mut builder, field, value);
                     }
                     ShortcutMode::TailingSequence => {
-                        if fields.len() != 2 {
+                        if fields.len() < 2 {
                             return Err(Error::new(format!(
-                                "[{}] configure with shortcut mode [TailingSequence] must have exactly 2 fields",
+                                "[{}] configure with shortcut mode [TailingSequence] must have at least 2 fields",
                                 companion
                             )));
                         }
-                        let k1 = fields[0];
-                        let k2 = fields[1];
-                        match value.rsplit_once(COMMA) {
-                            Some((v1, v2)) => {
-                                append_k_v(&mut builder, k1, v1);
-                                append_k_seq(&mut builder, k2, v2);
-                            }
-                            None => {
-                                return Err(Error::new(format!(
-                                    "[{}] configure with shortcut mode [TailingSequence] value must contains `,`",
-                                    companion
-                                )));
-                            }
+                        // 前面的字段依次对应一个值, 剩余的值作为最后一个字段的序列
+                        let values = value.splitn(fields.len(), COMMA).collect::<Vec<_>>();
+                        if values.len() != fields.len() {
+                            return Err(Error::new(format!(
+                                "[{}] configure with shortcut mode [TailingSequence] value must contains at least {} values separated by `,`",
+                                companion,
+                                fields.len()
+                            )));
                         }
+                        let last = fields.len() - 1;
+                        fields
+                            .iter()
+                            .zip(values.iter())
+                            .take(last)
+                            .for_each(|(k, v)| append_k_v(&mut builder, k, v.trim()));
+                        append_k_seq(&mut builder, fields[last], values[last]);
                     }
                     ShortcutMode::Unsupported => {
                         return Err(Error::new(format!(
@@ -0,0 +1,8 @@
+use crate::new_type;
+use http::uri::Scheme;
+
+new_type!(
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    ClientScheme,
+    Scheme
+);
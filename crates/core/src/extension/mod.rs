@@ -1,9 +1,15 @@
 mod client_addr;
+mod client_scheme;
+mod peer_addr;
 mod raw_uri;
 mod route_id;
+mod upstream;
 mod url_params;
 
 pub use client_addr::ClientAddr;
+pub use client_scheme::ClientScheme;
+pub use peer_addr::PeerAddr;
 pub use raw_uri::RawUri;
 pub use route_id::RouteId;
+pub use upstream::{UpstreamAddr, UpstreamLatency};
 pub use url_params::{insert_url_params, UrlParams};
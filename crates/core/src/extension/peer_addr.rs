@@ -0,0 +1,11 @@
+use crate::new_type;
+use std::net::SocketAddr;
+
+new_type!(
+    ///
+    /// 连接的对端地址, 开启真实客户端地址提取时`ClientAddr`可能被替换, 该地址保持不变
+    ///
+    #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    PeerAddr,
+    SocketAddr
+);
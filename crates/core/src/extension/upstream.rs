@@ -0,0 +1,15 @@
+use crate::new_type;
+use std::sync::Arc;
+use std::time::Duration;
+
+new_type!(
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    UpstreamAddr,
+    Arc<str>
+);
+
+new_type!(
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    UpstreamLatency,
+    Duration
+);
@@ -8,6 +8,8 @@ pub mod executor;
 pub mod expression;
 pub mod extension;
 pub mod make;
+pub mod metrics;
+pub mod propagation;
 pub mod util;
 
 pub use error::*;
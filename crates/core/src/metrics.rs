@@ -0,0 +1,186 @@
+//!
+//! Prometheus 指标
+//!
+//! 所有指标都注册在[`prometheus::default_registry`]中，通过[`encode`]输出`Prometheus`文本格式。
+//!
+//! 为了保证标签的基数有界，路由标签只来自路由ID，上游标签只来自负载均衡器名称以及选中的后端地址，
+//! 不会使用原始的请求路径。
+//!
+use crate::Error;
+use http::{Method, StatusCode};
+use prometheus::{
+    Encoder, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+    register_histogram_vec, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec,
+};
+use std::sync::LazyLock;
+
+///
+/// 请求总数
+///
+pub static HTTP_REQUESTS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "satex_http_requests_total",
+        "Total number of HTTP requests.",
+        &["route", "method", "status"]
+    )
+    .expect("register metric `satex_http_requests_total` error!")
+});
+
+///
+/// 请求延迟
+///
+pub static HTTP_REQUEST_DURATION_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "satex_http_request_duration_seconds",
+        "HTTP request latency in seconds.",
+        &["route", "method", "status"]
+    )
+    .expect("register metric `satex_http_request_duration_seconds` error!")
+});
+
+///
+/// 正在处理的请求数量
+///
+pub static HTTP_REQUESTS_IN_FLIGHT: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "satex_http_requests_in_flight",
+        "Number of HTTP requests currently being served."
+    )
+    .expect("register metric `satex_http_requests_in_flight` error!")
+});
+
+///
+/// 上游请求延迟
+///
+pub static UPSTREAM_REQUEST_DURATION_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "satex_upstream_request_duration_seconds",
+        "Upstream request latency in seconds.",
+        &["upstream", "backend"]
+    )
+    .expect("register metric `satex_upstream_request_duration_seconds` error!")
+});
+
+///
+/// 上游请求错误数量
+///
+pub static UPSTREAM_ERRORS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "satex_upstream_errors_total",
+        "Total number of failed upstream requests.",
+        &["upstream", "backend"]
+    )
+    .expect("register metric `satex_upstream_errors_total` error!")
+});
+
+///
+/// 后端服务健康状态, `1`表示可以接收流量, `0`表示不可以接收流量
+///
+pub static UPSTREAM_BACKEND_HEALTHY: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "satex_upstream_backend_healthy",
+        "Whether the upstream backend is ready to receive traffic.",
+        &["upstream", "backend"]
+    )
+    .expect("register metric `satex_upstream_backend_healthy` error!")
+});
+
+///
+/// 活跃的连接数量
+///
+pub static SERVER_ACTIVE_CONNECTIONS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "satex_server_active_connections",
+        "Number of active connections.",
+        &["listener", "worker"]
+    )
+    .expect("register metric `satex_server_active_connections` error!")
+});
+
+///
+/// 配置重新加载次数
+///
+pub static CONFIG_RELOADS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "satex_config_reloads_total",
+        "Total number of config reloads.",
+        &["source", "result"]
+    )
+    .expect("register metric `satex_config_reloads_total` error!")
+});
+
+///
+/// 记录一次配置重新加载的结果
+///
+/// # Arguments
+///
+/// * `source`: 配置变更的来源
+/// * `success`: 是否成功
+///
+pub fn record_reload(source: &str, success: bool) {
+    let result = if success { "success" } else { "failure" };
+    CONFIG_RELOADS_TOTAL
+        .with_label_values(&[source, result])
+        .inc();
+}
+
+///
+/// 获取状态码的类别, 例如: `2xx`
+///
+pub fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+///
+/// 获取请求方法的标签值, 非标准的请求方法统一使用`OTHER`
+///
+pub fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        Method::HEAD => "HEAD",
+        Method::OPTIONS => "OPTIONS",
+        Method::CONNECT => "CONNECT",
+        Method::PATCH => "PATCH",
+        Method::TRACE => "TRACE",
+        _ => "OTHER",
+    }
+}
+
+///
+/// 将所有指标编码为`Prometheus`文本格式
+///
+pub fn encode() -> Result<String, Error> {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .map_err(Error::new)?;
+    String::from_utf8(buffer).map_err(Error::new)
+}
+
+///
+/// 计数守卫, 创建时增加计数, 释放时减少计数
+///
+pub struct GaugeGuard(IntGauge);
+
+impl GaugeGuard {
+    pub fn new(gauge: IntGauge) -> Self {
+        gauge.inc();
+        Self(gauge)
+    }
+}
+
+impl Drop for GaugeGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
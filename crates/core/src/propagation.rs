@@ -0,0 +1,68 @@
+//!
+//! 分布式追踪上下文传播
+//!
+//! 使用全局的[`TextMapPropagator`](opentelemetry::propagation::TextMapPropagator)在请求头中提取和注入追踪上下文,
+//! 未设置全局传播器时所有操作都不会产生任何效果。
+//!
+use http::header::{HeaderName, HeaderValue};
+use http::HeaderMap;
+use opentelemetry::global;
+use opentelemetry::propagation::{Extractor, Injector};
+use tracing::{debug, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+///
+/// 从请求头中提取追踪上下文, 并设置为`span`的父上下文
+///
+/// # Arguments
+///
+/// * `span`: 当前的span
+/// * `headers`: 请求头
+///
+pub fn set_parent(span: &Span, headers: &HeaderMap) {
+    let context =
+        global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)));
+    if let Err(e) = span.set_parent(context) {
+        debug!("set span parent error: {}", e);
+    }
+}
+
+///
+/// 将`span`的追踪上下文注入到请求头中
+///
+/// # Arguments
+///
+/// * `span`: 当前的span
+/// * `headers`: 请求头
+///
+pub fn inject(span: &Span, headers: &mut HeaderMap) {
+    let context = span.context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(headers))
+    });
+}
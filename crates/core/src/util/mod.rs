@@ -1,3 +1,4 @@
+mod cidr;
 mod clone_service;
 mod new_type;
 mod path;
@@ -5,6 +6,7 @@ mod response;
 mod try_downcast;
 mod with;
 
+pub use cidr::Cidr;
 pub use clone_service::SyncBoxCloneService;
 pub use path::{canonicalize, remove_end_sep, remove_start_sep};
 pub use response::*;
@@ -0,0 +1,40 @@
+use satex_core::util::Cidr;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+fn contains(cidr: &str, ip: &str) -> bool {
+    Cidr::from_str(cidr)
+        .unwrap()
+        .contains(&IpAddr::from_str(ip).unwrap())
+}
+
+#[test]
+fn parse() {
+    let cidr = Cidr::from_str("10.0.0.0/8").unwrap();
+    assert_eq!(cidr.prefix(), 8);
+    assert_eq!(cidr.to_string(), "10.0.0.0/8");
+    assert_eq!(Cidr::from_str("::1").unwrap().to_string(), "::1/128");
+    assert!(Cidr::from_str("10.0.0.0/33").is_err());
+    assert!(Cidr::from_str("fd00::/129").is_err());
+    assert!(Cidr::from_str("localhost").is_err());
+}
+
+#[test]
+fn contains_ipv4() {
+    assert!(contains("10.0.0.0/8", "10.255.0.1"));
+    assert!(!contains("10.0.0.0/8", "11.0.0.1"));
+    assert!(contains("192.168.1.1", "192.168.1.1"));
+    assert!(!contains("192.168.1.1", "192.168.1.2"));
+    assert!(contains("0.0.0.0/0", "8.8.8.8"));
+    assert!(!contains("0.0.0.0/0", "::1"));
+    // IPv4映射的IPv6地址
+    assert!(contains("127.0.0.0/8", "::ffff:127.0.0.1"));
+}
+
+#[test]
+fn contains_ipv6() {
+    assert!(contains("fd00::/8", "fd12:3456::1"));
+    assert!(!contains("fd00::/8", "fe80::1"));
+    assert!(contains("::/0", "2001:db8::1"));
+    assert!(!contains("::1", "127.0.0.1"));
+}
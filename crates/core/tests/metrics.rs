@@ -0,0 +1,32 @@
+use http::{Method, StatusCode};
+use satex_core::metrics::{
+    GaugeGuard, HTTP_REQUESTS_IN_FLIGHT, HTTP_REQUESTS_TOTAL, encode, method_label, status_class,
+};
+
+#[test]
+fn labels() {
+    assert_eq!(status_class(StatusCode::OK), "2xx");
+    assert_eq!(status_class(StatusCode::NOT_FOUND), "4xx");
+    assert_eq!(status_class(StatusCode::BAD_GATEWAY), "5xx");
+    assert_eq!(method_label(&Method::GET), "GET");
+    assert_eq!(
+        method_label(&Method::from_bytes(b"PURGE").unwrap()),
+        "OTHER"
+    );
+}
+
+#[test]
+fn encode_metrics() {
+    HTTP_REQUESTS_TOTAL
+        .with_label_values(&["test", "GET", "2xx"])
+        .inc();
+    {
+        let _guard = GaugeGuard::new(HTTP_REQUESTS_IN_FLIGHT.clone());
+        assert_eq!(HTTP_REQUESTS_IN_FLIGHT.get(), 1);
+    }
+    assert_eq!(HTTP_REQUESTS_IN_FLIGHT.get(), 0);
+
+    let text = encode().unwrap();
+    assert!(text.contains(r#"satex_http_requests_total{method="GET",route="test",status="2xx"} 1"#));
+    assert!(text.contains("satex_http_requests_in_flight 0"));
+}
@@ -0,0 +1,39 @@
+use http::HeaderMap;
+use opentelemetry::global;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use satex_core::propagation::{inject, set_parent};
+use tracing::info_span;
+use tracing_subscriber::layer::SubscriberExt;
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const PARENT_ID: &str = "00f067aa0ba902b7";
+
+#[test]
+fn extract_and_inject() {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let provider = SdkTracerProvider::builder().build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+    tracing::subscriber::with_default(subscriber, || {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            format!("00-{TRACE_ID}-{PARENT_ID}-01").parse().unwrap(),
+        );
+        headers.insert("tracestate", "vendor=value".parse().unwrap());
+
+        let span = info_span!("route");
+        set_parent(&span, &headers);
+
+        let mut forwarded = HeaderMap::new();
+        inject(&span, &mut forwarded);
+        let traceparent = forwarded["traceparent"].to_str().unwrap();
+        assert!(traceparent.starts_with(&format!("00-{TRACE_ID}-")));
+        assert!(!traceparent.contains(PARENT_ID));
+        assert!(traceparent.ends_with("-01"));
+        assert_eq!(forwarded["tracestate"], "vendor=value");
+    });
+}
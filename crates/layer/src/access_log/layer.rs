@@ -0,0 +1,22 @@
+use crate::access_log::{AccessLog, AccessLogWriter, Format};
+use tower::Layer;
+
+#[derive(Clone)]
+pub struct AccessLogLayer {
+    format: Format,
+    writer: AccessLogWriter,
+}
+
+impl AccessLogLayer {
+    pub fn new(format: Format, writer: AccessLogWriter) -> Self {
+        Self { format, writer }
+    }
+}
+
+impl<S> Layer<S> for AccessLogLayer {
+    type Service = AccessLog<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AccessLog::new(inner, self.format, self.writer.clone())
+    }
+}
@@ -0,0 +1,29 @@
+use crate::access_log::{AccessLogLayer, AccessLogWriter, Format, Rotation};
+use crate::make::MakeRouteLayer;
+use satex_core::component::{Args, Configurable};
+use satex_core::Error;
+use satex_macro::make;
+use std::path::PathBuf;
+
+#[make(kind = AccessLog)]
+pub struct MakeAccessLogRouteLayer {
+    #[serde(default)]
+    format: Format,
+    #[serde(default)]
+    path: Option<PathBuf>,
+    #[serde(default)]
+    rotation: Rotation,
+}
+
+impl MakeRouteLayer for MakeAccessLogRouteLayer {
+    type Layer = AccessLogLayer;
+
+    fn make(&self, args: Args) -> Result<Self::Layer, Error> {
+        let config = Config::with_args(args)?;
+        let writer = match config.path {
+            Some(path) => AccessLogWriter::file(path, config.rotation)?,
+            None => AccessLogWriter::stdout()?,
+        };
+        Ok(AccessLogLayer::new(config.format, writer))
+    }
+}
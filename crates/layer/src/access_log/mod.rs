@@ -0,0 +1,247 @@
+#![doc = include_str!("../../docs/access_log.md")]
+
+mod layer;
+mod make;
+mod record;
+mod writer;
+
+pub use layer::*;
+pub use make::*;
+pub use record::*;
+pub use writer::*;
+
+use bytes::Bytes;
+use futures::future::LocalBoxFuture;
+use http::header::HeaderName;
+use http::{HeaderValue, Request, Response};
+use http_body::{Frame, SizeHint};
+use pin_project_lite::pin_project;
+use satex_core::body::Body;
+use satex_core::extension::{UpstreamAddr, UpstreamLatency};
+use satex_core::{BoxError, Error};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tower::Service;
+
+///
+/// 请求ID请求头
+///
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+#[derive(Clone)]
+pub struct AccessLog<S> {
+    inner: S,
+    format: Format,
+    writer: AccessLogWriter,
+}
+
+impl<S> AccessLog<S> {
+    pub fn new(inner: S, format: Format, writer: AccessLogWriter) -> Self {
+        Self {
+            inner,
+            format,
+            writer,
+        }
+    }
+}
+
+impl<S, ResBody> Service<Request<Body>> for AccessLog<S>
+where
+    S: Service<Request<Body>, Response = Response<ResBody>>,
+    S::Error: Into<BoxError>,
+    S::Future: 'static,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(|e| Error::new(e.into()))
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let start = Instant::now();
+        let request_id = request_id(&mut request);
+        let bytes_in = Arc::new(AtomicU64::new(0));
+        let mut logger = Logger {
+            record: Record::new(&request, request_id),
+            format: self.format,
+            writer: self.writer.clone(),
+            start,
+            bytes_in: bytes_in.clone(),
+        };
+        let request = request.map(|body| {
+            Body::new(CountBody {
+                inner: body,
+                counter: bytes_in,
+            })
+        });
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            match future.await {
+                Ok(response) => {
+                    let record = &mut logger.record;
+                    record.status = Some(response.status().as_u16());
+                    let extensions = response.extensions();
+                    record.upstream_addr = extensions
+                        .get::<UpstreamAddr>()
+                        .map(|addr| addr.to_string());
+                    record.upstream_latency =
+                        extensions.get::<UpstreamLatency>().map(|latency| **latency);
+                    Ok(response.map(|body| {
+                        Body::new(LogBody {
+                            inner: body,
+                            logger: Some(logger),
+                        })
+                    }))
+                }
+                Err(e) => {
+                    let e = Error::new(e.into());
+                    logger.set_error(&e);
+                    Err(e)
+                }
+            }
+        })
+    }
+}
+
+///
+/// 获取请求ID, 请求中不存在时生成新的请求ID并写入请求头, 以便转发到上游
+///
+fn request_id(request: &mut Request<Body>) -> String {
+    if let Some(request_id) = request
+        .headers()
+        .get(&X_REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+    {
+        return request_id.to_string();
+    }
+    let request_id = format!("{:032x}", rand::random::<u128>());
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        request.headers_mut().insert(X_REQUEST_ID, value);
+    }
+    request_id
+}
+
+///
+/// 日志记录器, 释放时写入访问日志
+///
+/// 响应体发送完成、请求出错或者客户端提前断开时都会释放, 保证每个请求都只写入一行日志。
+///
+struct Logger {
+    record: Record,
+    format: Format,
+    writer: AccessLogWriter,
+    start: Instant,
+    bytes_in: Arc<AtomicU64>,
+}
+
+impl Logger {
+    fn set_error(&mut self, error: &Error) {
+        self.record.error = Some(error.to_string());
+    }
+}
+
+impl Drop for Logger {
+    fn drop(&mut self) {
+        self.record.latency = self.start.elapsed();
+        self.record.bytes_in = self.bytes_in.load(Ordering::Relaxed);
+        self.writer.write_line(self.record.format(self.format));
+    }
+}
+
+pin_project! {
+    struct CountBody<B> {
+        #[pin]
+        inner: B,
+        counter: Arc<AtomicU64>,
+    }
+}
+
+impl<B> http_body::Body for CountBody<B>
+where
+    B: http_body::Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let poll = this.inner.poll_frame(cx);
+        if let Poll::Ready(Some(Ok(frame))) = &poll
+            && let Some(data) = frame.data_ref()
+        {
+            this.counter
+                .fetch_add(data.len() as u64, Ordering::Relaxed);
+        }
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+pin_project! {
+    struct LogBody<B> {
+        #[pin]
+        inner: B,
+        logger: Option<Logger>,
+    }
+}
+
+impl<B> http_body::Body for LogBody<B>
+where
+    B: http_body::Body<Data = Bytes>,
+    B::Error: Into<BoxError>,
+{
+    type Data = Bytes;
+    type Error = Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        match this.inner.poll_frame(cx) {
+            Poll::Ready(Some(Ok(frame))) => {
+                if let (Some(data), Some(logger)) = (frame.data_ref(), this.logger.as_mut()) {
+                    logger.record.bytes_out += data.len() as u64;
+                }
+                Poll::Ready(Some(Ok(frame)))
+            }
+            Poll::Ready(Some(Err(e))) => {
+                let e = Error::new(e.into());
+                if let Some(mut logger) = this.logger.take() {
+                    logger.set_error(&e);
+                }
+                Poll::Ready(Some(Err(e)))
+            }
+            Poll::Ready(None) => {
+                this.logger.take();
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
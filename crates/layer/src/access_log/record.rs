@@ -0,0 +1,162 @@
+use chrono::{DateTime, Local, SecondsFormat};
+use http::header::{REFERER, USER_AGENT};
+use http::{HeaderMap, Request};
+use satex_core::extension::{ClientAddr, RawUri, RouteId};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+const DASH: &str = "-";
+
+///
+/// 访问日志格式
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Format {
+    ///
+    /// `Combined`格式, 在标准字段之后追加扩展字段
+    ///
+    #[default]
+    Combined,
+
+    ///
+    /// 每行一个`JSON`对象
+    ///
+    Json,
+}
+
+///
+/// 一条访问日志
+///
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub time: DateTime<Local>,
+    pub request_id: String,
+    pub route: Option<String>,
+    pub client_addr: Option<SocketAddr>,
+    pub method: String,
+    pub uri: String,
+    pub version: String,
+    pub referer: Option<String>,
+    pub user_agent: Option<String>,
+    pub status: Option<u16>,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub latency: Duration,
+    pub upstream_addr: Option<String>,
+    pub upstream_latency: Option<Duration>,
+    pub error: Option<String>,
+}
+
+impl Record {
+    pub fn new<B>(request: &Request<B>, request_id: String) -> Self {
+        let extensions = request.extensions();
+        let uri = extensions
+            .get::<RawUri>()
+            .map(|uri| uri.to_string())
+            .unwrap_or_else(|| request.uri().to_string());
+        Self {
+            time: Local::now(),
+            request_id,
+            route: extensions.get::<RouteId>().map(|id| id.to_string()),
+            client_addr: extensions.get::<ClientAddr>().map(|addr| **addr),
+            method: request.method().to_string(),
+            uri,
+            version: format!("{:?}", request.version()),
+            referer: header(request.headers(), REFERER.as_str()),
+            user_agent: header(request.headers(), USER_AGENT.as_str()),
+            status: None,
+            bytes_in: 0,
+            bytes_out: 0,
+            latency: Duration::ZERO,
+            upstream_addr: None,
+            upstream_latency: None,
+            error: None,
+        }
+    }
+
+    pub fn format(&self, format: Format) -> String {
+        match format {
+            Format::Combined => self.combined(),
+            Format::Json => self.json(),
+        }
+    }
+
+    fn combined(&self) -> String {
+        let mut line = String::with_capacity(256);
+        let _ = write!(
+            line,
+            "{} - - [{}] \"{} {} {}\" {} {} \"{}\" \"{}\" {} {:.3} \"{}\" {} \"{}\" \"{}\"",
+            self.client_addr
+                .map(|addr| addr.ip().to_string())
+                .unwrap_or_else(|| DASH.to_string()),
+            self.time.format("%d/%b/%Y:%H:%M:%S %z"),
+            escape(&self.method),
+            escape(&self.uri),
+            self.version,
+            self.status
+                .map(|status| status.to_string())
+                .unwrap_or_else(|| DASH.to_string()),
+            self.bytes_out,
+            escape(self.referer.as_deref().unwrap_or(DASH)),
+            escape(self.user_agent.as_deref().unwrap_or(DASH)),
+            self.bytes_in,
+            self.latency.as_secs_f64(),
+            escape(self.upstream_addr.as_deref().unwrap_or(DASH)),
+            self.upstream_latency
+                .map(|latency| format!("{:.3}", latency.as_secs_f64()))
+                .unwrap_or_else(|| DASH.to_string()),
+            escape(self.route.as_deref().unwrap_or(DASH)),
+            escape(&self.request_id),
+        );
+        line
+    }
+
+    fn json(&self) -> String {
+        json!({
+            "time": self.time.to_rfc3339_opts(SecondsFormat::Millis, false),
+            "request_id": self.request_id,
+            "route": self.route,
+            "client_addr": self.client_addr.map(|addr| addr.to_string()),
+            "method": self.method,
+            "uri": self.uri,
+            "version": self.version,
+            "referer": self.referer,
+            "user_agent": self.user_agent,
+            "status": self.status,
+            "bytes_in": self.bytes_in,
+            "bytes_out": self.bytes_out,
+            "latency": self.latency.as_secs_f64(),
+            "upstream_addr": self.upstream_addr,
+            "upstream_latency": self.upstream_latency.map(|latency| latency.as_secs_f64()),
+            "error": self.error,
+        })
+        .to_string()
+    }
+}
+
+fn header(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+}
+
+///
+/// 转义引号内的字段, 避免破坏日志行的结构
+///
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\x{:02x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
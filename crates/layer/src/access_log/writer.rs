@@ -0,0 +1,301 @@
+use chrono::Utc;
+use satex_core::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{create_dir_all, read_dir, remove_file, rename, File, OpenOptions};
+use std::io::{stdout, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, Weak};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+
+///
+/// 输出的文件路径以及轮转配置, 标准输出没有路径
+///
+type Key = (Option<PathBuf>, Rotation);
+
+///
+/// 已经创建的输出, 相同的文件以及轮转配置共享一个后台写入线程
+///
+/// 这里只保存弱引用, 使用输出的路由全部释放后, 后台写入线程随之停止并关闭文件。
+///
+static WRITERS: LazyLock<Mutex<HashMap<Key, Weak<Worker>>>> = LazyLock::new(Default::default);
+
+///
+/// 后台写入线程, 守卫释放时写入剩余的日志并停止线程
+///
+struct Worker {
+    writer: NonBlocking,
+    guard: Mutex<Option<WorkerGuard>>,
+}
+
+///
+/// 访问日志输出
+///
+/// 日志由后台线程写入, 缓冲区满时会丢弃新的日志, 不会阻塞工作线程。
+///
+#[derive(Clone)]
+pub struct AccessLogWriter {
+    worker: Arc<Worker>,
+}
+
+impl AccessLogWriter {
+    ///
+    /// 输出到标准输出
+    ///
+    pub fn stdout() -> Result<Self, Error> {
+        Self::get_or_create((None, Rotation::default()), || Ok(stdout()))
+    }
+
+    ///
+    /// 输出到文件
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: 文件路径
+    /// * `rotation`: 轮转配置
+    ///
+    /// returns: Result<AccessLogWriter, Error>
+    ///
+    pub fn file(path: impl AsRef<Path>, rotation: Rotation) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        Self::get_or_create((Some(path.clone()), rotation.clone()), || {
+            RollingFile::new(path, rotation)
+        })
+    }
+
+    fn get_or_create<W, F>(key: Key, f: F) -> Result<Self, Error>
+    where
+        W: Write + Send + 'static,
+        F: FnOnce() -> Result<W, Error>,
+    {
+        let mut writers = WRITERS.lock().map_err(|e| Error::new(e.to_string()))?;
+        writers.retain(|_, worker| worker.strong_count() > 0);
+        if let Some(worker) = writers.get(&key).and_then(Weak::upgrade) {
+            return Ok(Self { worker });
+        }
+        let (writer, guard) = tracing_appender::non_blocking(f()?);
+        let worker = Arc::new(Worker {
+            writer,
+            guard: Mutex::new(Some(guard)),
+        });
+        writers.insert(key, Arc::downgrade(&worker));
+        Ok(Self { worker })
+    }
+
+    ///
+    /// 停止所有的后台写入线程并写入剩余的日志, 服务退出时调用
+    ///
+    pub fn shutdown() {
+        let writers = match WRITERS.lock() {
+            Ok(mut writers) => std::mem::take(&mut *writers),
+            Err(_) => return,
+        };
+        for worker in writers.into_values().filter_map(|worker| worker.upgrade()) {
+            if let Ok(mut guard) = worker.guard.lock() {
+                guard.take();
+            }
+        }
+    }
+
+    ///
+    /// 写入一行日志
+    ///
+    pub fn write_line(&self, mut line: String) {
+        line.push('\n');
+        if let Err(e) = self.worker.writer.clone().write_all(line.as_bytes()) {
+            warn!("write access log error: {}", e);
+        }
+    }
+}
+
+///
+/// 日志文件轮转配置
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Rotation {
+    ///
+    /// 单个文件的最大字节数
+    ///
+    #[serde(default)]
+    pub max_size: Option<u64>,
+
+    ///
+    /// 按照时间轮转的周期
+    ///
+    #[serde(default)]
+    pub interval: Option<Interval>,
+
+    ///
+    /// 保留的历史文件数量
+    ///
+    #[serde(default)]
+    pub max_files: Option<usize>,
+}
+
+///
+/// 轮转周期, 以UTC时间为准
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Interval {
+    Minutely,
+    Hourly,
+    Daily,
+}
+
+impl Interval {
+    fn secs(&self) -> u64 {
+        match self {
+            Interval::Minutely => 60,
+            Interval::Hourly => 60 * 60,
+            Interval::Daily => 24 * 60 * 60,
+        }
+    }
+
+    ///
+    /// 计算下一次轮转的时间戳
+    ///
+    fn next(&self, now: u64) -> u64 {
+        let secs = self.secs();
+        (now / secs + 1) * secs
+    }
+}
+
+///
+/// 支持按照大小和时间轮转的日志文件
+///
+pub struct RollingFile {
+    path: PathBuf,
+    rotation: Rotation,
+    file: File,
+    size: u64,
+    next_rotation: Option<u64>,
+}
+
+impl RollingFile {
+    pub fn new(path: impl Into<PathBuf>, rotation: Rotation) -> Result<Self, Error> {
+        let path = path.into();
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            create_dir_all(parent).map_err(Error::new)?;
+        }
+        let file = open(&path)?;
+        let size = file.metadata().map_err(Error::new)?.len();
+        let next_rotation = rotation.interval.map(|interval| interval.next(now()));
+        Ok(Self {
+            path,
+            rotation,
+            file,
+            size,
+            next_rotation,
+        })
+    }
+
+    fn should_rotate(&self, len: usize) -> bool {
+        let by_size = self
+            .rotation
+            .max_size
+            .is_some_and(|max_size| self.size > 0 && self.size + len as u64 > max_size);
+        let by_time = self
+            .next_rotation
+            .is_some_and(|next_rotation| now() >= next_rotation);
+        by_size || by_time
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+        if self.size > 0 {
+            rename(&self.path, self.rotated_path())?;
+        }
+        self.file = open(&self.path).map_err(std::io::Error::other)?;
+        self.size = 0;
+        self.next_rotation = self.rotation.interval.map(|interval| interval.next(now()));
+        if let Some(max_files) = self.rotation.max_files {
+            self.prune(max_files)?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// 历史文件路径, 格式为`{path}.{yyyyMMddHHmmss}`, 存在同名文件时追加序号
+    ///
+    fn rotated_path(&self) -> PathBuf {
+        let suffix = Utc::now().format("%Y%m%d%H%M%S").to_string();
+        let mut path = self.history_path(&suffix);
+        let mut index = 1;
+        while path.exists() {
+            path = self.history_path(&format!("{}.{}", suffix, index));
+            index += 1;
+        }
+        path
+    }
+
+    fn history_path(&self, suffix: &str) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".");
+        path.push(suffix);
+        PathBuf::from(path)
+    }
+
+    ///
+    /// 删除超出保留数量的历史文件
+    ///
+    fn prune(&self, max_files: usize) -> std::io::Result<()> {
+        let Some(name) = self.path.file_name().and_then(|name| name.to_str()) else {
+            return Ok(());
+        };
+        let prefix = format!("{}.", name);
+        let dir = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let mut histories = read_dir(&dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+                let name = entry.file_name().into_string().ok()?;
+                name.starts_with(&prefix).then(|| (modified, entry.path()))
+            })
+            .collect::<Vec<_>>();
+        if histories.len() > max_files {
+            histories.sort();
+            for (_, path) in histories.iter().take(histories.len() - max_files) {
+                remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.should_rotate(buf.len()) {
+            self.rotate()?;
+        }
+        let len = self.file.write(buf)?;
+        self.size += len as u64;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn open(path: &Path) -> Result<File, Error> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(Error::new)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
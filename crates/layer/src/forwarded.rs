@@ -0,0 +1,312 @@
+#![doc = include_str!("../docs/forwarded.md")]
+
+use crate::make::MakeRouteLayer;
+use http::header::{FORWARDED, HOST};
+use http::uri::Scheme;
+use http::{HeaderMap, HeaderName, HeaderValue, Request};
+use satex_core::component::{Args, Configurable};
+use satex_core::extension::{ClientAddr, ClientScheme, PeerAddr};
+use satex_core::util::Cidr;
+use satex_core::Error;
+use satex_macro::make;
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+pub const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+pub const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+pub const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+pub const X_FORWARDED_PORT: HeaderName = HeaderName::from_static("x-forwarded-port");
+
+const X_FORWARDED_HEADERS: [HeaderName; 4] = [
+    X_FORWARDED_FOR,
+    X_FORWARDED_PROTO,
+    X_FORWARDED_HOST,
+    X_FORWARDED_PORT,
+];
+
+///
+/// 设置的转发请求头
+///
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+pub enum Style {
+    ///
+    /// `X-Forwarded-For`、`X-Forwarded-Proto`、`X-Forwarded-Host`以及`X-Forwarded-Port`
+    ///
+    #[default]
+    XForwarded,
+
+    ///
+    /// RFC 7239 `Forwarded`
+    ///
+    Forwarded,
+
+    ///
+    /// 同时设置两种请求头
+    ///
+    Both,
+}
+
+///
+/// 处理客户端请求中已经存在的转发请求头的策略
+///
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+pub enum Policy {
+    ///
+    /// 删除已经存在的转发请求头
+    ///
+    #[default]
+    Strip,
+
+    ///
+    /// 保留已经存在的转发请求头, 并追加客户端地址
+    ///
+    Append,
+
+    ///
+    /// 客户端地址在`trusted-proxies`中时按照`Append`处理, 否则按照`Strip`处理
+    ///
+    Trust,
+}
+
+#[make(kind = Forwarded, shortcut_mode = TailingSequence)]
+struct MakeForwardedRouteLayer {
+    #[serde(default)]
+    policy: Policy,
+    #[serde(default)]
+    style: Style,
+    #[serde(default)]
+    trusted_proxies: Vec<String>,
+}
+
+impl MakeRouteLayer for MakeForwardedRouteLayer {
+    type Layer = ForwardedRouteLayer;
+
+    fn make(&self, args: Args) -> Result<Self::Layer, Error> {
+        let config = Config::with_args(args)?;
+        let trusted_proxies = config
+            .trusted_proxies
+            .iter()
+            .map(|cidr| Cidr::from_str(cidr))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ForwardedRouteLayer::new(
+            config.style,
+            config.policy,
+            trusted_proxies,
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ForwardedRouteLayer {
+    style: Style,
+    policy: Policy,
+    trusted_proxies: Arc<[Cidr]>,
+}
+
+impl ForwardedRouteLayer {
+    pub fn new(style: Style, policy: Policy, trusted_proxies: Vec<Cidr>) -> Self {
+        Self {
+            style,
+            policy,
+            trusted_proxies: trusted_proxies.into(),
+        }
+    }
+}
+
+impl<S> Layer<S> for ForwardedRouteLayer {
+    type Service = Forwarded<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Forwarded {
+            style: self.style,
+            policy: self.policy,
+            trusted_proxies: self.trusted_proxies.clone(),
+            inner,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Forwarded<S> {
+    style: Style,
+    policy: Policy,
+    trusted_proxies: Arc<[Cidr]>,
+    inner: S,
+}
+
+impl<S> Forwarded<S> {
+    ///
+    /// 是否保留客户端请求中已经存在的转发请求头
+    ///
+    fn trusted(&self, client_ip: Option<IpAddr>) -> bool {
+        match self.policy {
+            Policy::Strip => false,
+            Policy::Append => true,
+            Policy::Trust => client_ip.is_some_and(|client_ip| {
+                self.trusted_proxies
+                    .iter()
+                    .any(|cidr| cidr.contains(&client_ip))
+            }),
+        }
+    }
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for Forwarded<S>
+where
+    S: Service<Request<ReqBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        // 当前这一跳使用连接的对端地址, 不受真实客户端地址提取的影响
+        let client_ip = request
+            .extensions()
+            .get::<PeerAddr>()
+            .map(|peer_addr| peer_addr.ip())
+            .or_else(|| {
+                request
+                    .extensions()
+                    .get::<ClientAddr>()
+                    .map(|client_addr| client_addr.ip())
+            });
+        let proto = request
+            .extensions()
+            .get::<ClientScheme>()
+            .map(|scheme| (**scheme).clone())
+            .or_else(|| request.uri().scheme().cloned())
+            .unwrap_or(Scheme::HTTP);
+        let host = request
+            .headers()
+            .get(HOST)
+            .and_then(|host| host.to_str().ok())
+            .or_else(|| {
+                request
+                    .uri()
+                    .authority()
+                    .map(|authority| authority.as_str())
+            })
+            .map(|host| host.to_string());
+        let trusted = self.trusted(client_ip);
+
+        let headers = request.headers_mut();
+        if !trusted {
+            X_FORWARDED_HEADERS.iter().for_each(|header| {
+                headers.remove(header);
+            });
+            headers.remove(FORWARDED);
+        }
+        let forwarded = Forward {
+            client_ip,
+            proto,
+            host,
+        };
+        if matches!(self.style, Style::XForwarded | Style::Both) {
+            forwarded.x_forwarded(headers);
+        }
+        if matches!(self.style, Style::Forwarded | Style::Both) {
+            forwarded.forwarded(headers);
+        }
+        self.inner.call(request)
+    }
+}
+
+///
+/// 当前这一跳的转发信息
+///
+struct Forward {
+    client_ip: Option<IpAddr>,
+    proto: Scheme,
+    host: Option<String>,
+}
+
+impl Forward {
+    ///
+    /// 追加客户端地址到`X-Forwarded-For`, 其他请求头不存在时设置为当前请求的值
+    ///
+    fn x_forwarded(&self, headers: &mut HeaderMap) {
+        if let Some(client_ip) = self.client_ip {
+            let value = match headers
+                .get(X_FORWARDED_FOR)
+                .and_then(|value| value.to_str().ok())
+            {
+                Some(value) if !value.trim().is_empty() => format!("{}, {}", value, client_ip),
+                _ => client_ip.to_string(),
+            };
+            insert(headers, X_FORWARDED_FOR, &value);
+        }
+        if !headers.contains_key(X_FORWARDED_PROTO) {
+            insert(headers, X_FORWARDED_PROTO, self.proto.as_str());
+        }
+        if let Some(host) = self.host.as_deref() {
+            if !headers.contains_key(X_FORWARDED_HOST) {
+                insert(headers, X_FORWARDED_HOST, host);
+            }
+            if !headers.contains_key(X_FORWARDED_PORT) {
+                insert(headers, X_FORWARDED_PORT, &self.port(host).to_string());
+            }
+        }
+    }
+
+    ///
+    /// 追加当前这一跳的`Forwarded`元素
+    ///
+    fn forwarded(&self, headers: &mut HeaderMap) {
+        let mut pairs = Vec::with_capacity(3);
+        if let Some(client_ip) = self.client_ip {
+            pairs.push(match client_ip {
+                IpAddr::V4(ip) => format!("for={}", ip),
+                IpAddr::V6(ip) => format!("for=\"[{}]\"", ip),
+            });
+        }
+        if let Some(host) = self.host.as_deref() {
+            pairs.push(format!("host={}", quote(host)));
+        }
+        pairs.push(format!("proto={}", self.proto));
+        let element = pairs.join(";");
+        let value = match headers.get(FORWARDED).and_then(|value| value.to_str().ok()) {
+            Some(value) if !value.trim().is_empty() => format!("{}, {}", value, element),
+            _ => element,
+        };
+        insert(headers, FORWARDED, &value);
+    }
+
+    ///
+    /// 客户端请求的端口, `Host`请求头不包含端口时使用协议的默认端口
+    ///
+    fn port(&self, host: &str) -> u16 {
+        host.rsplit_once(':')
+            .filter(|(_, port)| !port.ends_with(']'))
+            .and_then(|(_, port)| port.parse().ok())
+            .unwrap_or(if self.proto == Scheme::HTTPS { 443 } else { 80 })
+    }
+}
+
+fn insert(headers: &mut HeaderMap, name: HeaderName, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(name, value);
+    }
+}
+
+///
+/// 包含`token`以外的字符时使用引号
+///
+fn quote(value: &str) -> String {
+    let token = value
+        .bytes()
+        .all(|c| c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c));
+    if token {
+        value.to_string()
+    } else {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
@@ -10,8 +10,10 @@ use std::sync::Arc;
 use tower::layer::layer_fn;
 use tower::{Layer, Service};
 
+pub mod access_log;
 pub mod concurrency_limit;
 pub mod cors;
+pub mod forwarded;
 pub mod make;
 pub mod remove_header;
 pub mod set_header;
@@ -0,0 +1,169 @@
+use http::{Request, Response};
+use http_body_util::BodyExt;
+use satex_core::body::Body;
+use satex_core::component::Args;
+use satex_core::extension::{RouteId, UpstreamAddr, UpstreamLatency};
+use satex_layer::access_log::{
+    AccessLogLayer, AccessLogWriter, Interval, MakeAccessLogRouteLayer, RollingFile, Rotation,
+    X_REQUEST_ID,
+};
+use satex_layer::make::MakeRouteLayer;
+use serde_json::Value;
+use std::convert::Infallible;
+use std::fs::{read_dir, read_to_string, remove_dir_all};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::time::sleep;
+use tower::{service_fn, Layer, Service};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("satex-{}-{}", name, std::process::id()));
+    let _ = remove_dir_all(&dir);
+    dir
+}
+
+async fn read_lines(path: &PathBuf) -> Vec<String> {
+    for _ in 0..100 {
+        if let Ok(content) = read_to_string(path)
+            && !content.is_empty()
+        {
+            return content.lines().map(String::from).collect();
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+    panic!("access log not written: {:?}", path);
+}
+
+#[tokio::test]
+async fn write_json_line() {
+    let dir = temp_dir("access-log-json");
+    let path = dir.join("access.log");
+    let writer = AccessLogWriter::file(&path, Rotation::default()).unwrap();
+    let layer = AccessLogLayer::new(serde_yaml::from_str("Json").unwrap(), writer);
+
+    let mut service = layer.layer(service_fn(|request: Request<Body>| async move {
+        assert!(request.headers().contains_key(X_REQUEST_ID));
+        let bytes = request.into_body().collect().await.unwrap().to_bytes();
+        let mut response = Response::new(Body::from(bytes));
+        response
+            .extensions_mut()
+            .insert(UpstreamAddr::new("127.0.0.1:8080"));
+        response
+            .extensions_mut()
+            .insert(UpstreamLatency::new(Duration::from_millis(5)));
+        Ok::<_, Infallible>(response)
+    }));
+
+    let mut request = Request::post("/echo?name=satex")
+        .body(Body::from("hello"))
+        .unwrap();
+    request.extensions_mut().insert(RouteId::new("echo"));
+    let response = service.call(request).await.unwrap();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(bytes, "hello");
+
+    let lines = read_lines(&path).await;
+    assert_eq!(lines.len(), 1);
+    let record = serde_json::from_str::<Value>(&lines[0]).unwrap();
+    assert_eq!(record["method"], "POST");
+    assert_eq!(record["uri"], "/echo?name=satex");
+    assert_eq!(record["route"], "echo");
+    assert_eq!(record["status"], 200);
+    assert_eq!(record["bytes_in"], 5);
+    assert_eq!(record["bytes_out"], 5);
+    assert_eq!(record["upstream_addr"], "127.0.0.1:8080");
+    assert_eq!(record["upstream_latency"], 0.005);
+    assert_eq!(record["request_id"].as_str().unwrap().len(), 32);
+    remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn write_combined_line() {
+    let dir = temp_dir("access-log-combined");
+    let path = dir.join("access.log");
+    let yaml = format!("path: {}", path.display());
+    let value = serde_yaml::from_str::<serde_yaml::Value>(&yaml).unwrap();
+    let layer = MakeAccessLogRouteLayer.make(Args::full(&value)).unwrap();
+
+    let mut service = layer.layer(service_fn(|_: Request<Body>| async move {
+        Ok::<_, Infallible>(Response::new(Body::from("ok")))
+    }));
+    let request = Request::get("/")
+        .header(X_REQUEST_ID, "request-1")
+        .header("user-agent", "curl \"8\"")
+        .body(Body::empty())
+        .unwrap();
+    let response = service.call(request).await.unwrap();
+    drop(response);
+
+    let lines = read_lines(&path).await;
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].starts_with("- - - ["));
+    assert!(lines[0].contains(r#""GET / HTTP/1.1" 200 0 "-" "curl \"8\"" 0 "#));
+    assert!(lines[0].ends_with(r#" "-" - "-" "request-1""#));
+    remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn make_with_shortcut() {
+    assert!(MakeAccessLogRouteLayer.make(Args::shortcut("Json")).is_ok());
+    assert!(MakeAccessLogRouteLayer.make(Args::shortcut("Unknown")).is_err());
+}
+
+#[test]
+fn rotate_by_size() {
+    let dir = temp_dir("access-log-rotation");
+    let path = dir.join("access.log");
+    let rotation = Rotation {
+        max_size: Some(10),
+        interval: Some(Interval::Daily),
+        max_files: Some(2),
+    };
+    let mut file = RollingFile::new(&path, rotation).unwrap();
+    for _ in 0..5 {
+        file.write_all(b"0123456789\n").unwrap();
+    }
+    file.flush().unwrap();
+
+    assert_eq!(read_to_string(&path).unwrap(), "0123456789\n");
+    let histories = read_dir(&dir)
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path() != path)
+        .count();
+    assert_eq!(histories, 2);
+    remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn flush_when_released() {
+    let dir = temp_dir("access-log-release");
+    let path = dir.join("access.log");
+    let writer = AccessLogWriter::file(&path, Rotation::default()).unwrap();
+    let shared = AccessLogWriter::file(&path, Rotation::default()).unwrap();
+    writer.write_line("first".to_string());
+    shared.write_line("second".to_string());
+
+    // 所有的输出释放后写入剩余的日志
+    drop(writer);
+    drop(shared);
+    assert_eq!(read_to_string(&path).unwrap(), "first\nsecond\n");
+
+    // 轮转配置不同时创建新的写入线程
+    let writer = AccessLogWriter::file(&path, Rotation::default()).unwrap();
+    let rotated = AccessLogWriter::file(
+        &path,
+        Rotation {
+            max_size: Some(1),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    writer.write_line("third".to_string());
+    drop(writer);
+    rotated.write_line("fourth".to_string());
+    drop(rotated);
+    assert_eq!(read_to_string(&path).unwrap(), "fourth\n");
+    remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,180 @@
+mod identify;
+
+use crate::identify::Identify;
+use http::uri::Scheme;
+use http::{HeaderMap, Request};
+use satex_core::body::Body;
+use satex_core::component::Args;
+use satex_core::extension::{ClientAddr, ClientScheme, PeerAddr};
+use satex_layer::forwarded::{ForwardedRouteLayer, MakeForwardedRouteLayer};
+use satex_layer::make::MakeRouteLayer;
+use std::cell::RefCell;
+use std::net::SocketAddr;
+use tower::{Layer, Service};
+
+///
+/// 转发请求, 返回上游收到的请求头
+///
+async fn forward(
+    layer: &ForwardedRouteLayer,
+    client_addr: &str,
+    headers: &[(&'static str, &str)],
+) -> HeaderMap {
+    let mut request = Request::builder()
+        .uri("/api")
+        .header("host", "example.com:8443")
+        .body(Body::empty())
+        .unwrap();
+    for (name, value) in headers {
+        request.headers_mut().insert(*name, value.parse().unwrap());
+    }
+    let extensions = request.extensions_mut();
+    extensions.insert(ClientAddr::new(client_addr.parse::<SocketAddr>().unwrap()));
+    extensions.insert(ClientScheme::new(Scheme::HTTPS));
+
+    let received = RefCell::new(HeaderMap::new());
+    let mut service = layer.layer(Identify::new(|request: Request<Body>| {
+        *received.borrow_mut() = request.headers().clone();
+        true
+    }));
+    assert!(service.call(request).await.is_ok());
+    received.take()
+}
+
+fn get<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).map(|value| value.to_str().unwrap())
+}
+
+#[tokio::test]
+async fn strip() {
+    let value = serde_yaml::from_str("policy: Strip").unwrap();
+    let layer = MakeForwardedRouteLayer.make(Args::full(&value)).unwrap();
+    let headers = forward(
+        &layer,
+        "192.168.1.10:50000",
+        &[
+            ("x-forwarded-for", "1.1.1.1"),
+            ("x-forwarded-proto", "http"),
+        ],
+    )
+    .await;
+    assert_eq!(get(&headers, "x-forwarded-for"), Some("192.168.1.10"));
+    assert_eq!(get(&headers, "x-forwarded-proto"), Some("https"));
+    assert_eq!(get(&headers, "x-forwarded-host"), Some("example.com:8443"));
+    assert_eq!(get(&headers, "x-forwarded-port"), Some("8443"));
+    assert_eq!(get(&headers, "forwarded"), None);
+}
+
+#[tokio::test]
+async fn append() {
+    let value = serde_yaml::from_str("policy: Append\nstyle: Both").unwrap();
+    let layer = MakeForwardedRouteLayer.make(Args::full(&value)).unwrap();
+    let headers = forward(
+        &layer,
+        "[::1]:50000",
+        &[
+            ("x-forwarded-for", "1.1.1.1"),
+            ("x-forwarded-proto", "http"),
+            ("forwarded", "for=1.1.1.1"),
+        ],
+    )
+    .await;
+    assert_eq!(get(&headers, "x-forwarded-for"), Some("1.1.1.1, ::1"));
+    assert_eq!(get(&headers, "x-forwarded-proto"), Some("http"));
+    assert_eq!(
+        get(&headers, "forwarded"),
+        Some("for=1.1.1.1, for=\"[::1]\";host=\"example.com:8443\";proto=https")
+    );
+}
+
+#[tokio::test]
+async fn trust() {
+    let yaml = r#"
+        policy: Trust
+        style: Forwarded
+        trusted-proxies:
+          - 10.0.0.0/8
+          - 192.168.1.1
+    "#;
+    let value = serde_yaml::from_str(yaml).unwrap();
+    let layer = MakeForwardedRouteLayer.make(Args::full(&value)).unwrap();
+    let incoming = [("forwarded", "for=1.1.1.1"), ("x-forwarded-for", "1.1.1.1")];
+
+    // 可信代理转发的请求保留已经存在的请求头
+    let headers = forward(&layer, "10.1.2.3:50000", &incoming).await;
+    assert_eq!(
+        get(&headers, "forwarded"),
+        Some("for=1.1.1.1, for=10.1.2.3;host=\"example.com:8443\";proto=https")
+    );
+    assert_eq!(get(&headers, "x-forwarded-for"), Some("1.1.1.1"));
+
+    // 不可信的客户端删除已经存在的请求头
+    let headers = forward(&layer, "192.168.1.2:50000", &incoming).await;
+    assert_eq!(
+        get(&headers, "forwarded"),
+        Some("for=192.168.1.2;host=\"example.com:8443\";proto=https")
+    );
+    assert_eq!(get(&headers, "x-forwarded-for"), None);
+}
+
+#[tokio::test]
+async fn peer_addr() {
+    let layer = MakeForwardedRouteLayer
+        .make(Args::shortcut("Trust,XForwarded,10.0.0.0/8"))
+        .unwrap();
+    let mut request = Request::builder()
+        .uri("/api")
+        .header("x-forwarded-for", "1.1.1.1")
+        .body(Body::empty())
+        .unwrap();
+    // 提取真实客户端地址后, 当前这一跳仍然使用连接的对端地址
+    let extensions = request.extensions_mut();
+    extensions.insert(ClientAddr::new("1.1.1.1:0".parse::<SocketAddr>().unwrap()));
+    extensions.insert(PeerAddr::new(
+        "10.1.2.3:50000".parse::<SocketAddr>().unwrap(),
+    ));
+
+    let received = RefCell::new(HeaderMap::new());
+    let mut service = layer.layer(Identify::new(|request: Request<Body>| {
+        *received.borrow_mut() = request.headers().clone();
+        true
+    }));
+    assert!(service.call(request).await.is_ok());
+    assert_eq!(
+        get(&received.take(), "x-forwarded-for"),
+        Some("1.1.1.1, 10.1.2.3")
+    );
+}
+
+#[tokio::test]
+async fn invalid_trusted_proxies() {
+    assert!(MakeForwardedRouteLayer
+        .make(Args::shortcut("Trust,XForwarded,10.0.0.0/33"))
+        .is_err());
+    assert!(MakeForwardedRouteLayer
+        .make(Args::shortcut("Trust,XForwarded,example.com"))
+        .is_err());
+}
+
+#[tokio::test]
+async fn shortcut() {
+    let layer = MakeForwardedRouteLayer
+        .make(Args::shortcut("Trust, Forwarded, 10.0.0.0/8, 192.168.1.1"))
+        .unwrap();
+    let incoming = [("forwarded", "for=1.1.1.1")];
+
+    // 最后一个值之后的所有值都是可信代理
+    let headers = forward(&layer, "192.168.1.1:50000", &incoming).await;
+    assert_eq!(
+        get(&headers, "forwarded"),
+        Some("for=1.1.1.1, for=192.168.1.1;host=\"example.com:8443\";proto=https")
+    );
+
+    // 快捷模式需要设置所有的参数
+    assert!(MakeForwardedRouteLayer
+        .make(Args::shortcut("Strip"))
+        .is_err());
+    assert!(MakeForwardedRouteLayer
+        .make(Args::shortcut("Append,Both"))
+        .is_err());
+}
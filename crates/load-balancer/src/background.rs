@@ -26,7 +26,9 @@ impl BackgroundTask for LoadBalancer {
                     .run_health_check(self.health_check_parallel)
                     .await;
                 next_health_check = now + self.health_check_frequency.unwrap_or(NEVER);
+                self.warm_up(Instant::now());
             }
+            self.observe_health();
 
             if self.update_frequency.is_none() && self.health_check_frequency.is_none() {
                 return;
@@ -0,0 +1,357 @@
+//!
+//! 熔断器
+//!
+use crate::Backend;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+///
+/// 熔断器, 限制发送到每个后端的请求数、连接数以及等待连接的请求数, 后端连续失败时熔断
+///
+/// 连续失败`consecutive_failures`次后熔断器打开, `open_duration`时间内拒绝发送到该后端的请求;
+/// 之后进入半开状态, 最多同时放行`half_open_requests`个试探请求, 试探成功时关闭, 失败时重新打开。
+///
+/// 代理的连接器通过[`CircuitBreaker::connect`]占用到后端的连接, 连接关闭时归还; 连接数达到`max_connections`时,
+/// 新的连接排队等待其他连接关闭, 排队的请求数超过`max_pending`或者没有设置`max_pending`时拒绝。
+///
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    max_requests: Option<usize>,
+    max_pending: Option<usize>,
+    max_connections: Option<usize>,
+    consecutive_failures: Option<usize>,
+    open_duration: Duration,
+    half_open_requests: usize,
+    states: Mutex<HashMap<u64, Arc<State>>>,
+    connections: Mutex<HashMap<SocketAddr, Arc<Connections>>>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            max_requests: None,
+            max_pending: None,
+            max_connections: None,
+            consecutive_failures: Some(5),
+            open_duration: Duration::from_secs(30),
+            half_open_requests: 1,
+            states: Mutex::new(HashMap::new()),
+            connections: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl CircuitBreaker {
+    /// 设置发送到每个后端的最大请求数(包含排队的请求), `None`表示不限制
+    pub fn with_max_requests(mut self, max_requests: Option<usize>) -> Self {
+        self.max_requests = max_requests;
+        self
+    }
+
+    /// 设置连接数达到上限时每个后端排队等待连接的最大请求数, `None`表示不排队
+    pub fn with_max_pending(mut self, max_pending: Option<usize>) -> Self {
+        self.max_pending = max_pending;
+        self
+    }
+
+    /// 设置每个后端的最大连接数, 包含连接池中空闲的连接, `None`表示不限制
+    pub fn with_max_connections(mut self, max_connections: Option<usize>) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+
+    /// 设置连续失败次数的阈值, `None`表示不熔断
+    pub fn with_consecutive_failures(mut self, consecutive_failures: Option<usize>) -> Self {
+        self.consecutive_failures = consecutive_failures;
+        self
+    }
+
+    /// 设置熔断器打开的时长
+    pub fn with_open_duration(mut self, open_duration: Duration) -> Self {
+        self.open_duration = open_duration;
+        self
+    }
+
+    /// 设置半开状态时最多同时放行的试探请求数
+    pub fn with_half_open_requests(mut self, half_open_requests: usize) -> Self {
+        self.half_open_requests = half_open_requests;
+        self
+    }
+
+    ///
+    /// 检查是否允许发送请求到后端, 不占用请求数, 用于选择后端时跳过会被拒绝的后端,
+    /// 请求可能复用连接池中空闲的连接, 所以不检查连接数
+    ///
+    pub fn check(&self, backend: &Backend) -> Result<(), Rejection> {
+        let Some(state) = self.states.lock().unwrap().get(&backend.key()).cloned() else {
+            return Ok(());
+        };
+        match *state.status.lock().unwrap() {
+            Status::Open { until } if Instant::now() < until => return Err(Rejection::Open),
+            Status::HalfOpen { trials } if trials >= self.half_open_requests.max(1) => {
+                return Err(Rejection::Open);
+            }
+            _ => {}
+        }
+        if self
+            .max_requests
+            .is_some_and(|max| state.requests.load(Ordering::Acquire) >= max)
+        {
+            return Err(Rejection::MaxRequests);
+        }
+        Ok(())
+    }
+
+    ///
+    /// 占用发送到后端的请求数, 返回的 [Permit] 释放时归还
+    ///
+    pub async fn acquire(&self, backend: &Backend) -> Result<Permit, Rejection> {
+        let state = self.state(backend);
+        let requests = state.requests.fetch_add(1, Ordering::AcqRel);
+        let mut permit = Permit {
+            state: state.clone(),
+            trial: false,
+        };
+        if self.max_requests.is_some_and(|max| requests >= max) {
+            return Err(Rejection::MaxRequests);
+        }
+        permit.trial = self.enter(&state)?;
+        Ok(permit)
+    }
+
+    ///
+    /// 占用一个到后端`addr`的连接, 连接数达到上限时排队等待其他连接关闭, 返回的 [ConnectionPermit] 释放时归还
+    ///
+    pub async fn connect(&self, addr: SocketAddr) -> Result<ConnectionPermit, Rejection> {
+        let Some(max) = self.max_connections else {
+            return Ok(ConnectionPermit { _permit: None });
+        };
+        let connections = self
+            .connections
+            .lock()
+            .unwrap()
+            .entry(addr)
+            .or_insert_with(|| {
+                Arc::new(Connections {
+                    semaphore: Arc::new(Semaphore::new(max)),
+                    pending: AtomicUsize::new(0),
+                })
+            })
+            .clone();
+        if let Ok(permit) = connections.semaphore.clone().try_acquire_owned() {
+            return Ok(ConnectionPermit {
+                _permit: Some(permit),
+            });
+        }
+        let Some(max_pending) = self.max_pending else {
+            return Err(Rejection::MaxConnections);
+        };
+        let pending = Pending::new(&connections.pending);
+        if pending.0 >= max_pending {
+            return Err(Rejection::MaxPending);
+        }
+        connections
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .map(|permit| ConnectionPermit {
+                _permit: Some(permit),
+            })
+            .map_err(|_| Rejection::MaxConnections)
+    }
+
+    ///
+    /// 记录请求结果, 返回熔断器状态的变化: `Some(true)`表示打开, `Some(false)`表示关闭
+    ///
+    pub(crate) fn record(&self, backend: &Backend, success: bool) -> Option<bool> {
+        let threshold = self.consecutive_failures?;
+        let state = self.states.lock().unwrap().get(&backend.key()).cloned()?;
+        let mut status = state.status.lock().unwrap();
+        let open = Status::Open {
+            until: Instant::now() + self.open_duration,
+        };
+        match (&mut *status, success) {
+            (Status::Closed { failures }, true) => {
+                *failures = 0;
+                None
+            }
+            (Status::Closed { failures }, false) => {
+                *failures += 1;
+                if *failures < threshold.max(1) {
+                    return None;
+                }
+                *status = open;
+                Some(true)
+            }
+            (Status::HalfOpen { .. }, true) => {
+                *status = Status::Closed { failures: 0 };
+                Some(false)
+            }
+            (Status::HalfOpen { .. }, false) => {
+                *status = open;
+                Some(true)
+            }
+            (Status::Open { .. }, _) => None,
+        }
+    }
+
+    ///
+    /// 删除已经下线的后端的状态
+    ///
+    pub(crate) fn retain(&self, backends: &BTreeSet<Backend>) {
+        let keys = backends.iter().map(Backend::key).collect::<HashSet<_>>();
+        self.states
+            .lock()
+            .unwrap()
+            .retain(|key, _| keys.contains(key));
+        let addrs = backends
+            .iter()
+            .map(|backend| backend.addr)
+            .collect::<HashSet<_>>();
+        self.connections
+            .lock()
+            .unwrap()
+            .retain(|addr, _| addrs.contains(addr));
+    }
+
+    fn state(&self, backend: &Backend) -> Arc<State> {
+        self.states
+            .lock()
+            .unwrap()
+            .entry(backend.key())
+            .or_insert_with(|| {
+                Arc::new(State {
+                    status: Mutex::new(Status::Closed { failures: 0 }),
+                    requests: AtomicUsize::new(0),
+                })
+            })
+            .clone()
+    }
+
+    ///
+    /// 打开的熔断器到期后进入半开状态, 返回请求是否为试探请求
+    ///
+    fn enter(&self, state: &State) -> Result<bool, Rejection> {
+        let mut status = state.status.lock().unwrap();
+        match &mut *status {
+            Status::Closed { .. } => Ok(false),
+            Status::Open { until } if Instant::now() < *until => Err(Rejection::Open),
+            Status::Open { .. } => {
+                *status = Status::HalfOpen { trials: 1 };
+                Ok(true)
+            }
+            Status::HalfOpen { trials } if *trials < self.half_open_requests.max(1) => {
+                *trials += 1;
+                Ok(true)
+            }
+            Status::HalfOpen { .. } => Err(Rejection::Open),
+        }
+    }
+}
+
+///
+/// 请求被熔断器拒绝的原因
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    /// 熔断器打开, 或者半开状态的试探请求已满
+    Open,
+    /// 发送到后端的请求数达到上限
+    MaxRequests,
+    /// 排队等待连接的请求数达到上限
+    MaxPending,
+    /// 连接数达到上限, 并且不允许排队等待连接
+    MaxConnections,
+}
+
+impl Rejection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rejection::Open => "open",
+            Rejection::MaxRequests => "max-requests",
+            Rejection::MaxPending => "max-pending",
+            Rejection::MaxConnections => "max-connections",
+        }
+    }
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+///
+/// 熔断器允许的请求, 释放时归还占用的请求数
+///
+#[derive(Debug)]
+pub struct Permit {
+    state: Arc<State>,
+    trial: bool,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.state.requests.fetch_sub(1, Ordering::AcqRel);
+        if self.trial
+            && let Status::HalfOpen { trials } = &mut *self.state.status.lock().unwrap()
+        {
+            *trials = trials.saturating_sub(1);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    status: Mutex<Status>,
+    requests: AtomicUsize,
+}
+
+///
+/// 到一个后端地址的连接数以及排队等待连接的请求数
+///
+#[derive(Debug)]
+struct Connections {
+    semaphore: Arc<Semaphore>,
+    pending: AtomicUsize,
+}
+
+///
+/// 熔断器允许的连接, 连接关闭时释放, 归还占用的连接数
+///
+#[derive(Debug)]
+pub struct ConnectionPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+#[derive(Debug)]
+enum Status {
+    Closed { failures: usize },
+    Open { until: Instant },
+    HalfOpen { trials: usize },
+}
+
+///
+/// 排队等待连接的请求, 释放时减少排队的请求数
+///
+struct Pending<'a>(usize, &'a AtomicUsize);
+
+impl<'a> Pending<'a> {
+    fn new(pending: &'a AtomicUsize) -> Self {
+        Self(pending.fetch_add(1, Ordering::AcqRel), pending)
+    }
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        self.1.fetch_sub(1, Ordering::AcqRel);
+    }
+}
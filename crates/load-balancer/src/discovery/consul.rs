@@ -0,0 +1,313 @@
+use crate::discovery::Discovery;
+use crate::{Backend, Labels};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{Request, StatusCode};
+use http_body_util::{BodyExt, Empty};
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use satex_core::Error;
+use satex_core::background::BackgroundTask;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::sync::watch::{Receiver, Sender, channel};
+use tokio::time::{sleep, timeout};
+use tracing::{debug, warn};
+use url::Url;
+
+const X_CONSUL_INDEX: &str = "X-Consul-Index";
+const X_CONSUL_TOKEN: &str = "X-Consul-Token";
+
+///
+/// 查询没有阻塞时, 再次查询之前等待的时间
+///
+const NON_BLOCKING_INTERVAL: Duration = Duration::from_secs(1);
+
+///
+/// 服务实例信息, 保存在[`Backend::extension`]中
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServiceInstance {
+    pub id: String,
+    pub node: String,
+    pub tags: Vec<String>,
+    pub meta: HashMap<String, String>,
+    pub weight: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ServiceEntry {
+    node: Node,
+    service: Service,
+    #[serde(default)]
+    checks: Vec<Check>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Node {
+    node: String,
+    address: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Service {
+    #[serde(rename = "ID")]
+    id: String,
+    #[serde(default)]
+    address: String,
+    port: u16,
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+    #[serde(default)]
+    meta: Option<HashMap<String, String>>,
+    #[serde(default)]
+    weights: Option<Weights>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Weights {
+    passing: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Check {
+    status: String,
+}
+
+impl ServiceEntry {
+    fn passing(&self) -> bool {
+        self.checks.iter().all(|check| check.status == "passing")
+    }
+
+    fn into_backend(self) -> Result<Backend, Error> {
+        // 服务地址为空时使用节点地址
+        let address = if self.service.address.is_empty() {
+            &self.node.address
+        } else {
+            &self.service.address
+        };
+        let ip = address.parse::<IpAddr>().map_err(|e| {
+            Error::new(format!(
+                "instance `{}` with invalid address `{}`: {}",
+                self.service.id, address, e
+            ))
+        })?;
+        let weight = self
+            .service
+            .weights
+            .map(|weights| weights.passing)
+            .unwrap_or(1)
+            .max(1);
+        // 服务实例的元数据作为后端的标签
+        let meta = self.service.meta.unwrap_or_default();
+        let labels = meta
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect::<Labels>();
+        let mut backend = Backend::new_with_weight(SocketAddr::new(ip, self.service.port), weight)
+            .with_labels(labels);
+        backend.extension.insert(ServiceInstance {
+            id: self.service.id,
+            node: self.node.node,
+            tags: self.service.tags.unwrap_or_default(),
+            meta,
+            weight,
+        });
+        Ok(backend)
+    }
+}
+
+///
+/// 通过阻塞查询持续监听`Consul`风格的服务目录
+///
+/// 每次请求携带上一次响应的`X-Consul-Index`, 服务目录在数据变化或者等待超时后才会返回,
+/// 最新的结果通过[`ConsulDiscovery`]提供给负载均衡器。
+///
+pub struct ConsulWatch {
+    client: Client<HttpConnector, Empty<Bytes>>,
+    address: String,
+    service: String,
+    datacenter: Option<String>,
+    token: Option<String>,
+    wait: Duration,
+    sender: Sender<Option<BTreeSet<Backend>>>,
+}
+
+impl ConsulWatch {
+    ///
+    /// 创建监听实例以及对应的服务发现
+    ///
+    /// # Arguments
+    ///
+    /// * `address`: 服务目录地址, 例如: `http://127.0.0.1:8500`
+    /// * `service`: 服务名称
+    ///
+    pub fn new(address: impl Into<String>, service: impl Into<String>) -> (Self, ConsulDiscovery) {
+        let (sender, receiver) = channel(None);
+        let watch = Self {
+            client: Client::builder(TokioExecutor::new()).build_http(),
+            address: address.into().trim_end_matches('/').to_string(),
+            service: service.into(),
+            datacenter: None,
+            token: None,
+            wait: Duration::from_secs(30),
+            sender,
+        };
+        (watch, ConsulDiscovery { receiver })
+    }
+
+    /// 设置数据中心
+    pub fn with_datacenter(mut self, datacenter: impl Into<String>) -> Self {
+        self.datacenter = Some(datacenter.into());
+        self
+    }
+
+    /// 设置访问令牌
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// 设置阻塞查询的最长等待时间
+    pub fn with_wait(mut self, wait: Duration) -> Self {
+        self.wait = wait;
+        self
+    }
+
+    ///
+    /// 查询服务健康实例的地址, 服务名称以及查询参数都会进行编码
+    ///
+    fn uri(&self, index: u64) -> Result<String, Error> {
+        let mut url = Url::parse(&self.address).map_err(Error::new)?;
+        url.path_segments_mut()
+            .map_err(|_| Error::new(format!("invalid catalog address `{}`", self.address)))?
+            .pop_if_empty()
+            .extend(["v1", "health", "service", &self.service]);
+        {
+            let mut query = url.query_pairs_mut();
+            query
+                .append_pair("passing", "true")
+                .append_pair("index", &index.to_string())
+                .append_pair("wait", &format!("{}s", self.wait.as_secs().max(1)));
+            if let Some(datacenter) = &self.datacenter {
+                query.append_pair("dc", datacenter);
+            }
+        }
+        Ok(url.into())
+    }
+
+    async fn fetch(&self, index: u64) -> Result<(u64, BTreeSet<Backend>), Error> {
+        let mut builder = Request::get(self.uri(index)?);
+        if let Some(token) = &self.token {
+            builder = builder.header(X_CONSUL_TOKEN, token);
+        }
+        let request = builder.body(Empty::new()).map_err(Error::new)?;
+
+        // 服务端会在等待时间的基础上增加最多`wait / 16`的随机抖动
+        let deadline = self.wait + self.wait / 16 + Duration::from_secs(5);
+        let response = timeout(deadline, self.client.request(request))
+            .await
+            .map_err(|_| Error::new("catalog request timeout"))?
+            .map_err(Error::new)?;
+        if response.status() != StatusCode::OK {
+            return Err(Error::new(format!(
+                "catalog responded with {}",
+                response.status()
+            )));
+        }
+        let index = response
+            .headers()
+            .get(X_CONSUL_INDEX)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+            .unwrap_or_default();
+        let body = response
+            .into_body()
+            .collect()
+            .await
+            .map_err(Error::new)?
+            .to_bytes();
+        let entries = serde_json::from_slice::<Vec<ServiceEntry>>(&body).map_err(Error::new)?;
+        // 跳过地址不是IP的实例(例如: 使用主机名注册的实例), 不影响其他实例
+        let backends = entries
+            .into_iter()
+            .filter(ServiceEntry::passing)
+            .filter_map(|entry| {
+                entry
+                    .into_backend()
+                    .inspect_err(|e| warn!("skip service `{}` {}", self.service, e))
+                    .ok()
+            })
+            .collect();
+        Ok((index, backends))
+    }
+}
+
+#[async_trait]
+impl BackgroundTask for ConsulWatch {
+    async fn run(&self) {
+        let mut index = 0;
+        let mut backoff = Duration::from_secs(1);
+        // 所有的负载均衡器都释放之后停止监听
+        while !self.sender.is_closed() {
+            match self.fetch(index).await {
+                Ok((new_index, backends)) => {
+                    debug!(
+                        "service `{}` index {} with {} backends",
+                        self.service,
+                        new_index,
+                        backends.len()
+                    );
+                    backoff = Duration::from_secs(1);
+                    self.sender.send_replace(Some(backends));
+                    if new_index > index {
+                        index = new_index;
+                        continue;
+                    }
+                    // 索引为`0`、缺失或者没有变化时查询可能没有阻塞, 等待一段时间再查询, 避免频繁请求服务目录;
+                    // 索引回退时需要重新开始阻塞查询
+                    if new_index < index {
+                        index = 0;
+                    }
+                    sleep(NON_BLOCKING_INTERVAL).await;
+                }
+                Err(e) => {
+                    warn!("watch service `{}` error: {}", self.service, e);
+                    index = 0;
+                    sleep(backoff).await;
+                    backoff = (backoff * 2).min(self.wait);
+                }
+            }
+        }
+    }
+}
+
+///
+/// 基于`Consul`风格服务目录的服务发现, 返回[`ConsulWatch`]最近一次查询到的健康实例
+///
+pub struct ConsulDiscovery {
+    receiver: Receiver<Option<BTreeSet<Backend>>>,
+}
+
+#[async_trait]
+impl Discovery for ConsulDiscovery {
+    async fn discover(&self) -> Result<(BTreeSet<Backend>, HashMap<u64, bool>), Error> {
+        let mut receiver = self.receiver.clone();
+        // 等待第一次查询完成
+        let backends = timeout(Duration::from_secs(5), receiver.wait_for(Option::is_some))
+            .await
+            .map_err(|_| Error::new("waiting for the first catalog response timeout"))?
+            .map_err(Error::new)?
+            .clone()
+            .unwrap_or_default();
+        Ok((backends, HashMap::new()))
+    }
+}
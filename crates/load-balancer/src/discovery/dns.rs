@@ -0,0 +1,264 @@
+use crate::discovery::Discovery;
+use crate::{Backend, Labels};
+use async_trait::async_trait;
+use hickory_resolver::TokioResolver;
+use hickory_resolver::config::{NameServerConfig, NameServerConfigGroup, ResolverConfig};
+use hickory_resolver::name_server::TokioConnectionProvider;
+use hickory_resolver::proto::xfer::Protocol;
+use satex_core::Error;
+use std::collections::{BTreeSet, HashMap};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+///
+/// 一次DNS查询的结果
+///
+#[derive(Debug, Clone)]
+pub struct Lookup<T> {
+    ///
+    /// 查询到的记录
+    ///
+    pub records: Vec<T>,
+
+    ///
+    /// 记录的剩余有效期, 无法获取时为`None`
+    ///
+    pub ttl: Option<Duration>,
+}
+
+///
+/// `SRV`记录
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrvRecord {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub target: String,
+}
+
+///
+/// DNS查询接口, 默认实现为[`HickoryDnsLookup`]
+///
+#[async_trait]
+pub trait DnsLookup {
+    ///
+    /// 查询主机名的`A`以及`AAAA`记录
+    ///
+    async fn lookup_ip(&self, host: &str) -> Result<Lookup<IpAddr>, Error>;
+
+    ///
+    /// 查询`SRV`记录
+    ///
+    async fn lookup_srv(&self, name: &str) -> Result<Lookup<SrvRecord>, Error>;
+}
+
+///
+/// 基于`hickory-resolver`的DNS查询
+///
+#[derive(Clone)]
+pub struct HickoryDnsLookup(TokioResolver);
+
+impl HickoryDnsLookup {
+    ///
+    /// 使用系统的DNS配置, 例如: `/etc/resolv.conf`
+    ///
+    pub fn system() -> Result<Self, Error> {
+        TokioResolver::builder_tokio()
+            .map(|builder| Self(builder.build()))
+            .map_err(Error::new)
+    }
+
+    ///
+    /// 使用指定的DNS服务器, 通过`UDP`查询
+    ///
+    pub fn with_name_servers(name_servers: &[SocketAddr]) -> Self {
+        let group = name_servers
+            .iter()
+            .map(|addr| NameServerConfig::new(*addr, Protocol::Udp))
+            .collect::<Vec<_>>();
+        let config = ResolverConfig::from_parts(None, vec![], NameServerConfigGroup::from(group));
+        Self(TokioResolver::builder_with_config(config, TokioConnectionProvider::default()).build())
+    }
+}
+
+#[async_trait]
+impl DnsLookup for HickoryDnsLookup {
+    async fn lookup_ip(&self, host: &str) -> Result<Lookup<IpAddr>, Error> {
+        let lookup = self.0.lookup_ip(host).await.map_err(Error::new)?;
+        Ok(Lookup {
+            records: lookup.iter().collect(),
+            ttl: Some(
+                lookup
+                    .valid_until()
+                    .saturating_duration_since(Instant::now()),
+            ),
+        })
+    }
+
+    async fn lookup_srv(&self, name: &str) -> Result<Lookup<SrvRecord>, Error> {
+        let lookup = self.0.srv_lookup(name).await.map_err(Error::new)?;
+        Ok(Lookup {
+            records: lookup
+                .iter()
+                .map(|srv| SrvRecord {
+                    priority: srv.priority(),
+                    weight: srv.weight(),
+                    port: srv.port(),
+                    target: srv.target().to_utf8(),
+                })
+                .collect(),
+            ttl: Some(
+                lookup
+                    .as_lookup()
+                    .valid_until()
+                    .saturating_duration_since(Instant::now()),
+            ),
+        })
+    }
+}
+
+///
+/// DNS查询的目标
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DnsQuery {
+    ///
+    /// 查询主机名的`A`以及`AAAA`记录, 所有地址使用相同的端口
+    ///
+    Host { host: String, port: u16 },
+
+    ///
+    /// 查询`SRV`记录, 例如: `_http._tcp.example.com`
+    ///
+    Srv(String),
+}
+
+///
+/// 基于DNS的服务发现
+///
+/// 查询结果会缓存到记录的TTL过期为止, TTL会被限制在`[min_ttl, max_ttl]`之间,
+/// 无法获取TTL时使用`max_ttl`。查询失败或者没有任何记录时返回错误, 负载均衡器会继续使用上一次的后端集合。
+///
+/// `SRV`记录只使用优先级最高(`priority`最小)的一组, 记录的`weight`作为后端的权重。
+///
+pub struct DnsDiscovery {
+    lookup: Arc<dyn DnsLookup + Send + Sync>,
+    query: DnsQuery,
+    weight: usize,
+    labels: Labels,
+    min_ttl: Duration,
+    max_ttl: Duration,
+    cache: Mutex<Option<(BTreeSet<Backend>, Instant)>>,
+}
+
+impl DnsDiscovery {
+    pub fn new(lookup: impl DnsLookup + Send + Sync + 'static, query: DnsQuery) -> Self {
+        Self {
+            lookup: Arc::new(lookup),
+            query,
+            weight: 1,
+            labels: Labels::default(),
+            min_ttl: Duration::from_secs(5),
+            max_ttl: Duration::from_secs(60),
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// 设置主机名查询时后端的权重
+    pub fn with_weight(mut self, weight: usize) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// 设置所有后端的标签
+    pub fn with_labels(mut self, labels: Labels) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// 设置TTL的最小值
+    pub fn with_min_ttl(mut self, min_ttl: Duration) -> Self {
+        self.min_ttl = min_ttl;
+        self
+    }
+
+    /// 设置TTL的最大值
+    pub fn with_max_ttl(mut self, max_ttl: Duration) -> Self {
+        self.max_ttl = max_ttl;
+        self
+    }
+
+    fn cached(&self) -> Option<BTreeSet<Backend>> {
+        let cache = self.cache.lock().ok()?;
+        cache
+            .as_ref()
+            .filter(|(_, expires_at)| Instant::now() < *expires_at)
+            .map(|(backends, _)| backends.clone())
+    }
+
+    async fn resolve(&self) -> Result<(BTreeSet<Backend>, Option<Duration>), Error> {
+        match &self.query {
+            DnsQuery::Host { host, port } => {
+                let lookup = self.lookup.lookup_ip(host).await?;
+                let backends = lookup
+                    .records
+                    .into_iter()
+                    .map(|ip| {
+                        Backend::new_with_weight((ip, *port), self.weight)
+                            .with_labels(self.labels.clone())
+                    })
+                    .collect();
+                Ok((backends, lookup.ttl))
+            }
+            DnsQuery::Srv(name) => {
+                let lookup = self.lookup.lookup_srv(name).await?;
+                let mut ttl = lookup.ttl;
+                let priority = lookup.records.iter().map(|srv| srv.priority).min();
+                let mut backends = BTreeSet::new();
+                for srv in lookup
+                    .records
+                    .iter()
+                    .filter(|srv| Some(srv.priority) == priority)
+                {
+                    let target = self.lookup.lookup_ip(&srv.target).await?;
+                    ttl = min_ttl(ttl, target.ttl);
+                    let weight = (srv.weight as usize).max(1);
+                    backends.extend(target.records.into_iter().map(|ip| {
+                        Backend::new_with_weight((ip, srv.port), weight)
+                            .with_labels(self.labels.clone())
+                    }));
+                }
+                Ok((backends, ttl))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Discovery for DnsDiscovery {
+    async fn discover(&self) -> Result<(BTreeSet<Backend>, HashMap<u64, bool>), Error> {
+        if let Some(backends) = self.cached() {
+            return Ok((backends, HashMap::new()));
+        }
+        let (backends, ttl) = self.resolve().await?;
+        if backends.is_empty() {
+            return Err(Error::new(format!("no records found for {:?}", self.query)));
+        }
+        let ttl = ttl
+            .unwrap_or(self.max_ttl)
+            .clamp(self.min_ttl, self.max_ttl.max(self.min_ttl));
+        if let Ok(mut cache) = self.cache.lock() {
+            *cache = Some((backends.clone(), Instant::now() + ttl));
+        }
+        Ok((backends, HashMap::new()))
+    }
+}
+
+fn min_ttl(left: Option<Duration>, right: Option<Duration>) -> Option<Duration> {
+    match (left, right) {
+        (Some(left), Some(right)) => Some(left.min(right)),
+        (left, right) => left.or(right),
+    }
+}
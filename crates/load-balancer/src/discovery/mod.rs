@@ -0,0 +1,121 @@
+pub mod consul;
+pub mod dns;
+
+use crate::{Backend, Labels};
+use arc_swap::ArcSwap;
+use async_trait::async_trait;
+use satex_core::Error;
+use std::collections::{BTreeSet, HashMap};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::lookup_host;
+
+#[async_trait]
+pub trait Discovery {
+    async fn discover(&self) -> Result<(BTreeSet<Backend>, HashMap<u64, bool>), Error>;
+}
+
+pub struct StaticFixedDiscovery {
+    backends: ArcSwap<BTreeSet<Backend>>,
+}
+
+impl StaticFixedDiscovery {
+    pub fn new(backends: BTreeSet<Backend>) -> Self {
+        Self {
+            backends: ArcSwap::new(Arc::new(backends)),
+        }
+    }
+}
+
+#[async_trait]
+impl Discovery for StaticFixedDiscovery {
+    async fn discover(&self) -> Result<(BTreeSet<Backend>, HashMap<u64, bool>), Error> {
+        Ok((BTreeSet::clone(&self.backends.load()), HashMap::new()))
+    }
+}
+
+///
+/// 静态地址, 可以是`IP:PORT`或者`HOST:PORT`, 主机名解析出的所有后端使用相同的权重、优先级和标签
+///
+#[derive(Debug, Clone)]
+pub struct StaticAddr {
+    addr: String,
+    weight: usize,
+    priority: usize,
+    labels: Labels,
+}
+
+impl StaticAddr {
+    pub fn new(addr: impl Into<String>, weight: usize) -> Self {
+        Self {
+            addr: addr.into(),
+            weight,
+            priority: 0,
+            labels: Labels::default(),
+        }
+    }
+
+    pub fn with_priority(mut self, priority: usize) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_labels(mut self, labels: Labels) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    ///
+    /// 解析为后端服务, 主机名通过系统解析器解析
+    ///
+    pub(crate) async fn lookup(&self) -> Result<Vec<Backend>, Error> {
+        let backend = |addr: SocketAddr| {
+            Backend::new_with_weight(addr, self.weight)
+                .with_priority(self.priority)
+                .with_labels(self.labels.clone())
+        };
+        match self.addr.parse::<SocketAddr>() {
+            Ok(addr) => Ok(vec![backend(addr)]),
+            Err(_) => lookup_host(&self.addr)
+                .await
+                .map(|addrs| addrs.map(backend).collect())
+                .map_err(|e| Error::new(format!("lookup host `{}` error: {}", self.addr, e))),
+        }
+    }
+}
+
+///
+/// 静态地址的服务发现
+///
+/// 主机名在每次服务发现时都会通过系统解析器重新解析。
+/// 任意一个主机名解析失败时返回错误, 负载均衡器会继续使用上一次的后端集合。
+///
+pub struct StaticLookupDiscovery {
+    addrs: Vec<StaticAddr>,
+}
+
+impl StaticLookupDiscovery {
+    ///
+    /// 创建服务发现实例
+    ///
+    /// # Arguments
+    ///
+    /// * `addrs`: 静态地址
+    ///
+    pub fn new(addrs: impl IntoIterator<Item = StaticAddr>) -> Self {
+        Self {
+            addrs: addrs.into_iter().collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl Discovery for StaticLookupDiscovery {
+    async fn discover(&self) -> Result<(BTreeSet<Backend>, HashMap<u64, bool>), Error> {
+        let mut backends = BTreeSet::new();
+        for addr in self.addrs.iter() {
+            backends.extend(addr.lookup().await?);
+        }
+        Ok((backends, HashMap::new()))
+    }
+}
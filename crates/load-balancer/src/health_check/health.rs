@@ -1,5 +1,7 @@
+use crate::health_check::outlier::OutlierDetection;
 use arc_swap::ArcSwap;
 use std::sync::Arc;
+use std::time::Instant;
 
 #[derive(Clone)]
 struct HealthInner {
@@ -11,6 +13,42 @@ struct HealthInner {
     /// When [healthy] is true, this counts the number of consecutive health check failures
     /// so that the caller can flip the healthy when a certain threshold is met, and vise versa.
     consecutive_counter: usize,
+    /// The passive health state reported by the proxy
+    passive: Passive,
+    /// 后端新加入或者恢复接收流量的时间, 用于慢启动
+    warm_since: Option<Instant>,
+}
+
+/// 被动健康检查的状态
+#[derive(Clone, Default)]
+struct Passive {
+    /// 连续的错误次数
+    consecutive_errors: usize,
+    /// 当前统计窗口的开始时间, 请求总数以及错误数
+    window_start: Option<Instant>,
+    requests: usize,
+    errors: usize,
+    /// 驱逐结束的时间
+    ejected_until: Option<Instant>,
+    /// 连续被驱逐的次数, 用于计算驱逐时间的指数退避
+    ejections: u32,
+    /// 上一次驱逐结束的时间
+    last_ejection_end: Option<Instant>,
+}
+
+impl Passive {
+    fn ejected(&self, now: Instant) -> bool {
+        self.ejected_until.is_some_and(|until| now < until)
+    }
+}
+
+/// 被动健康检查导致的状态变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PassiveChange {
+    /// 后端被驱逐, 不再接收流量
+    Ejected(std::time::Duration),
+    /// 驱逐结束, 后端重新接收流量
+    Readmitted,
 }
 
 /// Health of backends that can be updated atomically
@@ -18,11 +56,7 @@ pub(crate) struct Health(ArcSwap<HealthInner>);
 
 impl Default for Health {
     fn default() -> Self {
-        Health(ArcSwap::new(Arc::new(HealthInner {
-            healthy: true,
-            enabled: true,
-            consecutive_counter: 0,
-        })))
+        Health::new(None)
     }
 }
 
@@ -34,19 +68,121 @@ impl Clone for Health {
 }
 
 impl Health {
+    /// 创建新加入的后端的健康状态, `warm_since` 不为空时从该时间开始慢启动
+    pub fn new(warm_since: Option<Instant>) -> Self {
+        Health(ArcSwap::new(Arc::new(HealthInner {
+            healthy: true,
+            enabled: true,
+            consecutive_counter: 0,
+            passive: Passive::default(),
+            warm_since,
+        })))
+    }
+
+    /// 后端新加入或者恢复接收流量的时间
+    pub fn warm_since(&self) -> Option<Instant> {
+        self.0.load().warm_since
+    }
+
     pub fn ready(&self) -> bool {
         let h = self.0.load();
-        h.healthy && h.enabled
+        h.healthy && h.enabled && !h.passive.ejected(Instant::now())
+    }
+
+    /// 后端是否因为被动健康检查而被驱逐
+    pub fn ejected(&self, now: Instant) -> bool {
+        self.0.load().passive.ejected(now)
+    }
+
+    /// 记录一次代理请求的结果, 返回驱逐状态的变化
+    ///
+    /// `can_eject` 用于限制同时被驱逐的后端比例, 只在需要驱逐时调用。
+    pub fn observe_passive<F>(
+        &self,
+        success: bool,
+        now: Instant,
+        detection: &OutlierDetection,
+        can_eject: F,
+    ) -> Option<PassiveChange>
+    where
+        F: Fn() -> bool,
+    {
+        // 驱逐期间仍在进行中的请求结果不再统计
+        if self.ejected(now) {
+            return None;
+        }
+        let can_eject = !success && can_eject();
+        let mut change = None;
+        self.0.rcu(|h| {
+            let mut new_health = (**h).clone();
+            let passive = &mut new_health.passive;
+            change = None;
+            if passive.ejected(now) {
+                return new_health;
+            }
+            if let Some(until) = passive.ejected_until.take() {
+                passive.last_ejection_end = Some(until);
+                passive.window_start = None;
+                new_health.warm_since = Some(now);
+                change = Some(PassiveChange::Readmitted);
+            }
+            if passive
+                .window_start
+                .is_none_or(|start| now.saturating_duration_since(start) >= detection.interval)
+            {
+                passive.window_start = Some(now);
+                passive.requests = 0;
+                passive.errors = 0;
+            }
+            passive.requests += 1;
+            if success {
+                passive.consecutive_errors = 0;
+                return new_health;
+            }
+            passive.errors += 1;
+            passive.consecutive_errors += 1;
+            if can_eject
+                && detection.should_eject(
+                    passive.consecutive_errors,
+                    passive.requests,
+                    passive.errors,
+                )
+            {
+                // 距离上一次驱逐结束足够久时重新开始退避
+                if passive
+                    .last_ejection_end
+                    .is_some_and(|end| now.saturating_duration_since(end) > detection.max_ejection)
+                {
+                    passive.ejections = 0;
+                }
+                let duration = detection.ejection_time(passive.ejections);
+                passive.ejected_until = Some(now + duration);
+                passive.ejections = passive.ejections.saturating_add(1);
+                passive.consecutive_errors = 0;
+                passive.window_start = None;
+                change = Some(PassiveChange::Ejected(duration));
+            }
+            new_health
+        });
+        change
     }
 
+    /// 设置是否启用, 只有从禁用变为启用时才开始慢启动,
+    /// 服务发现每次都会报告启用状态, 重复启用不会重新开始慢启动
     pub fn enable(&self, enabled: bool) {
-        let h = self.0.load();
-        if h.enabled != enabled {
-            // clone the inner
+        if self.0.load().enabled == enabled {
+            return;
+        }
+        self.0.rcu(|h| {
             let mut new_health = (**h).clone();
-            new_health.enabled = enabled;
-            self.0.store(Arc::new(new_health));
-        };
+            if h.enabled != enabled {
+                new_health.enabled = enabled;
+                if enabled {
+                    new_health.warm_since = Some(Instant::now());
+                }
+            }
+            new_health
+        });
     }
 
     // return true when the health is flipped
@@ -61,6 +197,9 @@ impl Health {
             if new_health.consecutive_counter >= flip_threshold {
                 new_health.healthy = health;
                 new_health.consecutive_counter = 0;
+                if health {
+                    new_health.warm_since = Some(Instant::now());
+                }
                 flipped = true;
             }
             self.0.store(Arc::new(new_health));
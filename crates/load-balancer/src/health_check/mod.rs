@@ -1,4 +1,5 @@
 pub mod health;
+pub mod outlier;
 pub mod tcp;
 
 use crate::Backend;
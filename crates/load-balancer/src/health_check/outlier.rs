@@ -0,0 +1,150 @@
+use crate::Backend;
+use crate::health_check::HealthStatusObserve;
+use async_trait::async_trait;
+use std::time::Duration;
+use tracing::{info, warn};
+
+///
+/// 被动健康检查(异常检测)
+///
+/// 根据代理请求的结果统计每个后端的连续错误次数以及错误率, 超过阈值的后端会被驱逐一段时间。
+/// 驱逐时间为`base_ejection * 2^n`, `n`为连续被驱逐的次数, 最长为`max_ejection`;
+/// 同时被驱逐的后端不会超过`max_ejection_percent`。
+///
+pub struct OutlierDetection {
+    pub(crate) consecutive_errors: Option<usize>,
+    pub(crate) error_rate: Option<f64>,
+    pub(crate) min_requests: usize,
+    pub(crate) interval: Duration,
+    pub(crate) base_ejection: Duration,
+    pub(crate) max_ejection: Duration,
+    pub(crate) max_ejection_percent: f64,
+    pub(crate) status_observe: Option<Box<dyn HealthStatusObserve + Send + Sync>>,
+}
+
+impl Default for OutlierDetection {
+    fn default() -> Self {
+        Self {
+            consecutive_errors: Some(5),
+            error_rate: None,
+            min_requests: 10,
+            interval: Duration::from_secs(10),
+            base_ejection: Duration::from_secs(30),
+            max_ejection: Duration::from_secs(300),
+            max_ejection_percent: 10.0,
+            status_observe: None,
+        }
+    }
+}
+
+impl OutlierDetection {
+    /// 设置连续错误次数的阈值, `None`表示不按照连续错误驱逐
+    pub fn with_consecutive_errors(mut self, consecutive_errors: Option<usize>) -> Self {
+        self.consecutive_errors = consecutive_errors;
+        self
+    }
+
+    /// 设置错误率的阈值(`0.0 ~ 1.0`), `None`表示不按照错误率驱逐
+    pub fn with_error_rate(mut self, error_rate: Option<f64>) -> Self {
+        self.error_rate = error_rate;
+        self
+    }
+
+    /// 设置统计错误率需要的最少请求数
+    pub fn with_min_requests(mut self, min_requests: usize) -> Self {
+        self.min_requests = min_requests;
+        self
+    }
+
+    /// 设置错误率的统计窗口
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// 设置基础驱逐时间
+    pub fn with_base_ejection(mut self, base_ejection: Duration) -> Self {
+        self.base_ejection = base_ejection;
+        self
+    }
+
+    /// 设置最长驱逐时间
+    pub fn with_max_ejection(mut self, max_ejection: Duration) -> Self {
+        self.max_ejection = max_ejection;
+        self
+    }
+
+    /// 设置同时被驱逐的后端的最大比例(`0 ~ 100`)
+    pub fn with_max_ejection_percent(mut self, max_ejection_percent: f64) -> Self {
+        self.max_ejection_percent = max_ejection_percent;
+        self
+    }
+
+    pub fn with_status_observe(
+        mut self,
+        status_observe: impl HealthStatusObserve + Send + Sync + 'static,
+    ) -> Self {
+        self.status_observe = Some(Box::new(status_observe));
+        self
+    }
+
+    pub(crate) fn should_eject(
+        &self,
+        consecutive_errors: usize,
+        requests: usize,
+        errors: usize,
+    ) -> bool {
+        let by_consecutive = self
+            .consecutive_errors
+            .is_some_and(|threshold| consecutive_errors >= threshold.max(1));
+        let by_rate = self.error_rate.is_some_and(|rate| {
+            requests >= self.min_requests.max(1) && errors as f64 >= requests as f64 * rate
+        });
+        by_consecutive || by_rate
+    }
+
+    pub(crate) fn ejection_time(&self, ejections: u32) -> Duration {
+        self.base_ejection
+            .checked_mul(2u32.saturating_pow(ejections))
+            .unwrap_or(self.max_ejection)
+            .min(self.max_ejection)
+    }
+
+    ///
+    /// 是否允许再驱逐一个后端
+    ///
+    pub(crate) fn can_eject(&self, ejected: usize, total: usize) -> bool {
+        total > 0 && (ejected as f64) < total as f64 * self.max_ejection_percent / 100.0
+    }
+}
+
+///
+/// 通过日志输出健康状态的变化
+///
+#[derive(Debug, Clone, Default)]
+pub struct LogHealthStatusObserve {
+    name: String,
+}
+
+impl LogHealthStatusObserve {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+#[async_trait]
+impl HealthStatusObserve for LogHealthStatusObserve {
+    async fn observe(&self, backend: &Backend, success: bool) {
+        if success {
+            info!(
+                "upstream `{}` backend {} is readmitted",
+                self.name, backend.addr
+            );
+        } else {
+            warn!(
+                "upstream `{}` backend {} is ejected",
+                self.name, backend.addr
+            );
+        }
+    }
+}
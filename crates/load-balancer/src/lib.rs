@@ -6,12 +6,15 @@
 mod background;
 mod load_balancer;
 
+pub mod circuit_breaker;
 pub mod discovery;
 pub mod health_check;
 pub mod resolver;
 pub mod selector;
+pub mod slow_start;
+pub mod sticky;
 
-pub use load_balancer::LoadBalancer;
+pub use load_balancer::{DEFAULT_OVERPROVISIONING_FACTOR, Inflight, LoadBalancer};
 
 use crate::discovery::Discovery;
 use crate::health_check::HealthCheck;
@@ -20,11 +23,12 @@ use arc_swap::ArcSwap;
 use derivative::Derivative;
 use http::Extensions;
 use satex_core::Error;
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::hash::{DefaultHasher, Hash, Hasher};
 use std::net::{AddrParseError, SocketAddr};
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::spawn;
 use tracing::{info, warn};
 
@@ -42,6 +46,13 @@ pub struct Backend {
     ///
     pub weight: usize,
 
+    ///
+    /// 后端服务优先级, 数值越小优先级越高, 默认为`0`.
+    /// 负载均衡器优先使用优先级最高的一组后端, 这一组中可以接收流量的后端比例过低时才会转发到下一组.
+    ///
+    #[derivative(Hash = "ignore")]
+    pub priority: usize,
+
     ///
     /// 拓展信息
     ///
@@ -63,10 +74,39 @@ impl Backend {
         Self {
             addr: addr.into(),
             weight,
+            priority: 0,
             extension: Extensions::new(),
         }
     }
 
+    /// 设置优先级
+    pub fn with_priority(mut self, priority: usize) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// 设置标签, 标签保存在拓展信息中, 标签为空时不保存
+    pub fn with_labels(mut self, labels: Labels) -> Self {
+        if !labels.is_empty() {
+            self.extension.insert(labels);
+        }
+        self
+    }
+
+    /// 后端服务的标签
+    pub fn labels(&self) -> Option<&Labels> {
+        self.extension.get::<Labels>()
+    }
+
+    /// 后端服务是否包含所有的标签, `subset` 为空时返回 true
+    pub fn matches(&self, subset: &[(String, String)]) -> bool {
+        subset.iter().all(|(name, value)| {
+            self.labels()
+                .and_then(|labels| labels.get(name))
+                .is_some_and(|label| label == value)
+        })
+    }
+
     /// 计算后端服务的哈希值
     pub(crate) fn key(&self) -> u64 {
         let mut hasher = DefaultHasher::new();
@@ -75,6 +115,38 @@ impl Backend {
     }
 }
 
+/// 后端服务标签, 例如: `version`、`zone`、`tenant`, 保存在 [Backend::extension] 中
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Labels(BTreeMap<String, String>);
+
+impl Labels {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
+impl From<BTreeMap<String, String>> for Labels {
+    fn from(labels: BTreeMap<String, String>) -> Self {
+        Self(labels)
+    }
+}
+
+impl FromIterator<(String, String)> for Labels {
+    fn from_iter<T: IntoIterator<Item = (String, String)>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
 impl FromStr for Backend {
     type Err = AddrParseError;
 
@@ -92,7 +164,6 @@ impl<'a> TryFrom<(&'a str, usize)> for Backend {
     }
 }
 
-
 pub struct Backends {
     discovery: Box<dyn Discovery + Send + Sync>,
     health_check: Option<Arc<dyn HealthCheck + Send + Sync>>,
@@ -131,28 +202,43 @@ impl Backends {
     ) where
         F: Fn(Arc<BTreeSet<Backend>>),
     {
-        if (**self.backends.load()) != new_backends {
-            let old_health = self.health.load();
-            let mut new_health = HashMap::with_capacity(new_backends.len());
-            for backend in new_backends.iter() {
-                let key = backend.key();
-                // use the default health if the backend is new
-                let health = old_health.get(&key).cloned().unwrap_or_default();
-
-                // override enablement
-                if let Some(enabled) = enablement.get(&key) {
-                    health.enable(*enabled);
+        // 拓展信息不参与后端的比较, 标签变化时同样需要更新
+        let old_backends = self.backends.load();
+        let changed = **old_backends != new_backends
+            || old_backends
+                .iter()
+                .zip(new_backends.iter())
+                .any(|(old, new)| old.labels() != new.labels());
+        if changed {
+            // 使用`rcu`保存健康状态, 避免覆盖同时继承的健康状态
+            self.health.rcu(|old_health| {
+                let mut new_health = HashMap::with_capacity(new_backends.len());
+                // 第一次服务发现得到的后端不需要慢启动
+                let warm_since = (!old_health.is_empty()).then(Instant::now);
+                for backend in new_backends.iter() {
+                    let key = backend.key();
+                    // use the default health if the backend is new
+                    let health = old_health
+                        .get(&key)
+                        .cloned()
+                        .unwrap_or_else(|| Health::new(warm_since));
+
+                    // override enablement
+                    if let Some(enabled) = enablement.get(&key) {
+                        health.enable(*enabled);
+                    }
+                    new_health.insert(key, health);
                 }
-                new_health.insert(key, health);
-            }
+                new_health
+            });
 
-            // 确保 `callback()` 首先执行是很重要的，因为计算选择器后端可能会很耗时。
+            // 确保 `callback()` 在保存后端之前执行是很重要的，因为计算选择器后端可能会很耗时。
             // 例如，如果调用者检查 `backends` 以查看是否有可用的后端，
             // 如果选择器尚未准备好，他们可能会遇到误报。
+            // 健康状态需要在 `callback()` 之前保存，选择器需要根据新加入的后端的慢启动状态计算有效权重。
             let new_backends = Arc::new(new_backends);
             callback(new_backends.clone());
             self.backends.store(new_backends);
-            self.health.store(Arc::new(new_health));
         } else {
             // no backend change, just check enablement
             for (key, backend_enabled) in enablement.iter() {
@@ -165,6 +251,28 @@ impl Backends {
         }
     }
 
+    /// 继承 `previous` 中后端的健康状态
+    ///
+    /// 还没有完成服务发现时保存 `previous` 的健康状态, 服务发现时同一个后端会继续使用该状态;
+    /// 已经完成服务发现时只替换仍然存在的后端的健康状态。
+    /// 服务发现可能在后台同时进行, 使用`rcu`合并同时保存的健康状态, 而不是覆盖。
+    pub(crate) fn inherit(&self, previous: &Backends) {
+        let previous = previous.health.load();
+        self.health.rcu(|current| -> HashMap<u64, Health> {
+            if current.is_empty() {
+                previous
+                    .iter()
+                    .map(|(key, health)| (*key, health.clone()))
+                    .collect()
+            } else {
+                current
+                    .iter()
+                    .map(|(key, health)| (*key, previous.get(key).unwrap_or(health).clone()))
+                    .collect()
+            }
+        });
+    }
+
     /// 检查指定的后端服务是否可以接收流量
     ///
     /// 在以下情况下返回 true:
@@ -181,6 +289,27 @@ impl Backends {
             .map_or(self.health_check.is_none(), |h| h.ready())
     }
 
+    /// 按照优先级分组统计可以接收流量的后端数量以及后端总数
+    pub fn priorities(&self) -> BTreeMap<usize, (usize, usize)> {
+        let mut priorities = BTreeMap::new();
+        for backend in self.backends.load().iter() {
+            let (ready, total) = priorities.entry(backend.priority).or_insert((0, 0));
+            if self.ready(backend) {
+                *ready += 1;
+            }
+            *total += 1;
+        }
+        priorities
+    }
+
+    /// 后端新加入或者恢复接收流量的时间, 用于慢启动
+    pub(crate) fn warm_since(&self, backend: &Backend) -> Option<Instant> {
+        self.health
+            .load()
+            .get(&backend.key())
+            .and_then(|h| h.warm_since())
+    }
+
     /// 手动设置一个 [Backend] 是否可以接收流量。
     ///
     /// 此方法不会覆盖后端的健康状态。它的目的是在后端仍然健康时，停止其接受流量。
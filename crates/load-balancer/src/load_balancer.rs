@@ -1,16 +1,46 @@
+use crate::circuit_breaker::{CircuitBreaker, Permit, Rejection};
 use crate::health_check::HealthCheck;
+use crate::health_check::health::PassiveChange;
+use crate::health_check::outlier::OutlierDetection;
 use crate::selector::{BackendIter, BoxSelector, Selector};
+use crate::slow_start::SlowStart;
+use crate::sticky::StickySession;
 use crate::{Backend, Backends};
+use http::HeaderMap;
+use http::uri::Scheme;
 use satex_core::Error;
-use std::time::Duration;
+use satex_core::metrics::UPSTREAM_BACKEND_HEALTHY;
+use std::cell::Cell;
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// 默认的超额配置系数，优先级组中可以接收流量的后端比例低于 `1 / 1.4` 时开始转发到下一组
+pub const DEFAULT_OVERPROVISIONING_FACTOR: f64 = 1.4;
+
+/// 设置了慢启动时有效权重的精度, 后端的权重放大为 `weight * 10` 后再按照慢启动的比例降低
+const SLOW_START_SCALE: f64 = 10.0;
+
+/// 选择后端时最多遍历的次数，避免所有后端都不可用时一直遍历
+const MAX_ITERATIONS: usize = 256;
 
 /// 负载均衡器
 pub struct LoadBalancer {
+    name: String,
+    scheme: Scheme,
     selector: BoxSelector,
     pub(crate) backends: Backends,
     pub(crate) update_frequency: Option<Duration>,
     pub(crate) health_check_frequency: Option<Duration>,
     pub(crate) health_check_parallel: bool,
+    outlier_detection: Option<OutlierDetection>,
+    sticky_session: Option<StickySession>,
+    slow_start: Option<SlowStart>,
+    /// 下一次按照慢启动的进度更新有效权重的时间, 没有处于慢启动中的后端时为空
+    reweight_at: Mutex<Option<Instant>>,
+    circuit_breaker: Option<CircuitBreaker>,
+    overprovisioning_factor: f64,
 }
 
 impl LoadBalancer {
@@ -22,14 +52,44 @@ impl LoadBalancer {
     {
         let selector = BoxSelector::new(selector);
         Self {
+            name: String::new(),
+            scheme: Scheme::HTTP,
             backends,
             selector,
             update_frequency: None,
             health_check_frequency: None,
             health_check_parallel: false,
+            outlier_detection: None,
+            sticky_session: None,
+            slow_start: None,
+            reweight_at: Mutex::new(None),
+            circuit_breaker: None,
+            overprovisioning_factor: DEFAULT_OVERPROVISIONING_FACTOR,
         }
     }
 
+    /// 设置名称, 名称会作为指标的上游标签
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// 负载均衡器名称
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 设置转发到后端使用的协议，默认为 `http`
+    pub fn with_scheme(mut self, scheme: Scheme) -> Self {
+        self.scheme = scheme;
+        self
+    }
+
+    /// 转发到后端使用的协议
+    pub fn scheme(&self) -> &Scheme {
+        &self.scheme
+    }
+
     /// 设置健康检查
     pub fn with_health_check(
         mut self,
@@ -56,20 +116,158 @@ impl LoadBalancer {
         self
     }
 
+    /// 设置被动健康检查
+    pub fn with_outlier_detection(mut self, outlier_detection: OutlierDetection) -> Self {
+        self.outlier_detection = Some(outlier_detection);
+        self
+    }
+
+    /// 设置超额配置系数，优先级组接收的流量比例为 `可以接收流量的后端比例 * 超额配置系数`，
+    /// 最大为 `100%`，剩余的流量转发到下一个优先级组。
+    pub fn with_overprovisioning_factor(mut self, overprovisioning_factor: f64) -> Self {
+        self.overprovisioning_factor = overprovisioning_factor;
+        self
+    }
+
+    /// 设置慢启动
+    pub fn with_slow_start(mut self, slow_start: SlowStart) -> Self {
+        self.slow_start = Some(slow_start);
+        self
+    }
+
+    /// 设置熔断器
+    pub fn with_circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// 熔断器配置
+    pub fn circuit_breaker(&self) -> Option<&CircuitBreaker> {
+        self.circuit_breaker.as_ref()
+    }
+
+    /// 设置会话保持
+    pub fn with_sticky_session(mut self, sticky_session: StickySession) -> Self {
+        self.sticky_session = Some(sticky_session);
+        self
+    }
+
+    /// 会话保持配置
+    pub fn sticky_session(&self) -> Option<&StickySession> {
+        self.sticky_session.as_ref()
+    }
+
+    /// 返回请求的会话保持Cookie记录的后端，后端不存在或者不可以接收流量时返回空。
+    pub fn select_sticky(&self, headers: &HeaderMap) -> Option<Backend> {
+        let addr = self.sticky_session.as_ref()?.find(headers)?;
+        self.backends
+            .items()
+            .iter()
+            .find(|backend| backend.addr == addr && self.backends.ready(backend))
+            .cloned()
+    }
+
+    /// 报告一次发送到 `backend` 的请求结果，用于被动健康检查。
+    ///
+    /// 连接错误、超时以及 `5xx` 响应都应该报告为失败，设置了熔断器时同时记录到熔断器。
+    pub async fn report(&self, backend: &Backend, success: bool) {
+        if let Some(opened) = self
+            .circuit_breaker
+            .as_ref()
+            .and_then(|circuit_breaker| circuit_breaker.record(backend, success))
+        {
+            if opened {
+                warn!(
+                    "upstream `{}` backend {} circuit breaker is open",
+                    self.name, backend.addr
+                );
+            } else {
+                info!(
+                    "upstream `{}` backend {} circuit breaker is closed",
+                    self.name, backend.addr
+                );
+            }
+        }
+        let Some(detection) = &self.outlier_detection else {
+            return;
+        };
+        let health = self.backends.health.load();
+        let Some(h) = health.get(&backend.key()) else {
+            return;
+        };
+        let now = Instant::now();
+        let change = h.observe_passive(success, now, detection, || {
+            let ejected = health.values().filter(|h| h.ejected(now)).count();
+            detection.can_eject(ejected, health.len())
+        });
+        let success = match change {
+            Some(PassiveChange::Ejected(duration)) => {
+                debug!(
+                    "{:?} is ejected from `{}` for {:?}",
+                    backend, self.name, duration
+                );
+                false
+            }
+            Some(PassiveChange::Readmitted) => {
+                self.warm_up(now);
+                true
+            }
+            None => return,
+        };
+        if let Some(status_observe) = &detection.status_observe {
+            status_observe.observe(backend, success).await;
+        }
+    }
+
+    /// 继承 `previous` 中仍然存在的后端的健康状态，用于重新加载配置时保留健康检查和被动健康检查的结果。
+    pub fn inherit(&self, previous: &LoadBalancer) {
+        self.backends.inherit(&previous.backends);
+        // 继承的后端可能仍然处于慢启动中
+        self.warm_up(Instant::now());
+    }
+
     /// 运行服务发现并更新选择算法。
     ///
     /// 如果这个 [LoadBalancer] 实例作为后台服务运行，此函数将每隔 `update_frequency` 被调用一次。
     pub async fn update(&self) -> Result<(), Error> {
+        let old_backends = self.backends.items();
+        let now = Instant::now();
         self.backends
-            .update(|backends| self.selector.update(&backends))
-            .await
+            .update(|backends| self.update_selector(&backends, now))
+            .await?;
+        // 后端集合没有变化时启用状态仍然可能变化
+        self.warm_up(now);
+
+        // 删除已经下线的后端服务的健康状态指标以及熔断器状态
+        let new_backends = self.backends.items();
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.retain(&new_backends);
+        }
+        for backend in old_backends.difference(&new_backends) {
+            let _ = UPSTREAM_BACKEND_HEALTHY
+                .remove_label_values(&[&self.name, &backend.addr.to_string()]);
+        }
+        Ok(())
+    }
+
+    /// 更新所有后端服务的健康状态指标
+    pub fn observe_health(&self) {
+        for backend in self.backends.items().iter() {
+            UPSTREAM_BACKEND_HEALTHY
+                .with_label_values(&[&self.name, &backend.addr.to_string()])
+                .set(self.backends.ready(backend) as i64);
+        }
     }
 
     /// 根据选择算法和健康检查结果返回第一个健康的 [Backend]。
     ///
     /// `key` 用于基于哈希的选择，如果选择是随机或轮询，则忽略此参数。
     ///
-    /// `max_iterations` 用于限制搜索下一个 Backend 的时间。在某些算法中，
+    /// 后端存在多个优先级时，先根据各个优先级组的健康比例选择一个优先级组，再在组内选择后端。
+    ///
+    /// 设置了慢启动时，处于慢启动中的后端在选择算法中使用按照慢启动的比例降低后的有效权重。
+    ///
+    /// [MAX_ITERATIONS] 用于限制搜索下一个 Backend 的时间。在某些算法中，
     /// 如 Ketama 哈希，搜索下一个后端是线性的，可能需要很多步骤。
     pub fn select(&self, key: &[u8]) -> Option<Backend> {
         self.select_with(key, |_, health| health)
@@ -81,15 +279,182 @@ impl LoadBalancer {
     /// 比如忽略内部健康检查或因为之前失败而跳过这个后端。`accept` 函数会被多次调用，遍历后端，
     /// 直到返回 `true`。
     pub fn select_with<F>(&self, key: &[u8], accept: F) -> Option<Backend>
+    where
+        F: Fn(&Backend, bool) -> bool,
+    {
+        self.ramp(Instant::now());
+        let priority = self.select_priority();
+        self.select_in(key, priority, &accept).or_else(|| {
+            // 选中的优先级组中没有可以选择的后端时, 不再区分优先级
+            priority.and_then(|_| self.select_in(key, None, &accept))
+        })
+    }
+
+    /// 在 `priority` 优先级组中选择后端, `priority` 为空时在所有后端中选择
+    fn select_in<F>(&self, key: &[u8], priority: Option<usize>, accept: &F) -> Option<Backend>
     where
         F: Fn(&Backend, bool) -> bool,
     {
         let mut iter = self.selector.iter(key);
-        while let Some(b) = iter.next() {
-            if accept(b, self.backends.ready(b)) {
+        for _ in 0..MAX_ITERATIONS {
+            let Some(b) = iter.next() else {
+                break;
+            };
+            if priority.is_none_or(|priority| b.priority == priority)
+                && accept(b, self.backends.ready(b))
+            {
                 return Some(b.clone());
             }
         }
         None
     }
+
+    /// 按照各个优先级组可以接收流量的后端比例选择本次使用的优先级组，只有一个优先级组时返回空。
+    ///
+    /// 优先级组接收的流量比例为 `可以接收流量的后端比例 * 超额配置系数`，最大为剩余的流量比例；
+    /// 所有优先级组的比例之和不足 `100%` 时按照比例放大。
+    fn select_priority(&self) -> Option<usize> {
+        let priorities = self.backends.priorities();
+        if priorities.len() <= 1 {
+            return None;
+        }
+        let mut remaining = 1.0;
+        let loads = priorities
+            .into_iter()
+            .map(|(priority, (ready, total))| {
+                let health = ready as f64 / total as f64 * self.overprovisioning_factor;
+                let load = health.min(remaining);
+                remaining -= load;
+                (priority, load)
+            })
+            .collect::<Vec<_>>();
+        let sum = loads.iter().map(|(_, load)| load).sum::<f64>();
+        if sum <= 0.0 {
+            return loads.first().map(|(priority, _)| *priority);
+        }
+        let mut roll = rand::random::<f64>() * sum;
+        for (priority, load) in loads.iter() {
+            if roll < *load {
+                return Some(*priority);
+            }
+            roll -= load;
+        }
+        loads.last().map(|(priority, _)| *priority)
+    }
+
+    /// 更新选择算法的后端集合, 设置了慢启动时按照慢启动的进度设置后端的有效权重
+    fn update_selector(&self, backends: &BTreeSet<Backend>, now: Instant) {
+        let Some(slow_start) = &self.slow_start else {
+            self.selector.update(backends);
+            return;
+        };
+        let warming = Cell::new(false);
+        self.selector.update_weighted(backends, &|backend| {
+            let factor = self.warm_factor(slow_start, backend, now);
+            warming.set(warming.get() || factor < 1.0);
+            (backend.weight as f64 * SLOW_START_SCALE * factor).ceil() as usize
+        });
+        if let Ok(mut reweight_at) = self.reweight_at.lock() {
+            *reweight_at = warming.get().then(|| now + slow_start.interval());
+        }
+    }
+
+    /// 后端慢启动的有效权重比例
+    fn warm_factor(&self, slow_start: &SlowStart, backend: &Backend, now: Instant) -> f64 {
+        self.backends.warm_since(backend).map_or(1.0, |warm_since| {
+            slow_start.factor(now.saturating_duration_since(warm_since))
+        })
+    }
+
+    /// 到达更新时间时按照慢启动的进度更新有效权重, 同一时间只有一个请求执行更新
+    fn ramp(&self, now: Instant) {
+        if self.slow_start.is_none() {
+            return;
+        }
+        let Ok(mut reweight_at) = self.reweight_at.try_lock() else {
+            return;
+        };
+        if reweight_at.is_some_and(|reweight_at| now >= reweight_at) {
+            *reweight_at = None;
+            drop(reweight_at);
+            self.update_selector(&self.backends.items(), now);
+        }
+    }
+
+    /// 有后端开始慢启动时更新有效权重, 例如: 重新启用、健康检查恢复以及驱逐结束的后端
+    pub(crate) fn warm_up(&self, now: Instant) {
+        let Some(slow_start) = &self.slow_start else {
+            return;
+        };
+        let ramping = self
+            .reweight_at
+            .lock()
+            .map_or(true, |reweight_at| reweight_at.is_some());
+        if ramping {
+            return;
+        }
+        let backends = self.backends.items();
+        if backends
+            .iter()
+            .any(|backend| self.warm_factor(slow_start, backend, now) < 1.0)
+        {
+            self.update_selector(&backends, now);
+        }
+    }
+
+    /// 开始跟踪发送到 `backend` 的请求，返回的 [Inflight] 释放时结束跟踪。
+    ///
+    /// 选择算法可以根据进行中的请求选择后端，例如: [crate::selector::LeastRequest]。
+    pub fn track(self: &Arc<Self>, backend: &Backend) -> Inflight {
+        self.selector.on_start(backend);
+        Inflight {
+            load_balancer: self.clone(),
+            backend: backend.clone(),
+            _permit: None,
+        }
+    }
+
+    /// 类似于 [Self::track]，设置了熔断器时先占用发送到 `backend` 的请求数，
+    /// 被熔断器拒绝时返回拒绝的原因。
+    pub async fn acquire(self: &Arc<Self>, backend: &Backend) -> Result<Inflight, Rejection> {
+        let permit = match &self.circuit_breaker {
+            Some(circuit_breaker) => Some(circuit_breaker.acquire(backend).await?),
+            None => None,
+        };
+        let mut inflight = self.track(backend);
+        inflight._permit = permit;
+        Ok(inflight)
+    }
+}
+
+/// 发送到后端的请求，释放时通知选择算法请求已经结束，并归还熔断器占用的请求数
+pub struct Inflight {
+    load_balancer: Arc<LoadBalancer>,
+    backend: Backend,
+    _permit: Option<Permit>,
+}
+
+impl Inflight {
+    /// 请求发送到的后端
+    pub fn backend(&self) -> &Backend {
+        &self.backend
+    }
+
+    /// 报告请求结果，见 [LoadBalancer::report]
+    pub async fn report(&self, success: bool) {
+        self.load_balancer.report(&self.backend, success).await;
+    }
+
+    /// 记录后端的响应延迟, 供延迟敏感的选择算法使用，例如: [crate::selector::PeakEwma]
+    pub fn observe_latency(&self, latency: Duration) {
+        self.load_balancer
+            .selector
+            .on_latency(&self.backend, latency);
+    }
+}
+
+impl Drop for Inflight {
+    fn drop(&mut self) {
+        self.load_balancer.selector.on_finish(&self.backend);
+    }
 }
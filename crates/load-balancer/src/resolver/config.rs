@@ -0,0 +1,578 @@
+use crate::discovery::StaticAddr;
+use crate::health_check::outlier::{LogHealthStatusObserve, OutlierDetection as Detection};
+use crate::health_check::tcp::TcpHealthCheck;
+use crate::selector::{BoxSelector, Consistent, LeastRequest, PeakEwma, Random, RoundRobin};
+use crate::sticky::StickySession;
+use crate::{DEFAULT_OVERPROVISIONING_FACTOR, Labels, LoadBalancer};
+use satex_core::Error;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+///
+/// 负载均衡策略, 支持策略名称, 例如: `PeakEwma`, 或者带有参数的策略, 例如: `{PeakEwma: {default-rtt-millis: 50}}`
+///
+#[derive(Deserialize, Default)]
+#[serde(from = "PolicyRepr")]
+pub(crate) enum Policy {
+    #[default]
+    RoundRobin,
+    Random,
+    Consistent,
+    LeastRequest,
+    PeakEwma(PeakEwmaConfig),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PolicyRepr {
+    Name(PolicyName),
+    PeakEwma {
+        #[serde(rename = "PeakEwma")]
+        config: PeakEwmaConfig,
+    },
+}
+
+#[derive(Deserialize)]
+enum PolicyName {
+    RoundRobin,
+    Random,
+    Consistent,
+    LeastRequest,
+    PeakEwma,
+}
+
+impl From<PolicyRepr> for Policy {
+    fn from(repr: PolicyRepr) -> Self {
+        match repr {
+            PolicyRepr::Name(PolicyName::RoundRobin) => Policy::RoundRobin,
+            PolicyRepr::Name(PolicyName::Random) => Policy::Random,
+            PolicyRepr::Name(PolicyName::Consistent) => Policy::Consistent,
+            PolicyRepr::Name(PolicyName::LeastRequest) => Policy::LeastRequest,
+            PolicyRepr::Name(PolicyName::PeakEwma) => Policy::PeakEwma(PeakEwmaConfig::default()),
+            PolicyRepr::PeakEwma { config } => Policy::PeakEwma(config),
+        }
+    }
+}
+
+///
+/// `PeakEwma`策略的参数
+///
+#[derive(Deserialize)]
+pub(crate) struct PeakEwmaConfig {
+    #[serde(default = "default_rtt_millis", rename = "default-rtt-millis")]
+    default_rtt_millis: u64,
+    #[serde(default = "default_decay_secs", rename = "decay-secs")]
+    decay_secs: u64,
+}
+
+impl Default for PeakEwmaConfig {
+    fn default() -> Self {
+        Self {
+            default_rtt_millis: default_rtt_millis(),
+            decay_secs: default_decay_secs(),
+        }
+    }
+}
+
+fn default_rtt_millis() -> u64 {
+    30
+}
+
+fn default_decay_secs() -> u64 {
+    10
+}
+
+impl Policy {
+    ///
+    /// 创建空的选择器, 后端集合由后台任务第一次服务发现时填充
+    ///
+    pub(crate) fn selector(&self) -> BoxSelector {
+        match self {
+            Policy::RoundRobin => BoxSelector::new(RoundRobin::new(&BTreeSet::new())),
+            Policy::Random => BoxSelector::new(Random::new(&BTreeSet::new())),
+            Policy::Consistent => BoxSelector::new(Consistent::new(&BTreeSet::new())),
+            Policy::LeastRequest => BoxSelector::new(LeastRequest::new(&BTreeSet::new())),
+            Policy::PeakEwma(config) => BoxSelector::new(
+                PeakEwma::new(&BTreeSet::new())
+                    .with_default_rtt(Duration::from_millis(config.default_rtt_millis))
+                    .with_decay(Duration::from_secs(config.decay_secs)),
+            ),
+        }
+    }
+}
+
+///
+/// 后端地址, 支持以下格式:
+///
+/// - `127.0.0.1:8080`
+/// - `127.0.0.1:8080@5`
+/// - `{addr: 127.0.0.1:8080, weight: 5, enabled: false}`
+/// - `{addr: 127.0.0.1:8080, priority: 1}`或者`{addr: 127.0.0.1:8080, backup: true}`
+/// - `{addr: 127.0.0.1:8080, labels: {version: v2}}`
+///
+/// 地址可以使用主机名, 例如: `backend.local:8080`, `enabled`为`false`的地址不会接收流量,
+/// `backup`为`true`的地址的优先级为`1`
+///
+#[derive(Deserialize)]
+#[serde(try_from = "AddrRepr")]
+pub(crate) struct Addr {
+    pub(crate) addr: String,
+    pub(crate) weight: usize,
+    pub(crate) enabled: bool,
+    pub(crate) priority: usize,
+    pub(crate) labels: BTreeMap<String, String>,
+}
+
+impl Addr {
+    pub(crate) fn to_static(&self) -> StaticAddr {
+        StaticAddr::new(self.addr.as_str(), self.weight)
+            .with_priority(self.priority)
+            .with_labels(Labels::from(self.labels.clone()))
+    }
+
+    pub(crate) fn is_hostname(&self) -> bool {
+        self.addr.parse::<SocketAddr>().is_err()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AddrRepr {
+    Short(String),
+    Full {
+        addr: String,
+        #[serde(default = "default_weight")]
+        weight: usize,
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+        #[serde(default)]
+        priority: Option<usize>,
+        #[serde(default)]
+        backup: bool,
+        #[serde(default)]
+        labels: BTreeMap<String, String>,
+    },
+}
+
+impl TryFrom<AddrRepr> for Addr {
+    type Error = Error;
+
+    fn try_from(repr: AddrRepr) -> Result<Self, Self::Error> {
+        let (addr, weight, enabled, priority, labels) = match repr {
+            AddrRepr::Short(value) => match value.rsplit_once('@') {
+                Some((addr, weight)) => {
+                    let weight = weight
+                        .trim()
+                        .parse::<usize>()
+                        .map_err(|e| Error::new(format!("invalid weight in `{}`: {}", value, e)))?;
+                    (addr.trim().to_string(), weight, true, 0, BTreeMap::new())
+                }
+                None => (
+                    value.trim().to_string(),
+                    default_weight(),
+                    true,
+                    0,
+                    BTreeMap::new(),
+                ),
+            },
+            AddrRepr::Full {
+                addr,
+                weight,
+                enabled,
+                priority,
+                backup,
+                labels,
+            } => {
+                let priority = priority.unwrap_or(if backup { 1 } else { 0 });
+                (addr.trim().to_string(), weight, enabled, priority, labels)
+            }
+        };
+        if weight == 0 {
+            return Err(Error::new(format!("weight of `{}` must be positive", addr)));
+        }
+        if addr.parse::<SocketAddr>().is_err() {
+            let valid = addr
+                .rsplit_once(':')
+                .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+            if !valid {
+                return Err(Error::new(format!(
+                    "invalid address `{}`, expected `host:port`",
+                    addr
+                )));
+            }
+        }
+        Ok(Self {
+            addr,
+            weight,
+            enabled,
+            priority,
+            labels,
+        })
+    }
+}
+
+fn default_weight() -> usize {
+    1
+}
+
+///
+/// 转发到后端使用的协议
+///
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Scheme {
+    #[default]
+    Http,
+    Https,
+}
+
+impl From<Scheme> for http::uri::Scheme {
+    fn from(scheme: Scheme) -> Self {
+        match scheme {
+            Scheme::Http => http::uri::Scheme::HTTP,
+            Scheme::Https => http::uri::Scheme::HTTPS,
+        }
+    }
+}
+
+///
+/// TCP健康检查配置
+///
+#[derive(Deserialize)]
+pub(crate) struct HealthCheck {
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    #[serde(default = "default_interval_secs", rename = "interval-secs")]
+    interval_secs: u64,
+    #[serde(default = "default_timeout_millis", rename = "timeout-millis")]
+    timeout_millis: u64,
+    #[serde(default = "default_threshold", rename = "consecutive-success")]
+    consecutive_success: usize,
+    #[serde(default = "default_threshold", rename = "consecutive-failure")]
+    consecutive_failure: usize,
+    #[serde(default)]
+    parallel: bool,
+}
+
+impl HealthCheck {
+    ///
+    /// 启用时为负载均衡器设置健康检查
+    ///
+    pub(crate) fn apply(&self, load_balancer: LoadBalancer) -> LoadBalancer {
+        if !self.enabled {
+            return load_balancer;
+        }
+        load_balancer
+            .with_health_check(
+                TcpHealthCheck::default()
+                    .with_connect_timeout(Duration::from_millis(self.timeout_millis))
+                    .with_consecutive_success(self.consecutive_success)
+                    .with_consecutive_failure(self.consecutive_failure),
+            )
+            .with_health_check_frequency(Duration::from_secs(self.interval_secs))
+            .with_health_check_parallel(self.parallel)
+    }
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            interval_secs: default_interval_secs(),
+            timeout_millis: default_timeout_millis(),
+            consecutive_success: default_threshold(),
+            consecutive_failure: default_threshold(),
+            parallel: false,
+        }
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_interval_secs() -> u64 {
+    10
+}
+
+fn default_timeout_millis() -> u64 {
+    1000
+}
+
+fn default_threshold() -> usize {
+    1
+}
+
+///
+/// 上游的通用配置, 所有解析器的上游都支持, 通过`#[serde(flatten)]`嵌入到上游配置中
+///
+#[derive(Deserialize)]
+pub(crate) struct UpstreamOptions {
+    #[serde(default, rename = "health-check")]
+    health_check: HealthCheck,
+    #[serde(default, rename = "outlier-detection")]
+    outlier_detection: Option<OutlierDetection>,
+    #[serde(default)]
+    sticky: Option<Sticky>,
+    #[serde(default, rename = "slow-start")]
+    slow_start: Option<SlowStart>,
+    #[serde(default, rename = "circuit-breaker")]
+    circuit_breaker: Option<CircuitBreaker>,
+    #[serde(
+        default = "default_overprovisioning_factor",
+        rename = "overprovisioning-factor"
+    )]
+    overprovisioning_factor: f64,
+}
+
+impl UpstreamOptions {
+    ///
+    /// 为负载均衡器设置健康检查、被动健康检查、会话保持、慢启动、熔断器以及超额配置系数
+    ///
+    pub(crate) fn apply(&self, load_balancer: LoadBalancer) -> LoadBalancer {
+        let load_balancer = self
+            .health_check
+            .apply(load_balancer)
+            .with_overprovisioning_factor(self.overprovisioning_factor);
+        let load_balancer = OutlierDetection::apply(self.outlier_detection.as_ref(), load_balancer);
+        let load_balancer = Sticky::apply(self.sticky.as_ref(), load_balancer);
+        let load_balancer = SlowStart::apply(self.slow_start.as_ref(), load_balancer);
+        CircuitBreaker::apply(self.circuit_breaker.as_ref(), load_balancer)
+    }
+}
+
+///
+/// 被动健康检查配置
+///
+#[derive(Deserialize)]
+pub(crate) struct OutlierDetection {
+    #[serde(default = "default_consecutive_errors", rename = "consecutive-errors")]
+    consecutive_errors: Option<usize>,
+    #[serde(default, rename = "error-rate")]
+    error_rate: Option<f64>,
+    #[serde(default = "default_min_requests", rename = "min-requests")]
+    min_requests: usize,
+    #[serde(default = "default_interval_secs", rename = "interval-secs")]
+    interval_secs: u64,
+    #[serde(default = "default_base_ejection_secs", rename = "base-ejection-secs")]
+    base_ejection_secs: u64,
+    #[serde(default = "default_max_ejection_secs", rename = "max-ejection-secs")]
+    max_ejection_secs: u64,
+    #[serde(
+        default = "default_max_ejection_percent",
+        rename = "max-ejection-percent"
+    )]
+    max_ejection_percent: f64,
+}
+
+impl OutlierDetection {
+    ///
+    /// 配置存在时为负载均衡器设置被动健康检查, 驱逐和恢复通过日志输出
+    ///
+    pub(crate) fn apply(
+        outlier_detection: Option<&Self>,
+        load_balancer: LoadBalancer,
+    ) -> LoadBalancer {
+        let Some(config) = outlier_detection else {
+            return load_balancer;
+        };
+        let name = load_balancer.name().to_string();
+        load_balancer.with_outlier_detection(
+            Detection::default()
+                .with_consecutive_errors(config.consecutive_errors)
+                .with_error_rate(config.error_rate)
+                .with_min_requests(config.min_requests)
+                .with_interval(Duration::from_secs(config.interval_secs))
+                .with_base_ejection(Duration::from_secs(config.base_ejection_secs))
+                .with_max_ejection(Duration::from_secs(config.max_ejection_secs))
+                .with_max_ejection_percent(config.max_ejection_percent)
+                .with_status_observe(LogHealthStatusObserve::new(name)),
+        )
+    }
+}
+
+fn default_consecutive_errors() -> Option<usize> {
+    Some(5)
+}
+
+fn default_min_requests() -> usize {
+    10
+}
+
+fn default_base_ejection_secs() -> u64 {
+    30
+}
+
+fn default_max_ejection_secs() -> u64 {
+    300
+}
+
+fn default_max_ejection_percent() -> f64 {
+    10.0
+}
+
+///
+/// 会话保持配置
+///
+#[derive(Deserialize)]
+pub(crate) struct Sticky {
+    #[serde(default = "default_cookie")]
+    cookie: String,
+    #[serde(default, rename = "ttl-secs")]
+    ttl_secs: Option<u64>,
+    #[serde(default = "default_path")]
+    path: String,
+    #[serde(default)]
+    domain: Option<String>,
+    #[serde(default)]
+    secure: bool,
+    #[serde(default = "default_enabled", rename = "http-only")]
+    http_only: bool,
+    #[serde(default, rename = "same-site")]
+    same_site: Option<SameSite>,
+    #[serde(default)]
+    secret: Option<String>,
+}
+
+impl Sticky {
+    ///
+    /// 配置存在时为负载均衡器设置会话保持
+    ///
+    pub(crate) fn apply(sticky: Option<&Self>, load_balancer: LoadBalancer) -> LoadBalancer {
+        let Some(config) = sticky else {
+            return load_balancer;
+        };
+        let mut sticky_session = StickySession::new(config.cookie.as_str())
+            .with_ttl(config.ttl_secs.map(Duration::from_secs))
+            .with_path(config.path.as_str())
+            .with_domain(config.domain.clone())
+            .with_secure(config.secure)
+            .with_http_only(config.http_only)
+            .with_same_site(config.same_site.map(SameSite::into));
+        if let Some(secret) = &config.secret {
+            sticky_session = sticky_session.with_secret(secret);
+        }
+        load_balancer.with_sticky_session(sticky_session)
+    }
+}
+
+#[derive(Deserialize, Clone, Copy)]
+enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl From<SameSite> for cookie::SameSite {
+    fn from(same_site: SameSite) -> Self {
+        match same_site {
+            SameSite::Strict => cookie::SameSite::Strict,
+            SameSite::Lax => cookie::SameSite::Lax,
+            SameSite::None => cookie::SameSite::None,
+        }
+    }
+}
+
+fn default_cookie() -> String {
+    String::from("SATEX_STICKY")
+}
+
+fn default_path() -> String {
+    String::from("/")
+}
+
+///
+/// 慢启动配置
+///
+#[derive(Deserialize)]
+pub(crate) struct SlowStart {
+    #[serde(rename = "window-secs")]
+    window_secs: u64,
+    #[serde(default = "default_aggression")]
+    aggression: f64,
+    #[serde(default = "default_min_weight_percent", rename = "min-weight-percent")]
+    min_weight_percent: f64,
+}
+
+impl SlowStart {
+    ///
+    /// 配置存在时为负载均衡器设置慢启动
+    ///
+    pub(crate) fn apply(slow_start: Option<&Self>, load_balancer: LoadBalancer) -> LoadBalancer {
+        let Some(config) = slow_start else {
+            return load_balancer;
+        };
+        load_balancer.with_slow_start(
+            crate::slow_start::SlowStart::new(Duration::from_secs(config.window_secs))
+                .with_aggression(config.aggression)
+                .with_min_weight_percent(config.min_weight_percent),
+        )
+    }
+}
+
+fn default_aggression() -> f64 {
+    1.0
+}
+
+fn default_min_weight_percent() -> f64 {
+    10.0
+}
+
+///
+/// 熔断器配置
+///
+#[derive(Deserialize)]
+pub(crate) struct CircuitBreaker {
+    #[serde(default, rename = "max-requests")]
+    max_requests: Option<usize>,
+    #[serde(default, rename = "max-pending")]
+    max_pending: Option<usize>,
+    #[serde(default, rename = "max-connections")]
+    max_connections: Option<usize>,
+    #[serde(
+        default = "default_consecutive_errors",
+        rename = "consecutive-failures"
+    )]
+    consecutive_failures: Option<usize>,
+    #[serde(default = "default_open_secs", rename = "open-secs")]
+    open_secs: u64,
+    #[serde(default = "default_half_open_requests", rename = "half-open-requests")]
+    half_open_requests: usize,
+}
+
+impl CircuitBreaker {
+    ///
+    /// 配置存在时为负载均衡器设置熔断器
+    ///
+    pub(crate) fn apply(
+        circuit_breaker: Option<&Self>,
+        load_balancer: LoadBalancer,
+    ) -> LoadBalancer {
+        let Some(config) = circuit_breaker else {
+            return load_balancer;
+        };
+        load_balancer.with_circuit_breaker(
+            crate::circuit_breaker::CircuitBreaker::default()
+                .with_max_requests(config.max_requests)
+                .with_max_pending(config.max_pending)
+                .with_max_connections(config.max_connections)
+                .with_consecutive_failures(config.consecutive_failures)
+                .with_open_duration(Duration::from_secs(config.open_secs))
+                .with_half_open_requests(config.half_open_requests),
+        )
+    }
+}
+
+fn default_open_secs() -> u64 {
+    30
+}
+
+fn default_half_open_requests() -> usize {
+    1
+}
+
+fn default_overprovisioning_factor() -> f64 {
+    DEFAULT_OVERPROVISIONING_FACTOR
+}
@@ -0,0 +1,88 @@
+use crate::discovery::consul::ConsulWatch;
+use crate::resolver::LoadBalancerResolver;
+use crate::resolver::config::{Policy, Scheme, UpstreamOptions};
+use crate::resolver::make::MakeLoadBalancerResolver;
+use crate::{Backends, LoadBalancer};
+use satex_core::Error;
+use satex_core::background::Supervisor;
+use satex_core::component::{Args, Configurable};
+use satex_macro::make;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub struct ConsulLoadBalancerResolver {
+    load_balancers: HashMap<String, Arc<LoadBalancer>>,
+}
+
+impl LoadBalancerResolver for ConsulLoadBalancerResolver {
+    fn find(&self, name: &str) -> Option<Arc<LoadBalancer>> {
+        self.load_balancers.get(name).cloned()
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.load_balancers.keys().cloned().collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct Service {
+    name: String,
+    #[serde(default)]
+    policy: Policy,
+    #[serde(default)]
+    scheme: Scheme,
+    #[serde(flatten)]
+    options: UpstreamOptions,
+}
+
+#[make(kind = Consul)]
+pub struct MakeConsulLoadBalancerResolver {
+    address: String,
+    #[serde(default)]
+    datacenter: Option<String>,
+    #[serde(default)]
+    token: Option<String>,
+    #[serde(default = "default_wait_secs", rename = "wait-secs")]
+    wait_secs: u64,
+    services: Vec<Service>,
+}
+
+fn default_wait_secs() -> u64 {
+    30
+}
+
+impl MakeLoadBalancerResolver for MakeConsulLoadBalancerResolver {
+    type Resolver = ConsulLoadBalancerResolver;
+
+    fn make(&self, args: Args, supervisor: &Supervisor) -> Result<Self::Resolver, Error> {
+        let config = Config::with_args(args)?;
+        let mut load_balancers = HashMap::with_capacity(config.services.len());
+        for service in config.services {
+            let (mut watch, discovery) = ConsulWatch::new(&config.address, &service.name);
+            watch = watch.with_wait(Duration::from_secs(config.wait_secs));
+            if let Some(datacenter) = &config.datacenter {
+                watch = watch.with_datacenter(datacenter);
+            }
+            if let Some(token) = &config.token {
+                watch = watch.with_token(token);
+            }
+            supervisor.spawn(format!("ConsulWatch - {}", service.name), Arc::new(watch));
+
+            // 服务发现只读取最近一次阻塞查询的结果, 可以频繁地刷新
+            let load_balancer =
+                LoadBalancer::new(Backends::new(discovery), service.policy.selector())
+                    .with_name(&service.name)
+                    .with_scheme(service.scheme.into())
+                    .with_update_frequency(Duration::from_secs(1));
+            let load_balancer = Arc::new(service.options.apply(load_balancer));
+            supervisor.spawn(
+                format!("LoadBalancer - {}", service.name),
+                load_balancer.clone(),
+            );
+            load_balancers.insert(service.name, load_balancer);
+        }
+        Ok(ConsulLoadBalancerResolver { load_balancers })
+    }
+}
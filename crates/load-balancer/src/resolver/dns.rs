@@ -0,0 +1,133 @@
+use crate::discovery::dns::{DnsDiscovery, DnsQuery, HickoryDnsLookup};
+use crate::resolver::LoadBalancerResolver;
+use crate::resolver::config::{Policy, Scheme, UpstreamOptions};
+use crate::resolver::make::MakeLoadBalancerResolver;
+use crate::{Backends, Labels, LoadBalancer};
+use satex_core::Error;
+use satex_core::background::Supervisor;
+use satex_core::component::{Args, Configurable};
+use satex_macro::make;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub struct DnsLoadBalancerResolver {
+    load_balancers: HashMap<String, Arc<LoadBalancer>>,
+}
+
+impl LoadBalancerResolver for DnsLoadBalancerResolver {
+    fn find(&self, name: &str) -> Option<Arc<LoadBalancer>> {
+        self.load_balancers.get(name).cloned()
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.load_balancers.keys().cloned().collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct Upstream {
+    name: String,
+    #[serde(default)]
+    policy: Policy,
+    #[serde(default)]
+    scheme: Scheme,
+    #[serde(default)]
+    host: Option<String>,
+    #[serde(default)]
+    srv: Option<String>,
+    #[serde(default = "default_weight")]
+    weight: usize,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+    #[serde(default = "default_min_ttl_secs", rename = "min-ttl-secs")]
+    min_ttl_secs: u64,
+    #[serde(default = "default_max_ttl_secs", rename = "max-ttl-secs")]
+    max_ttl_secs: u64,
+    #[serde(flatten)]
+    options: UpstreamOptions,
+}
+
+impl Upstream {
+    fn query(&self) -> Result<DnsQuery, Error> {
+        match (&self.host, &self.srv) {
+            (Some(host), None) => {
+                let (name, port) = host
+                    .rsplit_once(':')
+                    .and_then(|(name, port)| Some((name, port.parse::<u16>().ok()?)))
+                    .filter(|(name, _)| !name.is_empty())
+                    .ok_or_else(|| {
+                        Error::new(format!("invalid host `{}`, expected `host:port`", host))
+                    })?;
+                Ok(DnsQuery::Host {
+                    host: name.to_string(),
+                    port,
+                })
+            }
+            (None, Some(srv)) => Ok(DnsQuery::Srv(srv.clone())),
+            _ => Err(Error::new(format!(
+                "upstream `{}` must set exactly one of `host` and `srv`",
+                self.name
+            ))),
+        }
+    }
+}
+
+fn default_weight() -> usize {
+    1
+}
+
+fn default_min_ttl_secs() -> u64 {
+    5
+}
+
+fn default_max_ttl_secs() -> u64 {
+    60
+}
+
+#[make(kind = Dns)]
+pub struct MakeDnsLoadBalancerResolver {
+    #[serde(default, rename = "name-servers")]
+    name_servers: Vec<SocketAddr>,
+    upstreams: Vec<Upstream>,
+}
+
+impl MakeLoadBalancerResolver for MakeDnsLoadBalancerResolver {
+    type Resolver = DnsLoadBalancerResolver;
+
+    fn make(&self, args: Args, supervisor: &Supervisor) -> Result<Self::Resolver, Error> {
+        let config = Config::with_args(args)?;
+        let lookup = if config.name_servers.is_empty() {
+            HickoryDnsLookup::system()?
+        } else {
+            HickoryDnsLookup::with_name_servers(&config.name_servers)
+        };
+
+        let mut load_balancers = HashMap::with_capacity(config.upstreams.len());
+        for upstream in config.upstreams {
+            let min_ttl = Duration::from_secs(upstream.min_ttl_secs);
+            let discovery = DnsDiscovery::new(lookup.clone(), upstream.query()?)
+                .with_weight(upstream.weight)
+                .with_labels(Labels::from(upstream.labels.clone()))
+                .with_min_ttl(min_ttl)
+                .with_max_ttl(Duration::from_secs(upstream.max_ttl_secs));
+
+            // 按照最小TTL检查记录是否过期, 记录未过期时服务发现直接返回缓存的结果
+            let load_balancer =
+                LoadBalancer::new(Backends::new(discovery), upstream.policy.selector())
+                    .with_name(&upstream.name)
+                    .with_scheme(upstream.scheme.into())
+                    .with_update_frequency(min_ttl.max(Duration::from_secs(1)));
+            let load_balancer = Arc::new(upstream.options.apply(load_balancer));
+
+            supervisor.spawn(
+                format!("LoadBalancer - {}", upstream.name),
+                load_balancer.clone(),
+            );
+            load_balancers.insert(upstream.name, load_balancer);
+        }
+        Ok(DnsLoadBalancerResolver { load_balancers })
+    }
+}
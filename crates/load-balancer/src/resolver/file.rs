@@ -0,0 +1,255 @@
+use crate::discovery::Discovery;
+use crate::resolver::LoadBalancerResolver;
+use crate::resolver::config::{Addr, Policy, Scheme, UpstreamOptions};
+use crate::resolver::make::MakeLoadBalancerResolver;
+use crate::{Backend, Backends, LoadBalancer};
+use async_trait::async_trait;
+use satex_core::Error;
+use satex_core::background::Supervisor;
+use satex_core::component::{Args, Configurable};
+use satex_macro::make;
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+pub struct FileLoadBalancerResolver {
+    load_balancers: HashMap<String, Arc<LoadBalancer>>,
+}
+
+impl LoadBalancerResolver for FileLoadBalancerResolver {
+    fn find(&self, name: &str) -> Option<Arc<LoadBalancer>> {
+        self.load_balancers.get(name).cloned()
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.load_balancers.keys().cloned().collect()
+    }
+}
+
+///
+/// 上游定义文件, 支持`YAML`以及`JSON`格式
+///
+#[derive(Deserialize)]
+struct UpstreamFile {
+    upstreams: Vec<Upstream>,
+}
+
+#[derive(Deserialize)]
+struct Upstream {
+    name: String,
+    #[serde(default)]
+    policy: Policy,
+    #[serde(default)]
+    scheme: Scheme,
+    addrs: Vec<Addr>,
+    #[serde(flatten)]
+    options: UpstreamOptions,
+}
+
+impl UpstreamFile {
+    fn read(path: &Path) -> Result<(Self, Settings), Error> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Error::new(format!("read `{}` error: {}", path.display(), e)))?;
+        Self::parse(path, &content)
+    }
+
+    async fn read_async(path: &Path) -> Result<(Self, Settings), Error> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| Error::new(format!("read `{}` error: {}", path.display(), e)))?;
+        Self::parse(path, &content)
+    }
+
+    fn parse(path: &Path, content: &str) -> Result<(Self, Settings), Error> {
+        let value = serde_yaml::from_str::<Value>(content)
+            .map_err(|e| Error::new(format!("parse `{}` error: {}", path.display(), e)))?;
+        let settings = settings(&value);
+        let file = serde_yaml::from_value(value)
+            .map_err(|e| Error::new(format!("parse `{}` error: {}", path.display(), e)))?;
+        Ok((file, settings))
+    }
+}
+
+///
+/// 上游名称以及对应的后端地址
+///
+type Upstreams = Arc<HashMap<String, Vec<Addr>>>;
+
+///
+/// 上游名称以及除后端地址以外的配置, 这部分配置不支持热更新
+///
+type Settings = HashMap<String, Value>;
+
+fn settings(value: &Value) -> Settings {
+    value
+        .get("upstreams")
+        .and_then(Value::as_sequence)
+        .map(|upstreams| {
+            upstreams
+                .iter()
+                .filter_map(|upstream| {
+                    let mut upstream = upstream.as_mapping()?.clone();
+                    upstream.remove("addrs");
+                    let name = upstream.get("name")?.as_str()?.to_string();
+                    Some((name, Value::Mapping(upstream)))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+///
+/// 上游定义文件的缓存, 文件的修改时间变化时重新读取
+///
+struct FileSource {
+    path: PathBuf,
+    settings: Settings,
+    state: Mutex<(Option<SystemTime>, Upstreams)>,
+}
+
+impl FileSource {
+    fn new(
+        path: PathBuf,
+        modified: Option<SystemTime>,
+        settings: Settings,
+        addrs: HashMap<String, Vec<Addr>>,
+    ) -> Self {
+        Self {
+            path,
+            settings,
+            state: Mutex::new((modified, Arc::new(addrs))),
+        }
+    }
+
+    async fn load(&self) -> Result<Upstreams, Error> {
+        let mut state = self.state.lock().await;
+        let modified = modified(&self.path, tokio::fs::metadata(&self.path).await)?;
+        if state.0 != modified {
+            let (file, settings) = UpstreamFile::read_async(&self.path).await?;
+            info!("upstream file `{}` reloaded", self.path.display());
+            self.check(&settings);
+            let addrs = file
+                .upstreams
+                .into_iter()
+                .map(|upstream| (upstream.name, upstream.addrs))
+                .collect();
+            *state = (modified, Arc::new(addrs));
+        }
+        Ok(state.1.clone())
+    }
+
+    ///
+    /// 新增的上游以及策略、协议、健康检查等配置的变化只有在重新加载配置后生效
+    ///
+    fn check(&self, settings: &Settings) {
+        for (name, setting) in settings {
+            match self.settings.get(name) {
+                None => warn!(
+                    "upstream `{}` added to `{}` is ignored until the configuration is reloaded",
+                    name,
+                    self.path.display()
+                ),
+                Some(previous) if previous != setting => warn!(
+                    "changes of upstream `{}` in `{}` other than `addrs` are ignored until the configuration is reloaded",
+                    name,
+                    self.path.display()
+                ),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn modified(path: &Path, metadata: std::io::Result<Metadata>) -> Result<Option<SystemTime>, Error> {
+    metadata
+        .map(|metadata| metadata.modified().ok())
+        .map_err(|e| Error::new(format!("read `{}` metadata error: {}", path.display(), e)))
+}
+
+///
+/// 从上游定义文件中读取后端服务, 地址的`enabled`会写入启用状态表
+///
+/// 文件读取或者解析失败、上游从文件中删除时返回错误, 负载均衡器会继续使用上一次的后端集合。
+///
+pub struct FileDiscovery {
+    source: Arc<FileSource>,
+    name: String,
+}
+
+#[async_trait]
+impl Discovery for FileDiscovery {
+    async fn discover(&self) -> Result<(BTreeSet<Backend>, HashMap<u64, bool>), Error> {
+        let upstreams = self.source.load().await?;
+        let addrs = upstreams.get(&self.name).ok_or_else(|| {
+            Error::new(format!(
+                "upstream `{}` not found in `{}`",
+                self.name,
+                self.source.path.display()
+            ))
+        })?;
+        let mut backends = BTreeSet::new();
+        let mut enablement = HashMap::new();
+        for addr in addrs.iter() {
+            for backend in addr.to_static().lookup().await? {
+                enablement.insert(backend.key(), addr.enabled);
+                backends.insert(backend);
+            }
+        }
+        Ok((backends, enablement))
+    }
+}
+
+#[make(kind = File)]
+pub struct MakeFileLoadBalancerResolver {
+    path: PathBuf,
+    #[serde(default = "default_interval_secs", rename = "interval-secs")]
+    interval_secs: u64,
+}
+
+fn default_interval_secs() -> u64 {
+    5
+}
+
+impl MakeLoadBalancerResolver for MakeFileLoadBalancerResolver {
+    type Resolver = FileLoadBalancerResolver;
+
+    fn make(&self, args: Args, supervisor: &Supervisor) -> Result<Self::Resolver, Error> {
+        let config = Config::with_args(args)?;
+        let modified = modified(&config.path, std::fs::metadata(&config.path))?;
+        let (file, settings) = UpstreamFile::read(&config.path)?;
+
+        // 上游的名称、策略以及健康检查只在创建时读取, 之后只刷新后端地址
+        let mut upstreams = file.upstreams;
+        let addrs = upstreams
+            .iter_mut()
+            .map(|upstream| (upstream.name.clone(), std::mem::take(&mut upstream.addrs)))
+            .collect();
+        let source = Arc::new(FileSource::new(config.path, modified, settings, addrs));
+
+        let mut load_balancers = HashMap::with_capacity(upstreams.len());
+        for upstream in upstreams {
+            let discovery = FileDiscovery {
+                source: source.clone(),
+                name: upstream.name.clone(),
+            };
+            let load_balancer =
+                LoadBalancer::new(Backends::new(discovery), upstream.policy.selector())
+                    .with_name(&upstream.name)
+                    .with_scheme(upstream.scheme.into())
+                    .with_update_frequency(Duration::from_secs(config.interval_secs.max(1)));
+            let load_balancer = Arc::new(upstream.options.apply(load_balancer));
+            supervisor.spawn(
+                format!("LoadBalancer - {}", upstream.name),
+                load_balancer.clone(),
+            );
+            load_balancers.insert(upstream.name, load_balancer);
+        }
+        Ok(FileLoadBalancerResolver { load_balancers })
+    }
+}
@@ -1,12 +1,17 @@
 use crate::resolver::{ArcLoadBalancerResolver, LoadBalancerResolver};
 use satex_core::Error;
+use satex_core::background::Supervisor;
 use satex_core::component::Args;
 use satex_core::make::Make;
 use std::sync::Arc;
 
 pub trait MakeLoadBalancerResolver: Make {
     type Resolver: LoadBalancerResolver;
-    fn make(&self, args: Args) -> Result<Self::Resolver, Error>;
+
+    ///
+    /// 创建解析器, 负载均衡器等后台任务通过`supervisor`启动
+    ///
+    fn make(&self, args: Args, supervisor: &Supervisor) -> Result<Self::Resolver, Error>;
 }
 
 #[derive(Clone)]
@@ -43,8 +48,10 @@ where
 {
     type Resolver = ArcLoadBalancerResolver;
 
-    fn make(&self, args: Args) -> Result<Self::Resolver, Error> {
-        self.0.make(args).map(ArcLoadBalancerResolver::new)
+    fn make(&self, args: Args, supervisor: &Supervisor) -> Result<Self::Resolver, Error> {
+        self.0
+            .make(args, supervisor)
+            .map(ArcLoadBalancerResolver::new)
     }
 }
 
@@ -57,7 +64,7 @@ impl Make for ArcMakeLoadBalancerResolver {
 impl MakeLoadBalancerResolver for ArcMakeLoadBalancerResolver {
     type Resolver = ArcLoadBalancerResolver;
 
-    fn make(&self, args: Args) -> Result<Self::Resolver, Error> {
-        self.0.make(args)
+    fn make(&self, args: Args, supervisor: &Supervisor) -> Result<Self::Resolver, Error> {
+        self.0.make(args, supervisor)
     }
 }
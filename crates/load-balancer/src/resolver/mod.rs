@@ -1,6 +1,13 @@
+mod config;
+mod consul;
+mod dns;
+mod file;
 mod make;
 mod r#static;
 
+pub use consul::*;
+pub use dns::*;
+pub use file::*;
 pub use make::*;
 pub use r#static::*;
 
@@ -9,6 +16,11 @@ use std::sync::Arc;
 
 pub trait LoadBalancerResolver {
     fn find(&self, name: &str) -> Option<Arc<LoadBalancer>>;
+
+    /// 所有上游的名称
+    fn names(&self) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 #[derive(Clone)]
@@ -21,12 +33,28 @@ impl ArcLoadBalancerResolver {
     {
         Self(Arc::new(resolver))
     }
+
+    ///
+    /// 重新加载配置时, 新创建的上游继承上一次同名上游中仍然存在的后端的健康状态
+    ///
+    pub fn inherit(&self, previous: &ArcLoadBalancerResolver) {
+        for name in self.names() {
+            if let (Some(load_balancer), Some(previous)) = (self.find(&name), previous.find(&name))
+            {
+                load_balancer.inherit(&previous);
+            }
+        }
+    }
 }
 
 impl LoadBalancerResolver for ArcLoadBalancerResolver {
     fn find(&self, name: &str) -> Option<Arc<LoadBalancer>> {
         self.0.find(name)
     }
+
+    fn names(&self) -> Vec<String> {
+        self.0.names()
+    }
 }
 
 #[derive(Default)]
@@ -51,4 +79,11 @@ impl LoadBalancerResolver for CompositeLoadBalancerResolver {
         }
         None
     }
+
+    fn names(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .flat_map(|resolver| resolver.names())
+            .collect()
+    }
 }
@@ -1,18 +1,21 @@
-use crate::discovery::StaticFixedDiscovery;
-use crate::health_check::tcp::TcpHealthCheck;
-use crate::resolver::make::MakeLoadBalancerResolver;
+use crate::discovery::StaticLookupDiscovery;
 use crate::resolver::LoadBalancerResolver;
-use crate::selector::{BoxSelector, Consistent, Random, RoundRobin};
-use crate::{Backend, Backends, LoadBalancer};
-use satex_core::background::background_task;
-use satex_core::component::{Args, Configurable};
+use crate::resolver::config::{Addr, Policy, Scheme, UpstreamOptions};
+use crate::resolver::make::MakeLoadBalancerResolver;
+use crate::{Backends, LoadBalancer};
 use satex_core::Error;
+use satex_core::background::Supervisor;
+use satex_core::component::{Args, Configurable};
 use satex_macro::make;
 use serde::Deserialize;
-use std::collections::{BTreeSet, HashMap};
-use std::net::SocketAddr;
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::spawn;
+use std::time::Duration;
+
+///
+/// 包含主机名的上游默认的服务发现刷新间隔
+///
+const DEFAULT_REFRESH_INTERVAL_SECS: u64 = 30;
 
 pub struct StaticLoadBalancerResolver {
     load_balancers: HashMap<String, Arc<LoadBalancer>>,
@@ -22,6 +25,10 @@ impl LoadBalancerResolver for StaticLoadBalancerResolver {
     fn find(&self, name: &str) -> Option<Arc<LoadBalancer>> {
         self.load_balancers.get(name).cloned()
     }
+
+    fn names(&self) -> Vec<String> {
+        self.load_balancers.keys().cloned().collect()
+    }
 }
 
 #[derive(Deserialize)]
@@ -29,28 +36,13 @@ struct Upstream {
     name: String,
     #[serde(default)]
     policy: Policy,
-    addrs: Vec<SocketAddr>,
-    #[serde(default, rename = "health-check")]
-    health_check: HealthCheck,
-}
-
-#[derive(Deserialize, Default)]
-enum Policy {
-    #[default]
-    RoundRobin,
-    Random,
-    Consistent,
-}
-
-#[derive(Deserialize)]
-struct HealthCheck {
-    enabled: bool,
-}
-
-impl Default for HealthCheck {
-    fn default() -> Self {
-        Self { enabled: true }
-    }
+    #[serde(default)]
+    scheme: Scheme,
+    addrs: Vec<Addr>,
+    #[serde(default, rename = "refresh-interval-secs")]
+    refresh_interval_secs: Option<u64>,
+    #[serde(flatten)]
+    options: UpstreamOptions,
 }
 
 #[make(kind = Static, shortcut_mode = Sequence)]
@@ -61,39 +53,48 @@ pub struct MakeStaticLoadBalancerResolver {
 impl MakeLoadBalancerResolver for MakeStaticLoadBalancerResolver {
     type Resolver = StaticLoadBalancerResolver;
 
-    fn make(&self, args: Args) -> Result<Self::Resolver, Error> {
+    fn make(&self, args: Args, supervisor: &Supervisor) -> Result<Self::Resolver, Error> {
         let config = Config::with_args(args)?;
         let load_balancers = config
             .upstreams
             .into_iter()
             .map(|upstream| {
-                let backends = upstream
-                    .addrs
-                    .into_iter()
-                    .map(Backend::new)
-                    .collect::<BTreeSet<_>>();
-
-                let selector = match upstream.policy {
-                    Policy::RoundRobin => BoxSelector::new(RoundRobin::new(&backends)),
-                    Policy::Random => BoxSelector::new(Random::new(&backends)),
-                    Policy::Consistent => BoxSelector::new(Consistent::new(&backends)),
-                };
-
-                let backends = Backends::new(StaticFixedDiscovery::new(backends))
-                    .with_health_check(TcpHealthCheck::default());
-                let load_balancer = Arc::new(LoadBalancer::new(backends, selector));
-
-                if upstream.health_check.enabled {
-                    let task = background_task(
-                        format!("LoadBalancer - {}", upstream.name),
-                        load_balancer.clone(),
-                    );
-                    spawn(task);
-                }
-
+                let load_balancer = Arc::new(make_load_balancer(&upstream));
+                supervisor.spawn(
+                    format!("LoadBalancer - {}", upstream.name),
+                    load_balancer.clone(),
+                );
                 (upstream.name, load_balancer)
             })
             .collect::<HashMap<_, _>>();
         Ok(StaticLoadBalancerResolver { load_balancers })
     }
 }
+
+fn make_load_balancer(upstream: &Upstream) -> LoadBalancer {
+    let discovery = StaticLookupDiscovery::new(
+        upstream
+            .addrs
+            .iter()
+            .filter(|addr| addr.enabled)
+            .map(Addr::to_static),
+    );
+    let mut load_balancer = LoadBalancer::new(Backends::new(discovery), upstream.policy.selector())
+        .with_name(&upstream.name)
+        .with_scheme(upstream.scheme.into());
+
+    // 只有静态IP的上游不需要定时刷新
+    let refresh_interval_secs = upstream.refresh_interval_secs.or_else(|| {
+        upstream
+            .addrs
+            .iter()
+            .any(Addr::is_hostname)
+            .then_some(DEFAULT_REFRESH_INTERVAL_SECS)
+    });
+    if let Some(refresh_interval_secs) = refresh_interval_secs {
+        load_balancer =
+            load_balancer.with_update_frequency(Duration::from_secs(refresh_interval_secs));
+    }
+
+    upstream.options.apply(load_balancer)
+}
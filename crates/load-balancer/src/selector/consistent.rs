@@ -12,10 +12,10 @@ struct Inner {
 }
 
 impl Inner {
-    fn build(backends: &BTreeSet<Backend>) -> Self {
+    fn build(backends: &BTreeSet<Backend>, weight: &dyn Fn(&Backend) -> usize) -> Self {
         let buckets = backends
             .iter()
-            .map(|b| Bucket::new(b.addr, b.weight as u32))
+            .map(|b| Bucket::new(b.addr, weight(b) as u32))
             .collect::<Vec<_>>();
         let backends = backends.iter().map(|b| (b.addr, b.clone())).collect();
         Self {
@@ -30,15 +30,17 @@ pub struct KetamaHashing(ArcSwap<Inner>);
 
 impl KetamaHashing {
     pub fn new(backends: &BTreeSet<Backend>) -> Self {
-        Self(ArcSwap::new(Arc::new(Inner::build(backends))))
+        Self(ArcSwap::new(Arc::new(Inner::build(backends, &|backend| {
+            backend.weight
+        }))))
     }
 }
 
 impl Selector for KetamaHashing {
     type Iter = OwnedNodeIterator;
 
-    fn update(&self, backends: &BTreeSet<Backend>) {
-        self.0.store(Arc::new(Inner::build(backends)))
+    fn update_weighted(&self, backends: &BTreeSet<Backend>, weight: &dyn Fn(&Backend) -> usize) {
+        self.0.store(Arc::new(Inner::build(backends, weight)))
     }
 
     fn iter(&self, key: &[u8]) -> Self::Iter {
@@ -0,0 +1,108 @@
+use crate::Backend;
+use crate::selector::Selector;
+use crate::selector::p2c::P2cIterator;
+use arc_swap::ArcSwap;
+use std::collections::{BTreeSet, HashMap};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct Inner {
+    backends: Arc<[Backend]>,
+    weights: Box<[usize]>,
+    inflight: HashMap<SocketAddr, Arc<AtomicUsize>>,
+}
+
+impl Inner {
+    fn build(
+        backends: &BTreeSet<Backend>,
+        weight: &dyn Fn(&Backend) -> usize,
+        old: Option<&Inner>,
+    ) -> Self {
+        let backends = backends.iter().cloned().collect::<Arc<[_]>>();
+        let weights = backends.iter().map(weight).collect();
+        // 保留仍然存在的后端的进行中请求数
+        let inflight = backends
+            .iter()
+            .map(|backend| {
+                let counter = old
+                    .and_then(|old| old.inflight.get(&backend.addr).cloned())
+                    .unwrap_or_default();
+                (backend.addr, counter)
+            })
+            .collect();
+        Self {
+            backends,
+            weights,
+            inflight,
+        }
+    }
+
+    fn inflight(&self, backend: &Backend) -> usize {
+        self.inflight
+            .get(&backend.addr)
+            .map(|counter| counter.load(Ordering::Relaxed))
+            .unwrap_or_default()
+    }
+
+    ///
+    /// 按照权重折算后的进行中请求数
+    ///
+    fn cost(&self, index: usize) -> f64 {
+        self.inflight(&self.backends[index]) as f64 / self.weights[index].max(1) as f64
+    }
+}
+
+///
+/// 最少进行中请求选择算法
+///
+/// 每次随机选择两个后端, 优先使用按照权重折算后进行中请求更少的一个(Power of Two Choices)。
+/// 进行中的请求数通过[`Selector::on_start`]和[`Selector::on_finish`]维护。
+///
+pub struct LeastRequest(ArcSwap<Inner>);
+
+impl LeastRequest {
+    pub fn new(backends: &BTreeSet<Backend>) -> Self {
+        Self(ArcSwap::new(Arc::new(Inner::build(
+            backends,
+            &|backend| backend.weight,
+            None,
+        ))))
+    }
+
+    ///
+    /// 后端服务当前进行中的请求数
+    ///
+    pub fn inflight(&self, backend: &Backend) -> usize {
+        self.0.load().inflight(backend)
+    }
+}
+
+impl Selector for LeastRequest {
+    type Iter = P2cIterator;
+
+    fn update_weighted(&self, backends: &BTreeSet<Backend>, weight: &dyn Fn(&Backend) -> usize) {
+        let old = self.0.load();
+        self.0
+            .store(Arc::new(Inner::build(backends, weight, Some(&old))))
+    }
+
+    fn iter(&self, _key: &[u8]) -> Self::Iter {
+        let inner = self.0.load_full();
+        P2cIterator::new(inner.backends.clone(), |index| inner.cost(index))
+    }
+
+    fn on_start(&self, backend: &Backend) {
+        if let Some(counter) = self.0.load().inflight.get(&backend.addr) {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn on_finish(&self, backend: &Backend) {
+        if let Some(counter) = self.0.load().inflight.get(&backend.addr) {
+            let _ = counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+                count.checked_sub(1)
+            });
+        }
+    }
+}
@@ -1,10 +1,14 @@
 mod algorithm;
 mod consistent;
+mod least_request;
+mod p2c;
+mod peak_ewma;
 mod weighted;
 
-use crate::selector::weighted::Weighted;
 use crate::Backend;
+use crate::selector::weighted::Weighted;
 use std::collections::BTreeSet;
+use std::time::Duration;
 
 /// Random selection on weighted backends
 pub type Random = Weighted<algorithm::Random>;
@@ -15,6 +19,10 @@ pub type RoundRobin = Weighted<algorithm::RoundRobin>;
 /// Consistent Ketama hashing on weighted backends
 pub type Consistent = consistent::KetamaHashing;
 
+pub use least_request::LeastRequest;
+pub use p2c::P2cIterator;
+pub use peak_ewma::PeakEwma;
+
 pub trait BackendIter {
     fn next(&mut self) -> Option<&Backend>;
 }
@@ -30,9 +38,24 @@ impl BackendIter for BoxBackendIter {
 pub trait Selector {
     type Iter: BackendIter;
 
-    fn update(&self, backends: &BTreeSet<Backend>);
+    /// 更新后端集合, 后端的权重为`backend.weight`
+    fn update(&self, backends: &BTreeSet<Backend>) {
+        self.update_weighted(backends, &|backend| backend.weight)
+    }
+
+    /// 更新后端集合, `weight`返回后端的有效权重, 例如: 慢启动中的后端按照比例降低的权重
+    fn update_weighted(&self, backends: &BTreeSet<Backend>, weight: &dyn Fn(&Backend) -> usize);
 
     fn iter(&self, key: &[u8]) -> Self::Iter;
+
+    /// 请求开始发送到选中的后端
+    fn on_start(&self, _backend: &Backend) {}
+
+    /// 发送到后端的请求结束, 响应体读取完成或者出错
+    fn on_finish(&self, _backend: &Backend) {}
+
+    /// 收到后端的响应头, `latency`为请求发送到收到响应头的时间
+    fn on_latency(&self, _backend: &Backend, _latency: Duration) {}
 }
 
 pub(crate) struct Map<S>(S);
@@ -50,13 +73,25 @@ where
 {
     type Iter = BoxBackendIter;
 
-    fn update(&self, backends: &BTreeSet<Backend>) {
-        self.0.update(backends);
+    fn update_weighted(&self, backends: &BTreeSet<Backend>, weight: &dyn Fn(&Backend) -> usize) {
+        self.0.update_weighted(backends, weight);
     }
 
     fn iter(&self, key: &[u8]) -> Self::Iter {
         BoxBackendIter(Box::new(self.0.iter(key)))
     }
+
+    fn on_start(&self, backend: &Backend) {
+        self.0.on_start(backend)
+    }
+
+    fn on_finish(&self, backend: &Backend) {
+        self.0.on_finish(backend)
+    }
+
+    fn on_latency(&self, backend: &Backend, latency: Duration) {
+        self.0.on_latency(backend, latency)
+    }
 }
 
 pub struct BoxSelector(Box<dyn Selector<Iter = BoxBackendIter> + Send + Sync>);
@@ -74,11 +109,23 @@ impl BoxSelector {
 impl Selector for BoxSelector {
     type Iter = BoxBackendIter;
 
-    fn update(&self, backends: &BTreeSet<Backend>) {
-        self.0.update(backends)
+    fn update_weighted(&self, backends: &BTreeSet<Backend>, weight: &dyn Fn(&Backend) -> usize) {
+        self.0.update_weighted(backends, weight)
     }
 
     fn iter(&self, key: &[u8]) -> Self::Iter {
         self.0.iter(key)
     }
+
+    fn on_start(&self, backend: &Backend) {
+        self.0.on_start(backend)
+    }
+
+    fn on_finish(&self, backend: &Backend) {
+        self.0.on_finish(backend)
+    }
+
+    fn on_latency(&self, backend: &Backend, latency: Duration) {
+        self.0.on_latency(backend, latency)
+    }
 }
@@ -0,0 +1,67 @@
+use crate::Backend;
+use crate::selector::BackendIter;
+use rand::Rng;
+use std::sync::Arc;
+
+///
+/// `Power of Two Choices`的迭代器
+///
+/// 随机选择两个后端并按照代价从低到高依次返回, 之后按照顺序返回其余的后端。
+///
+pub struct P2cIterator {
+    backends: Arc<[Backend]>,
+    candidates: [usize; 2],
+    position: usize,
+}
+
+impl P2cIterator {
+    ///
+    /// 创建迭代器
+    ///
+    /// # Arguments
+    ///
+    /// * `backends`: 所有的后端
+    /// * `cost`: 根据后端下标计算代价
+    ///
+    pub(crate) fn new<F>(backends: Arc<[Backend]>, cost: F) -> Self
+    where
+        F: Fn(usize) -> f64,
+    {
+        let len = backends.len();
+        let mut candidates = [0, 0];
+        if len > 1 {
+            let mut rng = rand::rng();
+            let first = rng.random_range(0..len);
+            let second = (first + rng.random_range(1..len)) % len;
+            candidates = if cost(first) <= cost(second) {
+                [first, second]
+            } else {
+                [second, first]
+            };
+        }
+        Self {
+            backends,
+            candidates,
+            position: 0,
+        }
+    }
+}
+
+impl BackendIter for P2cIterator {
+    fn next(&mut self) -> Option<&Backend> {
+        let len = self.backends.len();
+        let candidates = &self.candidates[..len.min(2)];
+        while self.position < candidates.len() + len {
+            let position = self.position;
+            self.position += 1;
+            if let Some(index) = candidates.get(position) {
+                return Some(&self.backends[*index]);
+            }
+            let index = position - candidates.len();
+            if !candidates.contains(&index) {
+                return Some(&self.backends[index]);
+            }
+        }
+        None
+    }
+}
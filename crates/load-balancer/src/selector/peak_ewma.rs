@@ -0,0 +1,209 @@
+use crate::Backend;
+use crate::selector::Selector;
+use crate::selector::p2c::P2cIterator;
+use arc_swap::ArcSwap;
+use std::collections::{BTreeSet, HashMap};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+///
+/// 没有延迟数据时使用的默认延迟
+///
+const DEFAULT_RTT: Duration = Duration::from_millis(30);
+
+///
+/// 延迟的衰减周期
+///
+const DEFAULT_DECAY: Duration = Duration::from_secs(10);
+
+struct Stats {
+    outstanding: AtomicUsize,
+    // 延迟的移动平均值(纳秒)以及最后一次更新的时间
+    ewma: Mutex<(f64, Instant)>,
+}
+
+impl Stats {
+    fn new(rtt: Duration) -> Self {
+        Self {
+            outstanding: AtomicUsize::new(0),
+            ewma: Mutex::new((rtt.as_nanos() as f64, Instant::now())),
+        }
+    }
+
+    ///
+    /// 衰减到当前时间的延迟, 长时间没有请求的后端延迟会逐渐降低, 从而重新获得流量
+    ///
+    fn ewma(&self, now: Instant, decay: f64) -> f64 {
+        let Ok(mut ewma) = self.ewma.lock() else {
+            return 0.0;
+        };
+        let elapsed = now.saturating_duration_since(ewma.1).as_nanos() as f64;
+        ewma.0 *= (-elapsed / decay).exp();
+        ewma.1 = now;
+        ewma.0
+    }
+
+    fn observe(&self, rtt: Duration, now: Instant, decay: f64) {
+        let Ok(mut ewma) = self.ewma.lock() else {
+            return;
+        };
+        let rtt = rtt.as_nanos() as f64;
+        if rtt > ewma.0 {
+            // 延迟升高时立即生效
+            ewma.0 = rtt;
+        } else {
+            let elapsed = now.saturating_duration_since(ewma.1).as_nanos() as f64;
+            let weight = (-elapsed / decay).exp();
+            ewma.0 = ewma.0 * weight + rtt * (1.0 - weight);
+        }
+        ewma.1 = now;
+    }
+}
+
+struct Inner {
+    backends: Arc<[Backend]>,
+    // 后端的统计数据以及有效权重
+    stats: HashMap<SocketAddr, (Arc<Stats>, usize)>,
+}
+
+impl Inner {
+    fn build(
+        backends: &BTreeSet<Backend>,
+        weight: &dyn Fn(&Backend) -> usize,
+        old: Option<&Inner>,
+        default_rtt: Duration,
+    ) -> Self {
+        let backends = backends.iter().cloned().collect::<Arc<[_]>>();
+        let stats = backends
+            .iter()
+            .map(|backend| {
+                let stats = old
+                    .and_then(|old| old.stats.get(&backend.addr))
+                    .map(|(stats, _)| stats.clone())
+                    .unwrap_or_else(|| Arc::new(Stats::new(default_rtt)));
+                (backend.addr, (stats, weight(backend)))
+            })
+            .collect();
+        Self { backends, stats }
+    }
+}
+
+///
+/// 基于延迟峰值指数加权移动平均(Peak EWMA)的选择算法
+///
+/// 每个后端的代价为`延迟 * (进行中的请求数 + 1) / 权重`, 每次随机选择两个后端并优先使用代价更低的一个。
+/// 延迟通过[`Selector::on_latency`]更新, 新的延迟高于平均值时立即生效, 低于平均值时按照衰减周期平滑。
+///
+pub struct PeakEwma {
+    inner: ArcSwap<Inner>,
+    default_rtt: Duration,
+    decay: Duration,
+}
+
+impl PeakEwma {
+    pub fn new(backends: &BTreeSet<Backend>) -> Self {
+        Self {
+            inner: ArcSwap::new(Arc::new(Inner::build(
+                backends,
+                &|backend| backend.weight,
+                None,
+                DEFAULT_RTT,
+            ))),
+            default_rtt: DEFAULT_RTT,
+            decay: DEFAULT_DECAY,
+        }
+    }
+
+    /// 设置没有延迟数据时使用的默认延迟, 已有的后端同样使用新的默认延迟
+    pub fn with_default_rtt(mut self, default_rtt: Duration) -> Self {
+        let inner = self.inner.load();
+        let stats = inner
+            .stats
+            .iter()
+            .map(|(addr, (_, weight))| (*addr, (Arc::new(Stats::new(default_rtt)), *weight)))
+            .collect();
+        self.inner = ArcSwap::from_pointee(Inner {
+            backends: inner.backends.clone(),
+            stats,
+        });
+        self.default_rtt = default_rtt;
+        self
+    }
+
+    /// 设置延迟的衰减周期
+    pub fn with_decay(mut self, decay: Duration) -> Self {
+        self.decay = decay;
+        self
+    }
+
+    ///
+    /// 后端服务当前的代价, 后端不存在时返回`None`
+    ///
+    pub fn cost(&self, backend: &Backend) -> Option<f64> {
+        let inner = self.inner.load();
+        inner
+            .stats
+            .get(&backend.addr)
+            .map(|(stats, weight)| self.cost_of(stats, *weight, Instant::now()))
+    }
+
+    fn cost_of(&self, stats: &Stats, weight: usize, now: Instant) -> f64 {
+        let ewma = stats.ewma(now, self.decay_nanos());
+        let outstanding = stats.outstanding.load(Ordering::Relaxed) as f64;
+        ewma * (outstanding + 1.0) / weight.max(1) as f64
+    }
+
+    fn decay_nanos(&self) -> f64 {
+        (self.decay.as_nanos() as f64).max(1.0)
+    }
+}
+
+impl Selector for PeakEwma {
+    type Iter = P2cIterator;
+
+    fn update_weighted(&self, backends: &BTreeSet<Backend>, weight: &dyn Fn(&Backend) -> usize) {
+        let old = self.inner.load();
+        self.inner.store(Arc::new(Inner::build(
+            backends,
+            weight,
+            Some(&old),
+            self.default_rtt,
+        )))
+    }
+
+    fn iter(&self, _key: &[u8]) -> Self::Iter {
+        let inner = self.inner.load_full();
+        let now = Instant::now();
+        P2cIterator::new(inner.backends.clone(), |index| {
+            inner
+                .stats
+                .get(&inner.backends[index].addr)
+                .map(|(stats, weight)| self.cost_of(stats, *weight, now))
+                .unwrap_or_default()
+        })
+    }
+
+    fn on_start(&self, backend: &Backend) {
+        if let Some((stats, _)) = self.inner.load().stats.get(&backend.addr) {
+            stats.outstanding.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn on_finish(&self, backend: &Backend) {
+        if let Some((stats, _)) = self.inner.load().stats.get(&backend.addr) {
+            let _ = stats
+                .outstanding
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+                    count.checked_sub(1)
+                });
+        }
+    }
+
+    fn on_latency(&self, backend: &Backend, latency: Duration) {
+        if let Some((stats, _)) = self.inner.load().stats.get(&backend.addr) {
+            stats.observe(latency, Instant::now(), self.decay_nanos());
+        }
+    }
+}
@@ -13,12 +13,12 @@ struct Inner<H> {
 }
 
 impl<H: Algorithm> Inner<H> {
-    fn build(backends: &BTreeSet<Backend>) -> Self {
+    fn build(backends: &BTreeSet<Backend>, weight: &dyn Fn(&Backend) -> usize) -> Self {
         let mut backends = Vec::from_iter(backends.iter().cloned());
         backends.sort_unstable();
         let mut weighted = Vec::with_capacity(backends.len());
         for (index, b) in backends.iter().enumerate() {
-            for _ in 0..b.weight {
+            for _ in 0..weight(b) {
                 weighted.push(index as u16);
             }
         }
@@ -34,15 +34,17 @@ pub struct Weighted<H = FnvHasher>(ArcSwap<Inner<H>>);
 
 impl<H: Algorithm> Weighted<H> {
     pub fn new(backends: &BTreeSet<Backend>) -> Self {
-        Self(ArcSwap::new(Arc::new(Inner::build(backends))))
+        Self(ArcSwap::new(Arc::new(Inner::build(backends, &|backend| {
+            backend.weight
+        }))))
     }
 }
 
 impl<H: Algorithm> Selector for Weighted<H> {
     type Iter = WeightedIterator<H>;
 
-    fn update(&self, backends: &BTreeSet<Backend>) {
-        self.0.store(Arc::new(Inner::build(backends)))
+    fn update_weighted(&self, backends: &BTreeSet<Backend>, weight: &dyn Fn(&Backend) -> usize) {
+        self.0.store(Arc::new(Inner::build(backends, weight)))
     }
 
     fn iter(&self, key: &[u8]) -> Self::Iter {
@@ -0,0 +1,62 @@
+//!
+//! 慢启动
+//!
+use std::time::Duration;
+
+///
+/// 慢启动, 新加入或者恢复接收流量的后端在`window`时间内逐渐增加有效权重
+///
+/// 有效权重的比例为`max(min_weight_percent / 100, (elapsed / window) ^ (1 / aggression))`,
+/// `aggression`为`1.0`时线性增加, 大于`1.0`时前期增加更快。
+///
+#[derive(Debug, Clone)]
+pub struct SlowStart {
+    window: Duration,
+    aggression: f64,
+    min_weight_percent: f64,
+}
+
+impl SlowStart {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            aggression: 1.0,
+            min_weight_percent: 10.0,
+        }
+    }
+
+    pub fn with_aggression(mut self, aggression: f64) -> Self {
+        self.aggression = aggression;
+        self
+    }
+
+    pub fn with_min_weight_percent(mut self, min_weight_percent: f64) -> Self {
+        self.min_weight_percent = min_weight_percent;
+        self
+    }
+
+    ///
+    /// 慢启动期间更新有效权重的间隔
+    ///
+    pub(crate) fn interval(&self) -> Duration {
+        (self.window / 20).max(Duration::from_millis(10))
+    }
+
+    ///
+    /// 开始接收流量`elapsed`时间后的有效权重比例, 范围为`(0, 1]`
+    ///
+    pub fn factor(&self, elapsed: Duration) -> f64 {
+        if elapsed >= self.window || self.window.is_zero() {
+            return 1.0;
+        }
+        let ratio = elapsed.as_secs_f64() / self.window.as_secs_f64();
+        let factor = if self.aggression > 0.0 {
+            ratio.powf(1.0 / self.aggression)
+        } else {
+            ratio
+        };
+        factor
+            .max(self.min_weight_percent / 100.0)
+            .clamp(f64::MIN_POSITIVE, 1.0)
+    }
+}
@@ -0,0 +1,156 @@
+//!
+//! 基于Cookie的会话保持
+//!
+use crate::Backend;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use cookie::time::Duration as CookieDuration;
+use cookie::{Cookie, SameSite};
+use http::HeaderMap;
+use http::header::COOKIE;
+use ring::hmac;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+///
+/// 会话保持, 第一次响应时通过Cookie记录选中的后端, 之后携带该Cookie的请求会转发到同一个后端
+///
+#[derive(Debug, Clone)]
+pub struct StickySession {
+    name: String,
+    ttl: Option<Duration>,
+    path: String,
+    domain: Option<String>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+    key: Option<hmac::Key>,
+}
+
+impl StickySession {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ttl: None,
+            path: String::from("/"),
+            domain: None,
+            secure: false,
+            http_only: true,
+            same_site: None,
+            key: None,
+        }
+    }
+
+    ///
+    /// Cookie的有效期, 为空时为会话Cookie
+    ///
+    pub fn with_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    pub fn with_domain(mut self, domain: Option<String>) -> Self {
+        self.domain = domain;
+        self
+    }
+
+    pub fn with_secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub fn with_http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    pub fn with_same_site(mut self, same_site: Option<SameSite>) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    ///
+    /// 使用`HMAC-SHA256`签名Cookie, 签名不正确的Cookie会被忽略
+    ///
+    pub fn with_secret(mut self, secret: impl AsRef<[u8]>) -> Self {
+        self.key = Some(hmac::Key::new(hmac::HMAC_SHA256, secret.as_ref()));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    ///
+    /// 从请求头中读取Cookie记录的后端地址
+    ///
+    pub fn find(&self, headers: &HeaderMap) -> Option<SocketAddr> {
+        headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(Cookie::split_parse)
+            .filter_map(|cookie| cookie.ok())
+            .find(|cookie| cookie.name() == self.name)
+            .and_then(|cookie| self.decode(cookie.value()))
+    }
+
+    ///
+    /// 记录后端的`Set-Cookie`响应头的值
+    ///
+    pub fn set_cookie(&self, backend: &Backend) -> String {
+        let mut cookie = Cookie::build((self.name.as_str(), self.encode(backend)))
+            .path(self.path.as_str())
+            .secure(self.secure)
+            .http_only(self.http_only);
+        if let Some(ttl) = self.ttl {
+            cookie = cookie.max_age(CookieDuration::seconds(ttl.as_secs() as i64));
+        }
+        if let Some(domain) = &self.domain {
+            cookie = cookie.domain(domain.as_str());
+        }
+        if let Some(same_site) = self.same_site {
+            cookie = cookie.same_site(same_site);
+        }
+        cookie.to_string()
+    }
+
+    ///
+    /// 编码后端地址, 格式为`base64(addr)`, 签名时为`base64(addr).base64(signature)`
+    ///
+    pub fn encode(&self, backend: &Backend) -> String {
+        let addr = backend.addr.to_string();
+        let mut value = URL_SAFE_NO_PAD.encode(&addr);
+        if let Some(key) = &self.key {
+            let tag = hmac::sign(key, addr.as_bytes());
+            value.push('.');
+            value.push_str(&URL_SAFE_NO_PAD.encode(tag.as_ref()));
+        }
+        value
+    }
+
+    ///
+    /// 解码后端地址, 格式或者签名不正确时返回空
+    ///
+    pub fn decode(&self, value: &str) -> Option<SocketAddr> {
+        let (addr, tag) = match value.split_once('.') {
+            Some((addr, tag)) => (addr, Some(tag)),
+            None => (value, None),
+        };
+        let addr = URL_SAFE_NO_PAD.decode(addr).ok()?;
+        match (&self.key, tag) {
+            (Some(key), Some(tag)) => {
+                let tag = URL_SAFE_NO_PAD.decode(tag).ok()?;
+                hmac::verify(key, &addr, &tag).ok()?;
+            }
+            (None, None) => {}
+            _ => return None,
+        }
+        String::from_utf8(addr).ok()?.parse().ok()
+    }
+}
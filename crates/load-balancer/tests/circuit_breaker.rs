@@ -0,0 +1,148 @@
+use satex_load_balancer::circuit_breaker::{CircuitBreaker, Rejection};
+use satex_load_balancer::discovery::StaticFixedDiscovery;
+use satex_load_balancer::selector::RoundRobin;
+use satex_load_balancer::{Backend, Backends, LoadBalancer};
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
+
+async fn load_balancer(circuit_breaker: CircuitBreaker) -> (Arc<LoadBalancer>, Backend) {
+    let backend = Backend::from_str("127.0.0.1:8080").unwrap();
+    let backends = BTreeSet::from([backend.clone()]);
+    let selector = RoundRobin::new(&backends);
+    let load_balancer =
+        LoadBalancer::new(Backends::new(StaticFixedDiscovery::new(backends)), selector)
+            .with_circuit_breaker(circuit_breaker);
+    load_balancer.update().await.unwrap();
+    (Arc::new(load_balancer), backend)
+}
+
+#[tokio::test]
+async fn open_and_half_open() {
+    let (load_balancer, backend) = load_balancer(
+        CircuitBreaker::default()
+            .with_consecutive_failures(Some(2))
+            .with_open_duration(Duration::from_millis(100)),
+    )
+    .await;
+    let circuit_breaker = load_balancer.circuit_breaker().unwrap();
+
+    // 成功的请求重置连续失败的次数
+    for success in [false, true, false] {
+        load_balancer
+            .acquire(&backend)
+            .await
+            .unwrap()
+            .report(success)
+            .await;
+    }
+    assert_eq!(circuit_breaker.check(&backend), Ok(()));
+    load_balancer
+        .acquire(&backend)
+        .await
+        .unwrap()
+        .report(false)
+        .await;
+    assert_eq!(circuit_breaker.check(&backend), Err(Rejection::Open));
+    assert_eq!(
+        load_balancer.acquire(&backend).await.err(),
+        Some(Rejection::Open)
+    );
+
+    // 半开状态只放行一个试探请求, 试探失败时重新打开
+    sleep(Duration::from_millis(150)).await;
+    let trial = load_balancer.acquire(&backend).await.unwrap();
+    assert_eq!(
+        load_balancer.acquire(&backend).await.err(),
+        Some(Rejection::Open)
+    );
+    trial.report(false).await;
+    drop(trial);
+    assert_eq!(circuit_breaker.check(&backend), Err(Rejection::Open));
+
+    // 试探成功时关闭
+    sleep(Duration::from_millis(150)).await;
+    let trial = load_balancer.acquire(&backend).await.unwrap();
+    trial.report(true).await;
+    drop(trial);
+    assert_eq!(circuit_breaker.check(&backend), Ok(()));
+    let first = load_balancer.acquire(&backend).await.unwrap();
+    let second = load_balancer.acquire(&backend).await.unwrap();
+    drop((first, second));
+}
+
+#[tokio::test]
+async fn max_requests() {
+    let (load_balancer, backend) =
+        load_balancer(CircuitBreaker::default().with_max_requests(Some(2))).await;
+    let circuit_breaker = load_balancer.circuit_breaker().unwrap();
+
+    let first = load_balancer.acquire(&backend).await.unwrap();
+    let second = load_balancer.acquire(&backend).await.unwrap();
+    assert_eq!(circuit_breaker.check(&backend), Err(Rejection::MaxRequests));
+    assert_eq!(
+        load_balancer.acquire(&backend).await.err(),
+        Some(Rejection::MaxRequests)
+    );
+    drop(first);
+    assert_eq!(circuit_breaker.check(&backend), Ok(()));
+    let third = load_balancer.acquire(&backend).await.unwrap();
+    drop((second, third));
+}
+
+#[tokio::test]
+async fn max_connections_and_pending() {
+    let (load_balancer, backend) = load_balancer(
+        CircuitBreaker::default()
+            .with_max_connections(Some(1))
+            .with_max_pending(Some(1)),
+    )
+    .await;
+    let circuit_breaker = load_balancer.circuit_breaker().unwrap();
+
+    // 连接数不影响请求的检查, 请求可能复用空闲的连接
+    let first = circuit_breaker.connect(backend.addr).await.unwrap();
+    assert_eq!(circuit_breaker.check(&backend), Ok(()));
+
+    // 第二个连接排队等待, 排队已满时拒绝第三个连接
+    let pending = tokio::spawn({
+        let load_balancer = load_balancer.clone();
+        let addr = backend.addr;
+        async move {
+            let circuit_breaker = load_balancer.circuit_breaker().unwrap();
+            circuit_breaker.connect(addr).await.is_ok()
+        }
+    });
+    sleep(Duration::from_millis(50)).await;
+    assert_eq!(
+        circuit_breaker.connect(backend.addr).await.err(),
+        Some(Rejection::MaxPending)
+    );
+
+    drop(first);
+    assert!(
+        timeout(Duration::from_secs(1), pending)
+            .await
+            .unwrap()
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn max_connections_without_pending() {
+    let (load_balancer, backend) =
+        load_balancer(CircuitBreaker::default().with_max_connections(Some(1))).await;
+    let circuit_breaker = load_balancer.circuit_breaker().unwrap();
+
+    // 没有设置排队的请求数时直接拒绝
+    let first = circuit_breaker.connect(backend.addr).await.unwrap();
+    assert_eq!(
+        circuit_breaker.connect(backend.addr).await.err(),
+        Some(Rejection::MaxConnections)
+    );
+    drop(first);
+    let second = circuit_breaker.connect(backend.addr).await.unwrap();
+    drop(second);
+}
@@ -0,0 +1,255 @@
+use bytes::Bytes;
+use http::{Request, Response};
+use http_body_util::Full;
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use satex_core::background::Supervisor;
+use satex_core::component::Args;
+use satex_load_balancer::discovery::consul::ServiceInstance;
+use satex_load_balancer::resolver::{
+    ConsulLoadBalancerResolver, LoadBalancerResolver, MakeConsulLoadBalancerResolver,
+    MakeLoadBalancerResolver,
+};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::spawn;
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+use tokio::time::{sleep, timeout};
+
+const FIRST: &str = r#"[
+    {
+        "Node": {"Node": "node-1", "Address": "10.0.0.1"},
+        "Service": {"ID": "web-1", "Service": "web", "Address": "", "Port": 8080},
+        "Checks": [{"Status": "passing"}]
+    }
+]"#;
+
+const SECOND: &str = r#"[
+    {
+        "Node": {"Node": "node-1", "Address": "10.0.0.1"},
+        "Service": {"ID": "web-1", "Service": "web", "Address": "", "Port": 8080},
+        "Checks": [{"Status": "passing"}]
+    },
+    {
+        "Node": {"Node": "node-2", "Address": "10.0.0.2"},
+        "Service": {
+            "ID": "web-2",
+            "Service": "web",
+            "Address": "10.0.1.2",
+            "Port": 8081,
+            "Tags": ["canary"],
+            "Meta": {"version": "v2"},
+            "Weights": {"Passing": 3, "Warning": 1}
+        },
+        "Checks": [{"Status": "passing"}, {"Status": "passing"}]
+    },
+    {
+        "Node": {"Node": "node-3", "Address": "10.0.0.3"},
+        "Service": {"ID": "web-3", "Service": "web", "Address": "", "Port": 8080},
+        "Checks": [{"Status": "critical"}]
+    }
+]"#;
+
+///
+/// 模拟服务目录, 索引`0`返回一个实例, 之后的阻塞查询返回新增的实例
+///
+async fn catalog() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            spawn(async move {
+                let service = service_fn(|request: Request<Incoming>| async move {
+                    assert_eq!(request.uri().path(), "/v1/health/service/web");
+                    assert_eq!(request.headers()["X-Consul-Token"], "secret");
+                    let index = request
+                        .uri()
+                        .query()
+                        .unwrap_or_default()
+                        .split('&')
+                        .find_map(|pair| pair.strip_prefix("index="))
+                        .and_then(|index| index.parse::<u64>().ok())
+                        .unwrap_or_default();
+                    let (body, index) = match index {
+                        0 => (FIRST, 10),
+                        10 => {
+                            sleep(Duration::from_millis(200)).await;
+                            (SECOND, 11)
+                        }
+                        _ => {
+                            sleep(Duration::from_secs(1)).await;
+                            (SECOND, 11)
+                        }
+                    };
+                    let response = Response::builder()
+                        .header("X-Consul-Index", index)
+                        .body(Full::new(Bytes::from_static(body.as_bytes())))
+                        .unwrap();
+                    Ok::<_, Infallible>(response)
+                });
+                let _ = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+            });
+        }
+    });
+    format!("http://{}", addr)
+}
+
+#[tokio::test]
+async fn watch_passing_instances() {
+    let address = catalog().await;
+    let value = serde_yaml::from_str::<Value>(&format!(
+        r#"
+        address: {}
+        token: secret
+        wait-secs: 1
+        services:
+          - name: web
+            health-check:
+              enabled: false
+        "#,
+        address
+    ))
+    .unwrap();
+    let resolver = MakeConsulLoadBalancerResolver
+        .make(Args::full(&value), &Supervisor::default())
+        .unwrap();
+    let load_balancer = resolver.find("web").unwrap();
+
+    sleep(Duration::from_millis(100)).await;
+    let backend = load_balancer.select(b"").unwrap();
+    assert_eq!(backend.addr.to_string(), "10.0.0.1:8080");
+
+    sleep(Duration::from_millis(1500)).await;
+    let selected = (0..8)
+        .map(|_| {
+            let backend = load_balancer.select(b"").unwrap();
+            (backend.addr.to_string(), backend)
+        })
+        .collect::<BTreeMap<_, _>>();
+    assert_eq!(
+        selected.keys().collect::<Vec<_>>(),
+        vec!["10.0.0.1:8080", "10.0.1.2:8081"]
+    );
+
+    let canary = &selected["10.0.1.2:8081"];
+    assert_eq!(canary.weight, 3);
+    let instance = canary.extension.get::<ServiceInstance>().unwrap();
+    assert_eq!(instance.id, "web-2");
+    assert_eq!(instance.node, "node-2");
+    assert_eq!(instance.tags, vec!["canary".to_string()]);
+    assert_eq!(instance.meta["version"], "v2");
+}
+
+#[tokio::test]
+async fn service_options() {
+    let make = |options: &str| {
+        let value = serde_yaml::from_str::<Value>(&format!(
+            "{{address: 'http://127.0.0.1:8500', services: [{{name: web, {}}}]}}",
+            options
+        ))
+        .unwrap();
+        MakeConsulLoadBalancerResolver.make(Args::full(&value), &Supervisor::default())
+    };
+    let resolver =
+        make("overprovisioning-factor: 2.0, circuit-breaker: {max-requests: 100}").unwrap();
+    assert!(resolver.find("web").unwrap().circuit_breaker().is_some());
+    assert!(make("overprovisioning-factor: fast").is_err());
+}
+
+///
+/// 不支持阻塞查询的服务目录, 立即返回`body`并且没有索引, 收到的请求地址通过返回的通道发送
+///
+async fn non_blocking_catalog(body: &'static str) -> (String, UnboundedReceiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let (sender, receiver) = unbounded_channel();
+    spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let sender = sender.clone();
+            spawn(async move {
+                let service = service_fn(move |request: Request<Incoming>| {
+                    let _ = sender.send(request.uri().to_string());
+                    async move { Ok::<_, Infallible>(Response::new(Full::new(Bytes::from(body)))) }
+                });
+                let _ = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+            });
+        }
+    });
+    (address, receiver)
+}
+
+fn make(address: &str, datacenter: &str, service: &str) -> ConsulLoadBalancerResolver {
+    let value = serde_yaml::from_str::<Value>(&format!(
+        "{{address: '{}', datacenter: '{}', services: [{{name: '{}', health-check: {{enabled: false}}}}]}}",
+        address, datacenter, service
+    ))
+    .unwrap();
+    MakeConsulLoadBalancerResolver
+        .make(Args::full(&value), &Supervisor::default())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn encode_query() {
+    let (address, mut receiver) = non_blocking_catalog("[]").await;
+    let _resolver = make(&address, "dc 1&x", "web/api v2");
+    let uri = timeout(Duration::from_secs(1), receiver.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        uri,
+        "/v1/health/service/web%2Fapi%20v2?passing=true&index=0&wait=30s&dc=dc+1%26x"
+    );
+}
+
+#[tokio::test]
+async fn non_blocking_query() {
+    let (address, mut receiver) = non_blocking_catalog(FIRST).await;
+    let resolver = make(&address, "dc1", "web");
+    sleep(Duration::from_millis(1500)).await;
+    let backend = resolver.find("web").unwrap().select(b"").unwrap();
+    assert_eq!(backend.addr.to_string(), "10.0.0.1:8080");
+
+    // 索引没有变化时等待一段时间再查询
+    let mut requests = 0;
+    while receiver.try_recv().is_ok() {
+        requests += 1;
+    }
+    assert!(requests <= 2, "{}", requests);
+}
+
+#[tokio::test]
+async fn skip_hostname_instances() {
+    let (address, _receiver) = non_blocking_catalog(
+        r#"[
+            {
+                "Node": {"Node": "node-1", "Address": "10.0.0.1"},
+                "Service": {"ID": "web-1", "Service": "web", "Address": "web-1.local", "Port": 8080}
+            },
+            {
+                "Node": {"Node": "node-2", "Address": "10.0.0.2"},
+                "Service": {"ID": "web-2", "Service": "web", "Address": "", "Port": 8080}
+            }
+        ]"#,
+    )
+    .await;
+    let resolver = make(&address, "dc1", "web");
+    sleep(Duration::from_millis(200)).await;
+    let load_balancer = resolver.find("web").unwrap();
+    for _ in 0..4 {
+        let backend = load_balancer.select(b"").unwrap();
+        assert_eq!(backend.addr.to_string(), "10.0.0.2:8080");
+    }
+}
@@ -0,0 +1,218 @@
+use async_trait::async_trait;
+use satex_core::Error;
+use satex_core::background::Supervisor;
+use satex_core::component::Args;
+use satex_load_balancer::Backend;
+use satex_load_balancer::discovery::Discovery;
+use satex_load_balancer::discovery::dns::{DnsDiscovery, DnsLookup, DnsQuery, Lookup, SrvRecord};
+use satex_load_balancer::resolver::{
+    LoadBalancerResolver, MakeDnsLoadBalancerResolver, MakeLoadBalancerResolver,
+};
+use serde_yaml::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Clone, Default)]
+struct MockDnsLookup {
+    hosts: Arc<Mutex<HashMap<String, Vec<IpAddr>>>>,
+    srvs: Arc<Mutex<HashMap<String, Vec<SrvRecord>>>>,
+    ttl: Option<Duration>,
+    queries: Arc<AtomicUsize>,
+}
+
+impl MockDnsLookup {
+    fn host(&self, host: &str, ips: &[[u8; 4]]) {
+        let ips = ips
+            .iter()
+            .map(|ip| IpAddr::from(Ipv4Addr::from(*ip)))
+            .collect();
+        self.hosts.lock().unwrap().insert(host.to_string(), ips);
+    }
+}
+
+#[async_trait]
+impl DnsLookup for MockDnsLookup {
+    async fn lookup_ip(&self, host: &str) -> Result<Lookup<IpAddr>, Error> {
+        self.queries.fetch_add(1, Ordering::SeqCst);
+        let records = self.hosts.lock().unwrap().get(host).cloned();
+        records
+            .map(|records| Lookup {
+                records,
+                ttl: self.ttl,
+            })
+            .ok_or_else(|| Error::new(format!("no such host: {}", host)))
+    }
+
+    async fn lookup_srv(&self, name: &str) -> Result<Lookup<SrvRecord>, Error> {
+        self.queries.fetch_add(1, Ordering::SeqCst);
+        let records = self.srvs.lock().unwrap().get(name).cloned();
+        records
+            .map(|records| Lookup {
+                records,
+                ttl: self.ttl,
+            })
+            .ok_or_else(|| Error::new(format!("no such srv: {}", name)))
+    }
+}
+
+fn addrs(discovered: &(BTreeSet<Backend>, HashMap<u64, bool>)) -> Vec<(String, usize)> {
+    discovered
+        .0
+        .iter()
+        .map(|backend| (backend.addr.to_string(), backend.weight))
+        .collect()
+}
+
+#[tokio::test]
+async fn host_records_cached_until_ttl() {
+    let lookup = MockDnsLookup {
+        ttl: Some(Duration::from_secs(300)),
+        ..Default::default()
+    };
+    lookup.host("backend.local", &[[10, 0, 0, 1], [10, 0, 0, 2]]);
+    let discovery = DnsDiscovery::new(
+        lookup.clone(),
+        DnsQuery::Host {
+            host: "backend.local".to_string(),
+            port: 8080,
+        },
+    )
+    .with_weight(2);
+
+    let discovered = discovery.discover().await.unwrap();
+    assert_eq!(
+        addrs(&discovered),
+        vec![
+            ("10.0.0.1:8080".to_string(), 2),
+            ("10.0.0.2:8080".to_string(), 2)
+        ]
+    );
+
+    // 记录未过期, 不会重新查询
+    lookup.host("backend.local", &[[10, 0, 0, 3]]);
+    discovery.discover().await.unwrap();
+    assert_eq!(lookup.queries.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn host_records_refreshed_after_ttl() {
+    let lookup = MockDnsLookup {
+        ttl: Some(Duration::ZERO),
+        ..Default::default()
+    };
+    lookup.host("backend.local", &[[10, 0, 0, 1]]);
+    let discovery = DnsDiscovery::new(
+        lookup.clone(),
+        DnsQuery::Host {
+            host: "backend.local".to_string(),
+            port: 80,
+        },
+    )
+    .with_min_ttl(Duration::ZERO);
+    discovery.discover().await.unwrap();
+
+    lookup.host("backend.local", &[[10, 0, 0, 3]]);
+    let discovered = discovery.discover().await.unwrap();
+    assert_eq!(addrs(&discovered), vec![("10.0.0.3:80".to_string(), 1)]);
+
+    // 查询失败时返回错误, 由负载均衡器保留上一次的结果
+    lookup.hosts.lock().unwrap().clear();
+    assert!(discovery.discover().await.is_err());
+}
+
+#[tokio::test]
+async fn srv_records() {
+    let lookup = MockDnsLookup::default();
+    lookup.host("a.backend.local", &[[10, 0, 0, 1]]);
+    lookup.host("b.backend.local", &[[10, 0, 0, 2]]);
+    lookup.host("backup.backend.local", &[[10, 0, 0, 9]]);
+    lookup.srvs.lock().unwrap().insert(
+        "_http._tcp.backend.local".to_string(),
+        vec![
+            SrvRecord {
+                priority: 10,
+                weight: 5,
+                port: 8080,
+                target: "a.backend.local".to_string(),
+            },
+            SrvRecord {
+                priority: 10,
+                weight: 0,
+                port: 8081,
+                target: "b.backend.local".to_string(),
+            },
+            SrvRecord {
+                priority: 20,
+                weight: 1,
+                port: 8080,
+                target: "backup.backend.local".to_string(),
+            },
+        ],
+    );
+    let discovery = DnsDiscovery::new(
+        lookup,
+        DnsQuery::Srv("_http._tcp.backend.local".to_string()),
+    );
+    let discovered = discovery.discover().await.unwrap();
+    assert_eq!(
+        addrs(&discovered),
+        vec![
+            ("10.0.0.1:8080".to_string(), 5),
+            ("10.0.0.2:8081".to_string(), 1)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn invalid_upstream() {
+    for upstream in [
+        "{name: backend}",
+        "{name: backend, host: 'backend.local'}",
+        "{name: backend, host: 'backend.local:80', srv: '_http._tcp.backend.local'}",
+        "{name: backend, host: 'backend.local:80', overprovisioning-factor: fast}",
+    ] {
+        let value = serde_yaml::from_str::<Value>(&format!(
+            "{{name-servers: ['127.0.0.1:53'], upstreams: [{}]}}",
+            upstream
+        ))
+        .unwrap();
+        assert!(
+            MakeDnsLoadBalancerResolver
+                .make(Args::full(&value), &Supervisor::default())
+                .is_err(),
+            "{}",
+            upstream
+        );
+    }
+}
+
+#[tokio::test]
+async fn upstream_options() {
+    let value = serde_yaml::from_str::<Value>(
+        r#"
+        name-servers:
+          - 127.0.0.1:53
+        upstreams:
+          - name: backend
+            host: backend.local:8080
+            overprovisioning-factor: 2.0
+            health-check:
+              enabled: false
+            outlier-detection:
+              consecutive-errors: 3
+            slow-start:
+              window-secs: 30
+            circuit-breaker:
+              max-requests: 100
+        "#,
+    )
+    .unwrap();
+    let resolver = MakeDnsLoadBalancerResolver
+        .make(Args::full(&value), &Supervisor::default())
+        .unwrap();
+    let load_balancer = resolver.find("backend").unwrap();
+    assert!(load_balancer.circuit_breaker().is_some());
+}
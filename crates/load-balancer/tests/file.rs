@@ -0,0 +1,131 @@
+use satex_core::background::Supervisor;
+use satex_core::component::Args;
+use satex_load_balancer::resolver::{
+    LoadBalancerResolver, MakeFileLoadBalancerResolver, MakeLoadBalancerResolver,
+};
+use serde_yaml::Value;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::time::sleep;
+
+fn upstream_file(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("satex-{}-{}", std::process::id(), name));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+#[tokio::test]
+async fn reload_on_change() {
+    let path = upstream_file(
+        "upstreams.yaml",
+        r#"
+        upstreams:
+          - name: backend
+            health-check:
+              enabled: false
+            addrs:
+              - 127.0.0.1:8080
+        "#,
+    );
+    let value =
+        serde_yaml::from_str::<Value>(&format!("{{path: '{}', interval-secs: 1}}", path.display()))
+            .unwrap();
+    let resolver = MakeFileLoadBalancerResolver
+        .make(Args::full(&value), &Supervisor::default())
+        .unwrap();
+    sleep(Duration::from_millis(100)).await;
+
+    let load_balancer = resolver.find("backend").unwrap();
+    assert_eq!(
+        load_balancer.select(b"").unwrap().addr.to_string(),
+        "127.0.0.1:8080"
+    );
+
+    // 文件使用JSON格式重写, 禁用的地址不会被选中
+    std::fs::write(
+        &path,
+        r#"{"upstreams": [{"name": "backend", "addrs": [
+            {"addr": "127.0.0.1:8080", "enabled": false},
+            {"addr": "127.0.0.2:8080", "weight": 2},
+            "127.0.0.3:8080@3"
+        ]}]}"#,
+    )
+    .unwrap();
+    sleep(Duration::from_millis(1500)).await;
+
+    let selected = (0..20)
+        .map(|_| load_balancer.select(b"").unwrap().addr.to_string())
+        .collect::<BTreeSet<_>>();
+    assert_eq!(
+        selected,
+        BTreeSet::from(["127.0.0.2:8080".to_string(), "127.0.0.3:8080".to_string()])
+    );
+
+    // 文件内容错误时保留上一次的后端集合
+    std::fs::write(&path, "upstreams: [").unwrap();
+    sleep(Duration::from_millis(1500)).await;
+    assert!(load_balancer.select(b"").is_some());
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn reload_addrs_only() {
+    let path = upstream_file(
+        "upstreams-addrs-only.yaml",
+        r#"
+        upstreams:
+          - name: backend
+            health-check:
+              enabled: false
+            addrs:
+              - 127.0.0.1:8080
+        "#,
+    );
+    let value =
+        serde_yaml::from_str::<Value>(&format!("{{path: '{}', interval-secs: 1}}", path.display()))
+            .unwrap();
+    let resolver = MakeFileLoadBalancerResolver
+        .make(Args::full(&value), &Supervisor::default())
+        .unwrap();
+    sleep(Duration::from_millis(100)).await;
+
+    // 策略的变化以及新增的上游被忽略, 地址仍然会刷新
+    std::fs::write(
+        &path,
+        r#"
+        upstreams:
+          - name: backend
+            policy: Random
+            health-check:
+              enabled: false
+            addrs:
+              - 127.0.0.2:8080
+          - name: other
+            addrs:
+              - 127.0.0.3:8080
+        "#,
+    )
+    .unwrap();
+    sleep(Duration::from_millis(1500)).await;
+
+    let load_balancer = resolver.find("backend").unwrap();
+    assert_eq!(
+        load_balancer.select(b"").unwrap().addr.to_string(),
+        "127.0.0.2:8080"
+    );
+    assert!(resolver.find("other").is_none());
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn missing_file() {
+    let value = serde_yaml::from_str::<Value>("path: /nonexistent/upstreams.yaml").unwrap();
+    assert!(
+        MakeFileLoadBalancerResolver
+            .make(Args::full(&value), &Supervisor::default())
+            .is_err()
+    );
+}
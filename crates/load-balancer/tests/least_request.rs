@@ -0,0 +1,79 @@
+use satex_load_balancer::discovery::StaticFixedDiscovery;
+use satex_load_balancer::selector::{BackendIter, LeastRequest, Selector};
+use satex_load_balancer::{Backend, Backends, LoadBalancer};
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::sync::Arc;
+
+fn backends(addrs: &[&str]) -> BTreeSet<Backend> {
+    addrs
+        .iter()
+        .map(|addr| Backend::from_str(addr).unwrap())
+        .collect()
+}
+
+fn first(selector: &LeastRequest) -> Backend {
+    selector.iter(b"").next().cloned().unwrap()
+}
+
+#[test]
+fn prefer_less_inflight() {
+    let backends = backends(&["127.0.0.1:8080", "127.0.0.1:8081"]);
+    let busy = backends.first().unwrap().clone();
+    let idle = backends.last().unwrap().clone();
+    let selector = LeastRequest::new(&backends);
+
+    selector.on_start(&busy);
+    selector.on_start(&busy);
+    assert_eq!(selector.inflight(&busy), 2);
+    for _ in 0..10 {
+        assert_eq!(first(&selector), idle);
+    }
+
+    // 后端集合更新后保留进行中的请求数
+    selector.update(&backends);
+    assert_eq!(selector.inflight(&busy), 2);
+
+    selector.on_finish(&busy);
+    selector.on_finish(&busy);
+    selector.on_finish(&busy);
+    assert_eq!(selector.inflight(&busy), 0);
+}
+
+#[test]
+fn iterate_all_backends() {
+    let backends = backends(&["127.0.0.1:8080", "127.0.0.1:8081", "127.0.0.1:8082"]);
+    let selector = LeastRequest::new(&backends);
+    let mut iter = selector.iter(b"");
+    let mut selected = BTreeSet::new();
+    while let Some(backend) = iter.next() {
+        assert!(selected.insert(backend.clone()));
+    }
+    assert_eq!(selected, backends);
+
+    let selector = LeastRequest::new(&BTreeSet::new());
+    assert!(selector.iter(b"").next().is_none());
+}
+
+#[tokio::test]
+async fn track_inflight() {
+    let items = backends(&["127.0.0.1:8080", "127.0.0.1:8081"]);
+    let selector = LeastRequest::new(&items);
+    let load_balancer = Arc::new(LoadBalancer::new(
+        Backends::new(StaticFixedDiscovery::new(items)),
+        selector,
+    ));
+    load_balancer.update().await.unwrap();
+
+    let backend = load_balancer.select(b"").unwrap();
+    let inflight = load_balancer.track(&backend);
+    for _ in 0..10 {
+        assert_ne!(load_balancer.select(b"").unwrap(), backend);
+    }
+
+    drop(inflight);
+    let selected = (0..50)
+        .map(|_| load_balancer.select(b"").unwrap())
+        .collect::<BTreeSet<_>>();
+    assert_eq!(selected.len(), 2);
+}
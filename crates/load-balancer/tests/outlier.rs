@@ -0,0 +1,122 @@
+use async_trait::async_trait;
+use satex_load_balancer::discovery::StaticFixedDiscovery;
+use satex_load_balancer::health_check::HealthStatusObserve;
+use satex_load_balancer::health_check::outlier::OutlierDetection;
+use satex_load_balancer::selector::RoundRobin;
+use satex_load_balancer::{Backend, Backends, LoadBalancer};
+use std::cell::Cell;
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::sleep;
+
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<(String, bool)>>>);
+
+impl Recorder {
+    fn take(&self) -> Vec<(String, bool)> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+#[async_trait]
+impl HealthStatusObserve for Recorder {
+    async fn observe(&self, backend: &Backend, success: bool) {
+        self.0
+            .lock()
+            .unwrap()
+            .push((backend.addr.to_string(), success));
+    }
+}
+
+async fn load_balancer(detection: OutlierDetection) -> (LoadBalancer, Vec<Backend>) {
+    let backends = ["127.0.0.1:8080", "127.0.0.1:8081", "127.0.0.1:8082"]
+        .into_iter()
+        .map(|addr| Backend::from_str(addr).unwrap())
+        .collect::<BTreeSet<_>>();
+    let selector = RoundRobin::new(&backends);
+    let load_balancer = LoadBalancer::new(
+        Backends::new(StaticFixedDiscovery::new(backends.clone())),
+        selector,
+    )
+    .with_name("backend")
+    .with_outlier_detection(detection);
+    load_balancer.update().await.unwrap();
+    (load_balancer, backends.into_iter().collect())
+}
+
+fn ready(load_balancer: &LoadBalancer, backend: &Backend) -> bool {
+    let ready = Cell::new(None);
+    load_balancer.select_with(b"", |b, health| {
+        let found = b == backend;
+        if found {
+            ready.set(Some(health));
+        }
+        found
+    });
+    ready.get().unwrap()
+}
+
+#[tokio::test]
+async fn eject_consecutive_errors() {
+    let recorder = Recorder::default();
+    let detection = OutlierDetection::default()
+        .with_consecutive_errors(Some(2))
+        .with_base_ejection(Duration::from_millis(100))
+        .with_max_ejection(Duration::from_secs(1))
+        .with_max_ejection_percent(50.0)
+        .with_status_observe(recorder.clone());
+    let (load_balancer, backends) = load_balancer(detection).await;
+    let [a, b, c] = &backends[..] else {
+        unreachable!()
+    };
+
+    load_balancer.report(a, false).await;
+    load_balancer.report(a, true).await;
+    load_balancer.report(a, false).await;
+    assert!(ready(&load_balancer, a));
+    load_balancer.report(a, false).await;
+    assert!(!ready(&load_balancer, a));
+    assert_eq!(recorder.take(), vec![(a.addr.to_string(), false)]);
+
+    // 最多驱逐50%的后端
+    for backend in [b, b, c, c] {
+        load_balancer.report(backend, false).await;
+    }
+    assert!(!ready(&load_balancer, b));
+    assert!(ready(&load_balancer, c));
+    recorder.take();
+
+    // 驱逐时间结束后重新接收流量
+    sleep(Duration::from_millis(150)).await;
+    assert!(ready(&load_balancer, a));
+    load_balancer.report(a, true).await;
+    assert_eq!(recorder.take(), vec![(a.addr.to_string(), true)]);
+
+    // 再次驱逐时驱逐时间翻倍
+    load_balancer.report(b, true).await;
+    load_balancer.report(a, false).await;
+    load_balancer.report(a, false).await;
+    sleep(Duration::from_millis(150)).await;
+    assert!(!ready(&load_balancer, a));
+    sleep(Duration::from_millis(100)).await;
+    assert!(ready(&load_balancer, a));
+}
+
+#[tokio::test]
+async fn eject_error_rate() {
+    let detection = OutlierDetection::default()
+        .with_consecutive_errors(None)
+        .with_error_rate(Some(0.5))
+        .with_min_requests(4)
+        .with_max_ejection_percent(100.0);
+    let (load_balancer, backends) = load_balancer(detection).await;
+    let backend = &backends[0];
+    for success in [true, false, true] {
+        load_balancer.report(backend, success).await;
+    }
+    assert!(ready(&load_balancer, backend));
+    load_balancer.report(backend, false).await;
+    assert!(!ready(&load_balancer, backend));
+}
@@ -0,0 +1,80 @@
+use satex_load_balancer::Backend;
+use satex_load_balancer::selector::{BackendIter, PeakEwma, Selector};
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::thread::sleep;
+use std::time::Duration;
+
+fn backends() -> (BTreeSet<Backend>, Backend, Backend) {
+    let slow = Backend::from_str("127.0.0.1:8080").unwrap();
+    let fast = Backend::from_str("127.0.0.1:8081").unwrap();
+    let backends = BTreeSet::from([slow.clone(), fast.clone()]);
+    (backends, slow, fast)
+}
+
+fn first(selector: &PeakEwma) -> Backend {
+    selector.iter(b"").next().cloned().unwrap()
+}
+
+#[test]
+fn prefer_lower_latency() {
+    let (backends, slow, fast) = backends();
+    let selector = PeakEwma::new(&backends);
+    selector.on_latency(&slow, Duration::from_millis(200));
+    selector.on_latency(&fast, Duration::from_millis(10));
+    for _ in 0..10 {
+        assert_eq!(first(&selector), fast);
+    }
+
+    // 延迟升高立即生效
+    selector.on_latency(&fast, Duration::from_millis(500));
+    for _ in 0..10 {
+        assert_eq!(first(&selector), slow);
+    }
+}
+
+#[test]
+fn penalize_outstanding() {
+    let (backends, slow, fast) = backends();
+    let selector = PeakEwma::new(&backends);
+    selector.on_latency(&slow, Duration::from_millis(40));
+    selector.on_latency(&fast, Duration::from_millis(30));
+    for _ in 0..3 {
+        selector.on_start(&fast);
+    }
+    assert_eq!(first(&selector), slow);
+
+    for _ in 0..3 {
+        selector.on_finish(&fast);
+    }
+    assert_eq!(first(&selector), fast);
+}
+
+#[test]
+fn decay_over_time() {
+    let (backends, slow, _) = backends();
+    let selector = PeakEwma::new(&backends).with_decay(Duration::from_millis(10));
+    selector.update(&backends);
+    selector.on_latency(&slow, Duration::from_millis(200));
+    let cost = selector.cost(&slow).unwrap();
+    sleep(Duration::from_millis(50));
+    assert!(selector.cost(&slow).unwrap() < cost / 2.0);
+}
+
+#[test]
+fn default_rtt() {
+    let (backends, slow, fast) = backends();
+    let selector = PeakEwma::new(&backends).with_default_rtt(Duration::from_millis(10));
+    selector.on_latency(&slow, Duration::from_millis(20));
+
+    // 构造时已有的后端同样使用设置的默认延迟
+    let cost = selector.cost(&fast).unwrap();
+    assert!(
+        cost <= Duration::from_millis(10).as_nanos() as f64,
+        "{}",
+        cost
+    );
+    for _ in 0..10 {
+        assert_eq!(first(&selector), fast);
+    }
+}
@@ -0,0 +1,84 @@
+use satex_core::background::Supervisor;
+use satex_core::component::Args;
+use satex_load_balancer::resolver::{
+    LoadBalancerResolver, MakeLoadBalancerResolver, MakeStaticLoadBalancerResolver,
+};
+use satex_load_balancer::{Backend, LoadBalancer};
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+
+async fn load_balancer() -> Arc<LoadBalancer> {
+    let value = serde_yaml::from_str::<Value>(
+        r#"
+        upstreams:
+          - name: backend
+            health-check:
+              enabled: false
+            outlier-detection:
+              consecutive-errors: 1
+              max-ejection-percent: 100
+            addrs:
+              - 127.0.0.1:8080
+              - addr: 127.0.0.2:8080
+                priority: 0
+              - addr: 127.0.0.3:8080
+                backup: true
+              - addr: 127.0.0.4:8080
+                priority: 2
+        "#,
+    )
+    .unwrap();
+    let resolver = MakeStaticLoadBalancerResolver
+        .make(Args::full(&value), &Supervisor::default())
+        .unwrap();
+    sleep(Duration::from_millis(100)).await;
+    resolver.find("backend").unwrap()
+}
+
+fn counts(load_balancer: &LoadBalancer) -> HashMap<usize, usize> {
+    let mut counts = HashMap::new();
+    for _ in 0..1000 {
+        let backend = load_balancer.select(b"").unwrap();
+        *counts.entry(backend.priority).or_insert(0) += 1;
+    }
+    counts
+}
+
+#[tokio::test]
+async fn priority_groups() {
+    let load_balancer = load_balancer().await;
+    let priorities = load_balancer
+        .select_with(b"", |_, _| true)
+        .map(|backend| backend.priority);
+    assert_eq!(priorities, Some(0));
+    assert_eq!(counts(&load_balancer), HashMap::from([(0, 1000)]));
+
+    // 优先级最高的一组中一半的后端不可用时, 按照超额配置系数转发 30% 的流量到下一组
+    let primary = Backend::from_str("127.0.0.1:8080").unwrap();
+    load_balancer.report(&primary, false).await;
+    let spilled = counts(&load_balancer);
+    assert_eq!(spilled.get(&2), None);
+    let backup = spilled.get(&1).copied().unwrap_or_default();
+    assert!(
+        (200..400).contains(&backup),
+        "backup selected {} times",
+        backup
+    );
+
+    // 优先级最高的一组都不可用时, 全部转发到备份组
+    let primary = Backend::from_str("127.0.0.2:8080").unwrap();
+    load_balancer.report(&primary, false).await;
+    assert_eq!(self::counts(&load_balancer), HashMap::from([(1, 1000)]));
+
+    // 所有的后端都不可用时, 不再选择任何后端
+    for addr in ["127.0.0.3:8080", "127.0.0.4:8080"] {
+        load_balancer
+            .report(&Backend::from_str(addr).unwrap(), false)
+            .await;
+    }
+    assert_eq!(load_balancer.select(b""), None);
+}
@@ -0,0 +1,98 @@
+use satex_core::background::Supervisor;
+use satex_core::component::Args;
+use satex_load_balancer::resolver::{
+    ArcLoadBalancerResolver, LoadBalancerResolver, MakeLoadBalancerResolver,
+    MakeStaticLoadBalancerResolver,
+};
+use satex_load_balancer::{Backend, LoadBalancer};
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::time::sleep;
+
+fn make(yaml: &str, supervisor: &Supervisor) -> ArcLoadBalancerResolver {
+    let value = serde_yaml::from_str::<Value>(yaml).unwrap();
+    ArcLoadBalancerResolver::new(
+        MakeStaticLoadBalancerResolver
+            .make(Args::full(&value), supervisor)
+            .unwrap(),
+    )
+}
+
+fn counts(load_balancer: &LoadBalancer) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for _ in 0..10 {
+        let backend = load_balancer.select(b"").unwrap();
+        *counts.entry(backend.addr.to_string()).or_insert(0) += 1;
+    }
+    counts
+}
+
+const UPSTREAMS: &str = r#"
+    upstreams:
+      - name: backend
+        refresh-interval-secs: 5
+        health-check:
+          enabled: false
+        outlier-detection:
+          consecutive-errors: 1
+          max-ejection-percent: 100
+        addrs:
+          - 127.0.0.1:8080
+          - 127.0.0.2:8080
+"#;
+
+#[tokio::test]
+async fn cancel_background_tasks() {
+    let supervisor = Supervisor::new();
+    let resolver = make(UPSTREAMS, &supervisor);
+    sleep(Duration::from_millis(100)).await;
+    assert!(resolver.find("backend").is_some());
+    assert_eq!(supervisor.len(), 1);
+
+    supervisor.cancel();
+    supervisor.wait().await;
+    assert!(supervisor.is_cancelled());
+    assert!(supervisor.is_empty());
+}
+
+#[tokio::test]
+async fn inherit_health() {
+    let previous = Supervisor::new();
+    let resolver = make(UPSTREAMS, &previous);
+    sleep(Duration::from_millis(100)).await;
+    let load_balancer = resolver.find("backend").unwrap();
+    load_balancer
+        .report(&Backend::from_str("127.0.0.1:8080").unwrap(), false)
+        .await;
+    assert_eq!(
+        counts(&load_balancer),
+        HashMap::from([("127.0.0.2:8080".to_string(), 10)])
+    );
+
+    // 重新加载配置后, 仍然存在的后端继承上一次的健康状态
+    previous.cancel();
+    let supervisor = Supervisor::new();
+    let reloaded = make(
+        r#"
+        upstreams:
+          - name: backend
+            health-check:
+              enabled: false
+            addrs:
+              - 127.0.0.1:8080
+              - 127.0.0.2:8080
+              - 127.0.0.3:8080
+        "#,
+        &supervisor,
+    );
+    reloaded.inherit(&resolver);
+    sleep(Duration::from_millis(100)).await;
+    let load_balancer = reloaded.find("backend").unwrap();
+    let counts = counts(&load_balancer);
+    assert_eq!(counts.get("127.0.0.1:8080"), None);
+    assert_eq!(counts.get("127.0.0.2:8080"), Some(&5));
+    assert_eq!(counts.get("127.0.0.3:8080"), Some(&5));
+    supervisor.cancel();
+}
@@ -0,0 +1,140 @@
+use satex_core::background::Supervisor;
+use satex_core::component::Args;
+use satex_load_balancer::resolver::{
+    LoadBalancerResolver, MakeLoadBalancerResolver, MakeStaticLoadBalancerResolver,
+};
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::sleep;
+
+fn args(yaml: &str) -> Value {
+    serde_yaml::from_str::<Value>(yaml).unwrap()
+}
+
+#[tokio::test]
+async fn weighted_addrs() {
+    let value = args(
+        r#"
+        upstreams:
+          - name: backend
+            health-check:
+              enabled: false
+            addrs:
+              - 127.0.0.1:8080@3
+              - addr: 127.0.0.2:8080
+                weight: 1
+        "#,
+    );
+    let resolver = MakeStaticLoadBalancerResolver
+        .make(Args::full(&value), &Supervisor::default())
+        .unwrap();
+    sleep(Duration::from_millis(100)).await;
+
+    let load_balancer = resolver.find("backend").unwrap();
+    let mut counts = HashMap::new();
+    for _ in 0..40 {
+        let backend = load_balancer.select(b"").unwrap();
+        *counts.entry(backend.addr.to_string()).or_insert(0) += 1;
+    }
+    assert_eq!(counts.get("127.0.0.1:8080"), Some(&30));
+    assert_eq!(counts.get("127.0.0.2:8080"), Some(&10));
+}
+
+#[tokio::test]
+async fn hostname_addrs() {
+    let value = args(
+        r#"
+        upstreams:
+          - name: backend
+            refresh-interval-secs: 5
+            health-check:
+              enabled: false
+            addrs:
+              - localhost:8080@2
+        "#,
+    );
+    let resolver = MakeStaticLoadBalancerResolver
+        .make(Args::full(&value), &Supervisor::default())
+        .unwrap();
+    sleep(Duration::from_millis(100)).await;
+
+    let backend = resolver.find("backend").unwrap().select(b"").unwrap();
+    assert!(backend.addr.ip().is_loopback());
+    assert_eq!(backend.addr.port(), 8080);
+    assert_eq!(backend.weight, 2);
+}
+
+#[tokio::test]
+async fn invalid_addrs() {
+    for addr in ["127.0.0.1:8080@0", "127.0.0.1:8080@x", "localhost"] {
+        let value = args(&format!(
+            "upstreams: [{{name: backend, addrs: ['{}']}}]",
+            addr
+        ));
+        assert!(
+            MakeStaticLoadBalancerResolver
+                .make(Args::full(&value), &Supervisor::default())
+                .is_err(),
+            "{}",
+            addr
+        );
+    }
+}
+
+#[tokio::test]
+async fn upstream_scheme() {
+    let value = args(
+        r#"
+        upstreams:
+          - name: plain
+            addrs:
+              - 127.0.0.1:8080
+          - name: secure
+            scheme: https
+            addrs:
+              - 127.0.0.1:8443
+        "#,
+    );
+    let resolver = MakeStaticLoadBalancerResolver
+        .make(Args::full(&value), &Supervisor::default())
+        .unwrap();
+    assert_eq!(resolver.find("plain").unwrap().scheme().as_str(), "http");
+    assert_eq!(resolver.find("secure").unwrap().scheme().as_str(), "https");
+}
+
+#[tokio::test]
+async fn upstream_policy() {
+    for policy in [
+        "RoundRobin",
+        "PeakEwma",
+        "{PeakEwma: {}}",
+        "{PeakEwma: {default-rtt-millis: 50, decay-secs: 5}}",
+    ] {
+        let value = args(&format!(
+            "upstreams: [{{name: backend, policy: {}, addrs: ['127.0.0.1:8080']}}]",
+            policy
+        ));
+        assert!(
+            MakeStaticLoadBalancerResolver
+                .make(Args::full(&value), &Supervisor::default())
+                .is_ok(),
+            "{}",
+            policy
+        );
+    }
+
+    for policy in ["Fastest", "{PeakEwma: {default-rtt-millis: fast}}"] {
+        let value = args(&format!(
+            "upstreams: [{{name: backend, policy: {}, addrs: ['127.0.0.1:8080']}}]",
+            policy
+        ));
+        assert!(
+            MakeStaticLoadBalancerResolver
+                .make(Args::full(&value), &Supervisor::default())
+                .is_err(),
+            "{}",
+            policy
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use async_trait::async_trait;
+use satex_core::Error;
+use satex_load_balancer::discovery::Discovery;
+use satex_load_balancer::selector::{Consistent, RoundRobin};
+use satex_load_balancer::slow_start::SlowStart;
+use satex_load_balancer::{Backend, Backends, LoadBalancer};
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::sleep;
+
+#[derive(Clone, Default)]
+struct MutableDiscovery(Arc<Mutex<BTreeSet<Backend>>>);
+
+impl MutableDiscovery {
+    fn add(&self, addr: &str) {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(Backend::from_str(addr).unwrap());
+    }
+}
+
+#[async_trait]
+impl Discovery for MutableDiscovery {
+    async fn discover(&self) -> Result<(BTreeSet<Backend>, HashMap<u64, bool>), Error> {
+        Ok((self.0.lock().unwrap().clone(), HashMap::new()))
+    }
+}
+
+fn count(load_balancer: &LoadBalancer, addr: &str) -> usize {
+    (0..1000)
+        .filter_map(|_| load_balancer.select(b""))
+        .filter(|backend| backend.addr.to_string() == addr)
+        .count()
+}
+
+#[test]
+fn factor() {
+    let slow_start = SlowStart::new(Duration::from_secs(10)).with_min_weight_percent(0.0);
+    assert_eq!(slow_start.factor(Duration::from_secs(5)), 0.5);
+    assert_eq!(slow_start.factor(Duration::from_secs(10)), 1.0);
+    assert_eq!(slow_start.factor(Duration::from_secs(20)), 1.0);
+
+    let aggressive = slow_start.clone().with_aggression(2.0);
+    assert!((aggressive.factor(Duration::from_millis(2500)) - 0.5).abs() < 1e-9);
+
+    let min_weight = slow_start.with_min_weight_percent(20.0);
+    assert_eq!(min_weight.factor(Duration::from_secs(1)), 0.2);
+}
+
+#[tokio::test]
+async fn ramp_up_new_backend() {
+    let discovery = MutableDiscovery::default();
+    discovery.add("127.0.0.1:8080");
+    let load_balancer = LoadBalancer::new(
+        Backends::new(discovery.clone()),
+        RoundRobin::new(&BTreeSet::new()),
+    )
+    .with_slow_start(SlowStart::new(Duration::from_millis(500)).with_min_weight_percent(0.0));
+    load_balancer.update().await.unwrap();
+
+    // 第一次服务发现得到的后端不需要慢启动
+    assert_eq!(count(&load_balancer, "127.0.0.1:8080"), 1000);
+
+    // 新加入的后端在慢启动期间只接收少量的流量
+    discovery.add("127.0.0.2:8080");
+    load_balancer.update().await.unwrap();
+    let warming = count(&load_balancer, "127.0.0.2:8080");
+    assert!(warming < 200, "warming backend selected {} times", warming);
+
+    // 慢启动结束后按照权重接收流量
+    sleep(Duration::from_millis(600)).await;
+    assert_eq!(count(&load_balancer, "127.0.0.2:8080"), 500);
+}
+
+#[tokio::test]
+async fn consistent_affinity() {
+    let discovery = MutableDiscovery::default();
+    discovery.add("127.0.0.1:8080");
+    let load_balancer = LoadBalancer::new(
+        Backends::new(discovery.clone()),
+        Consistent::new(&BTreeSet::new()),
+    )
+    .with_slow_start(SlowStart::new(Duration::from_secs(60)));
+    load_balancer.update().await.unwrap();
+    discovery.add("127.0.0.2:8080");
+    load_balancer.update().await.unwrap();
+
+    // 慢启动只降低有效权重, 同一个键始终选择同一个后端
+    for key in 0..100u32 {
+        let key = key.to_le_bytes();
+        let first = load_balancer.select(&key).unwrap();
+        for _ in 0..10 {
+            assert_eq!(load_balancer.select(&key).unwrap(), first);
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct EnabledDiscovery(MutableDiscovery);
+
+#[async_trait]
+impl Discovery for EnabledDiscovery {
+    async fn discover(&self) -> Result<(BTreeSet<Backend>, HashMap<u64, bool>), Error> {
+        // 和文件服务发现一样每次都报告启用状态
+        let (backends, _) = self.0.discover().await?;
+        let enablement = backends
+            .iter()
+            .map(|backend| {
+                let mut hasher = DefaultHasher::new();
+                backend.hash(&mut hasher);
+                (hasher.finish(), true)
+            })
+            .collect();
+        Ok((backends, enablement))
+    }
+}
+
+#[tokio::test]
+async fn repeated_enablement() {
+    let discovery = EnabledDiscovery::default();
+    discovery.0.add("127.0.0.1:8080");
+    let load_balancer = LoadBalancer::new(
+        Backends::new(discovery.clone()),
+        RoundRobin::new(&BTreeSet::new()),
+    )
+    .with_slow_start(SlowStart::new(Duration::from_millis(500)).with_min_weight_percent(0.0));
+    load_balancer.update().await.unwrap();
+    discovery.0.add("127.0.0.2:8080");
+    load_balancer.update().await.unwrap();
+    assert!(count(&load_balancer, "127.0.0.2:8080") < 200);
+
+    // 重复报告启用状态不会重新开始慢启动
+    sleep(Duration::from_millis(600)).await;
+    load_balancer.update().await.unwrap();
+    assert_eq!(count(&load_balancer, "127.0.0.2:8080"), 500);
+}
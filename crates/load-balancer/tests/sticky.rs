@@ -0,0 +1,107 @@
+use http::header::COOKIE;
+use http::{HeaderMap, HeaderValue};
+use satex_core::background::Supervisor;
+use satex_core::component::Args;
+use satex_load_balancer::Backend;
+use satex_load_balancer::resolver::{
+    LoadBalancerResolver, MakeLoadBalancerResolver, MakeStaticLoadBalancerResolver,
+};
+use satex_load_balancer::sticky::StickySession;
+use serde_yaml::Value;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::time::sleep;
+
+fn headers(cookie: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(COOKIE, HeaderValue::from_str(cookie).unwrap());
+    headers
+}
+
+#[test]
+fn encode_and_decode() {
+    let backend = Backend::from_str("127.0.0.1:8080").unwrap();
+    let sticky_session = StickySession::new("backend");
+    let value = sticky_session.encode(&backend);
+    assert_eq!(sticky_session.decode(&value), Some(backend.addr));
+    assert_eq!(
+        sticky_session.find(&headers(&format!("a=b; backend={}", value))),
+        Some(backend.addr)
+    );
+    assert_eq!(sticky_session.find(&headers("backend=invalid")), None);
+
+    // 签名不正确或者缺少签名时忽略
+    let signed = StickySession::new("backend").with_secret("secret");
+    let value = signed.encode(&backend);
+    assert_eq!(signed.decode(&value), Some(backend.addr));
+    assert_eq!(signed.decode(&sticky_session.encode(&backend)), None);
+    assert_eq!(
+        StickySession::new("backend")
+            .with_secret("other")
+            .decode(&value),
+        None
+    );
+    let (addr, _) = value.split_once('.').unwrap();
+    let forged = StickySession::new("backend")
+        .with_secret("other")
+        .encode(&Backend::from_str("127.0.0.2:8080").unwrap());
+    let (_, tag) = forged.split_once('.').unwrap();
+    assert_eq!(signed.decode(&format!("{}.{}", addr, tag)), None);
+}
+
+#[test]
+fn set_cookie() {
+    let backend = Backend::from_str("127.0.0.1:8080").unwrap();
+    let sticky_session = StickySession::new("backend")
+        .with_ttl(Some(Duration::from_secs(60)))
+        .with_path("/api")
+        .with_secure(true);
+    let cookie = sticky_session.set_cookie(&backend);
+    assert!(cookie.starts_with(&format!("backend={}", sticky_session.encode(&backend))));
+    assert!(cookie.contains("Path=/api"));
+    assert!(cookie.contains("HttpOnly"));
+    assert!(cookie.contains("Secure"));
+    assert!(cookie.contains("Max-Age=60"));
+}
+
+#[tokio::test]
+async fn select_sticky() {
+    let value = serde_yaml::from_str::<Value>(
+        r#"
+        upstreams:
+          - name: backend
+            health-check:
+              enabled: false
+            sticky:
+              cookie: SESSION
+              secret: secret
+            addrs:
+              - 127.0.0.1:8080
+              - 127.0.0.2:8080
+        "#,
+    )
+    .unwrap();
+    let resolver = MakeStaticLoadBalancerResolver
+        .make(Args::full(&value), &Supervisor::default())
+        .unwrap();
+    sleep(Duration::from_millis(100)).await;
+
+    let load_balancer = resolver.find("backend").unwrap();
+    let sticky_session = load_balancer.sticky_session().unwrap();
+    assert_eq!(sticky_session.name(), "SESSION");
+
+    let backend = Backend::from_str("127.0.0.2:8080").unwrap();
+    let cookie = format!("SESSION={}", sticky_session.encode(&backend));
+    for _ in 0..4 {
+        assert_eq!(
+            load_balancer.select_sticky(&headers(&cookie)),
+            Some(backend.clone())
+        );
+    }
+
+    // 后端不存在时回退到选择算法
+    let removed = Backend::from_str("127.0.0.3:8080").unwrap();
+    let cookie = format!("SESSION={}", sticky_session.encode(&removed));
+    assert_eq!(load_balancer.select_sticky(&headers(&cookie)), None);
+    assert_eq!(load_balancer.select_sticky(&HeaderMap::new()), None);
+}
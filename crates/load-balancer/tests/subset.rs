@@ -0,0 +1,95 @@
+use satex_core::background::Supervisor;
+use satex_core::component::Args;
+use satex_load_balancer::discovery::{Discovery, StaticAddr, StaticLookupDiscovery};
+use satex_load_balancer::resolver::{
+    LoadBalancerResolver, MakeLoadBalancerResolver, MakeStaticLoadBalancerResolver,
+};
+use satex_load_balancer::{Backend, Labels};
+use serde_yaml::Value;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::time::sleep;
+
+fn labels(pairs: &[(&str, &str)]) -> Labels {
+    pairs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+fn subset(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn matches() {
+    let backend = Backend::from_str("127.0.0.1:8080")
+        .unwrap()
+        .with_labels(labels(&[("version", "v1"), ("zone", "a")]));
+    assert!(backend.matches(&[]));
+    assert!(backend.matches(&subset(&[("version", "v1")])));
+    assert!(backend.matches(&subset(&[("version", "v1"), ("zone", "a")])));
+    assert!(!backend.matches(&subset(&[("version", "v2")])));
+    assert!(!backend.matches(&subset(&[("version", "v1"), ("tenant", "a")])));
+
+    // 没有标签的后端只匹配空的子集
+    let backend = Backend::from_str("127.0.0.1:8080")
+        .unwrap()
+        .with_labels(Labels::default());
+    assert_eq!(backend.labels(), None);
+    assert!(backend.matches(&[]));
+    assert!(!backend.matches(&subset(&[("version", "v1")])));
+}
+
+#[tokio::test]
+async fn static_labels() {
+    let discovery = StaticLookupDiscovery::new([
+        StaticAddr::new("127.0.0.1:8080", 1).with_labels(labels(&[("version", "v1")])),
+        StaticAddr::new("127.0.0.2:8080", 1),
+    ]);
+    let (backends, _) = discovery.discover().await.unwrap();
+    let labels = backends
+        .iter()
+        .map(|backend| backend.labels().cloned())
+        .collect::<Vec<_>>();
+    assert_eq!(labels, vec![Some(self::labels(&[("version", "v1")])), None]);
+}
+
+#[tokio::test]
+async fn select_subset() {
+    let value = serde_yaml::from_str::<Value>(
+        r#"
+        upstreams:
+          - name: backend
+            health-check:
+              enabled: false
+            addrs:
+              - addr: 127.0.0.1:8080
+                labels:
+                  version: v1
+              - addr: 127.0.0.2:8080
+                labels:
+                  version: v2
+              - 127.0.0.3:8080
+        "#,
+    )
+    .unwrap();
+    let resolver = MakeStaticLoadBalancerResolver
+        .make(Args::full(&value), &Supervisor::default())
+        .unwrap();
+    sleep(Duration::from_millis(100)).await;
+
+    let load_balancer = resolver.find("backend").unwrap();
+    for (version, addr) in [("v1", "127.0.0.1:8080"), ("v2", "127.0.0.2:8080")] {
+        let subset = subset(&[("version", version)]);
+        for _ in 0..4 {
+            let backend = load_balancer
+                .select_with(b"", |backend, health| health && backend.matches(&subset))
+                .unwrap();
+            assert_eq!(backend.addr.to_string(), addr);
+        }
+    }
+}
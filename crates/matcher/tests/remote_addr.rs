@@ -45,6 +45,16 @@ async fn make_with_shortcut() {
     matches_shortcut(&mut parts, "Accept, 127.0.1.2/16").await;
     matches_shortcut(&mut parts, "Accept, 127.1.1.2/8").await;
     matches_shortcut(&mut parts, "Accept, 128.1.1.2/0").await;
+    matches_shortcut(&mut parts, "Accept, 127.0.0.1/32, 10.0.0.1").await;
+    matches_shortcut(&mut parts, "Accept, ::1, 127.0.0.1").await;
+}
+
+#[tokio::test]
+async fn make_with_invalid_shortcut() {
+    // 缺少地址列表
+    assert!(MakeRemoteAddrRouteMatcher
+        .make(Args::shortcut("Accept"))
+        .is_err());
 }
 
 #[tokio::test]
@@ -1,6 +1,8 @@
+use crate::real_ip::RealIp;
 use actix_service::{Service as ActixService, ServiceFactory};
 use actix_tls::accept::rustls_0_23::TlsStream;
 use futures::future::LocalBoxFuture;
+use http::uri::Scheme;
 use http::Response;
 use hyper::body::Incoming;
 use hyper::service::{service_fn, Service as HyperService};
@@ -8,24 +10,32 @@ use hyper::Request;
 use hyper_util::rt::TokioIo;
 use hyper_util::server::conn::auto::Builder as ConnectorBuilder;
 use satex_core::executor::SpawnLocalExecutor;
-use satex_core::extension::ClientAddr;
+use satex_core::extension::{ClientAddr, ClientScheme, PeerAddr};
+use satex_core::metrics::{GaugeGuard, SERVER_ACTIVE_CONNECTIONS};
 use satex_core::util::try_downcast;
 use satex_core::{BoxError, Error};
+use prometheus::IntGauge;
 use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::thread::current;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
 use tokio_util::either::Either;
 use tracing::debug;
 
 pub(crate) struct HttpServiceFactory<M> {
+    listener: Arc<str>,
+    real_ip: Option<RealIp>,
     make_service: M,
     builder: ConnectorBuilder<SpawnLocalExecutor>,
 }
 
 impl<M> HttpServiceFactory<M> {
-    pub fn new(make_service: M) -> Self {
+    pub fn new(listener: Arc<str>, real_ip: Option<RealIp>, make_service: M) -> Self {
         Self {
+            listener,
+            real_ip,
             make_service,
             builder: ConnectorBuilder::new(SpawnLocalExecutor::new()),
         }
@@ -51,11 +61,17 @@ where
     fn new_service(&self, _: Self::Config) -> Self::Future {
         let make_service = self.make_service.clone();
         let builder = self.builder.clone();
+        let real_ip = self.real_ip.clone();
+        // 服务在工作线程中创建, 使用工作线程名称作为指标标签
+        let worker = current().name().unwrap_or_default().to_string();
+        let connections = SERVER_ACTIVE_CONNECTIONS.with_label_values(&[&*self.listener, &worker]);
         Box::pin(async move {
-            make_service
-                .call(())
-                .await
-                .map(|service| HttpService { service, builder })
+            make_service.call(()).await.map(|service| HttpService {
+                service,
+                builder,
+                real_ip,
+                connections,
+            })
         })
     }
 }
@@ -63,6 +79,8 @@ where
 pub(crate) struct HttpService<S> {
     service: S,
     builder: ConnectorBuilder<SpawnLocalExecutor>,
+    real_ip: Option<RealIp>,
+    connections: IntGauge,
 }
 
 impl<A, S, ResBody> ActixService<A> for HttpService<S>
@@ -82,25 +100,38 @@ where
     }
 
     fn call(&self, stream: A) -> Self::Future {
-        let (client_addr, stream) = match try_downcast::<TcpStream, _>(stream) {
-            Ok(stream) => (get_client_addr(&stream), Either::Left(stream)),
+        let (peer_addr, client_scheme, stream) = match try_downcast::<TcpStream, _>(stream) {
+            Ok(stream) => (get_client_addr(&stream), Scheme::HTTP, Either::Left(stream)),
             Err(stream) => match try_downcast::<TlsStream<TcpStream>, _>(stream) {
-                Ok(stream) => (get_client_addr(stream.get_ref().0), Either::Right(stream)),
+                Ok(stream) => (
+                    get_client_addr(stream.get_ref().0),
+                    Scheme::HTTPS,
+                    Either::Right(stream),
+                ),
                 Err(_) => unreachable!(),
             },
         };
 
         let service = self.service.clone();
         let builder = self.builder.clone();
+        let real_ip = self.real_ip.clone();
+        let connections = GaugeGuard::new(self.connections.clone());
         Box::pin(async move {
+            let _connections = connections;
             builder
                 .serve_connection_with_upgrades(
                     TokioIo::new(stream),
                     service_fn(move |mut request: Request<Incoming>| {
+                        // 连接来自可信代理时使用请求头中的客户端地址
+                        let client_addr = real_ip
+                            .as_ref()
+                            .and_then(|real_ip| real_ip.resolve(peer_addr, request.headers()))
+                            .unwrap_or(peer_addr);
                         debug!("client ({:?}) request:\n{:#?}", client_addr, request);
-                        request
-                            .extensions_mut()
-                            .insert(ClientAddr::new(client_addr));
+                        let extensions = request.extensions_mut();
+                        extensions.insert(ClientAddr::new(client_addr));
+                        extensions.insert(PeerAddr::new(peer_addr));
+                        extensions.insert(ClientScheme::new(client_scheme.clone()));
                         service.call(request)
                     }),
                 )
@@ -1,5 +1,7 @@
 mod factory;
+mod real_ip;
 mod server;
 
+pub use real_ip::*;
 pub use server::*;
 pub mod router;
@@ -0,0 +1,152 @@
+use http::header::FORWARDED;
+use http::{HeaderMap, HeaderName};
+use satex_core::util::Cidr;
+use satex_layer::forwarded::X_FORWARDED_FOR;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+pub const X_REAL_IP: HeaderName = HeaderName::from_static("x-real-ip");
+
+///
+/// 获取真实客户端地址的请求头
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RealIpHeader {
+    ///
+    /// `X-Forwarded-For`, 从右往左取第一个不在可信代理中的地址
+    ///
+    XForwardedFor,
+
+    ///
+    /// `X-Real-IP`
+    ///
+    XRealIp,
+
+    ///
+    /// RFC 7239 `Forwarded`的`for`参数, 从右往左取第一个不在可信代理中的地址
+    ///
+    Forwarded,
+}
+
+///
+/// 真实客户端地址提取, 连接来自可信代理时从请求头中获取客户端地址
+///
+#[derive(Debug, Clone)]
+pub struct RealIp {
+    trusted_proxies: Arc<[Cidr]>,
+    headers: Arc<[RealIpHeader]>,
+}
+
+impl RealIp {
+    pub fn new(trusted_proxies: Vec<Cidr>) -> Self {
+        Self {
+            trusted_proxies: trusted_proxies.into(),
+            headers: Arc::new([
+                RealIpHeader::XForwardedFor,
+                RealIpHeader::XRealIp,
+                RealIpHeader::Forwarded,
+            ]),
+        }
+    }
+
+    ///
+    /// 设置获取客户端地址的请求头, 按照顺序使用第一个有效的请求头
+    ///
+    pub fn with_headers(mut self, headers: Vec<RealIpHeader>) -> Self {
+        self.headers = headers.into();
+        self
+    }
+
+    ///
+    /// 获取真实客户端地址
+    ///
+    /// # Arguments
+    ///
+    /// * `peer`: 连接的对端地址
+    /// * `headers`: 请求头
+    ///
+    /// returns: Option<SocketAddr>, 对端不是可信代理或者请求头中没有有效的地址时返回`None`,
+    /// 请求头中的地址不带端口时端口为`0`
+    ///
+    pub fn resolve(&self, peer: SocketAddr, headers: &HeaderMap) -> Option<SocketAddr> {
+        if !self.trusted(&peer.ip()) {
+            return None;
+        }
+        self.headers.iter().find_map(|header| match header {
+            RealIpHeader::XForwardedFor => self.rightmost(
+                values(headers, X_FORWARDED_FOR)
+                    .flat_map(|value| value.split(','))
+                    .map(parse_node)
+                    .collect(),
+            ),
+            RealIpHeader::XRealIp => headers
+                .get(X_REAL_IP)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_node),
+            RealIpHeader::Forwarded => self.rightmost(
+                values(headers, FORWARDED)
+                    .flat_map(|value| value.split(','))
+                    .map(forwarded_for)
+                    .collect(),
+            ),
+        })
+    }
+
+    fn trusted(&self, ip: &IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    ///
+    /// 从右往左跳过可信代理, 遇到无效的地址时放弃该请求头, 全部都是可信代理时取最左边的地址
+    ///
+    fn rightmost(&self, nodes: Vec<Option<SocketAddr>>) -> Option<SocketAddr> {
+        let mut client = None;
+        for node in nodes.into_iter().rev() {
+            let node = node?;
+            client = Some(node);
+            if !self.trusted(&node.ip()) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+///
+/// 请求头的所有值, 包含非法字符的值作为空值处理
+///
+fn values(headers: &HeaderMap, name: HeaderName) -> impl Iterator<Item = &str> {
+    headers
+        .get_all(name)
+        .into_iter()
+        .map(|value| value.to_str().unwrap_or_default())
+}
+
+///
+/// 获取`Forwarded`元素中`for`参数的地址
+///
+fn forwarded_for(element: &str) -> Option<SocketAddr> {
+    element
+        .split(';')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("for"))
+        .and_then(|(_, value)| parse_node(value))
+}
+
+///
+/// 解析地址, 支持`192.168.1.1`、`192.168.1.1:8080`、`[::1]`、`[::1]:8080`以及带引号的格式
+///
+fn parse_node(value: &str) -> Option<SocketAddr> {
+    let value = value.trim().trim_matches('"');
+    if let Ok(addr) = value.parse::<SocketAddr>() {
+        return Some(addr);
+    }
+    value
+        .strip_prefix('[')
+        .and_then(|value| value.strip_suffix(']'))
+        .unwrap_or(value)
+        .parse::<IpAddr>()
+        .ok()
+        .map(|ip| SocketAddr::new(ip, 0))
+}
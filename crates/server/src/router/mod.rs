@@ -9,26 +9,50 @@ use futures::future::{BoxFuture, LocalBoxFuture};
 use http::request::Parts;
 use http::{Request, Response, StatusCode};
 use hyper::service::Service as HyperService;
+use satex_core::background::Supervisor;
 use satex_core::body::Body;
 use satex_core::extension::{RawUri, RouteId};
+use satex_core::metrics::{
+    GaugeGuard, HTTP_REQUESTS_IN_FLIGHT, HTTP_REQUESTS_TOTAL, HTTP_REQUEST_DURATION_SECONDS,
+    method_label, status_class,
+};
 use satex_core::util::ResponseExt;
 use satex_core::{BoxError, Error};
 use satex_matcher::RouteMatcher;
 use std::future::{poll_fn, ready, Ready};
 use std::pin::pin;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::RwLock;
 use tower::Service;
-use tracing::{debug, info};
+use satex_core::propagation::set_parent;
+use tracing::field::Empty;
+use tracing::{debug, info, info_span, Instrument, Span};
 
 #[derive(Clone, Default)]
 pub struct Router {
     routes: Vec<Route>,
+    supervisor: Supervisor,
 }
 
 impl Router {
     pub fn new(routes: Vec<Route>) -> Self {
-        Self { routes }
+        Self {
+            routes,
+            supervisor: Supervisor::default(),
+        }
+    }
+
+    ///
+    /// 设置创建路由时启动的后台任务的监督者, 路由被替换或者清空时停止这些后台任务
+    ///
+    pub fn with_supervisor(mut self, supervisor: Supervisor) -> Self {
+        self.supervisor = supervisor;
+        self
+    }
+
+    pub fn supervisor(&self) -> &Supervisor {
+        &self.supervisor
     }
 
     pub fn into_static_service(self) -> MakeRouterService {
@@ -42,20 +66,24 @@ impl Router {
     {
         let routes = Arc::new(RwLock::new(self.routes));
         let make_service = MakeRouterService::new(InternalRouter::Dynamic(routes.clone()));
+        let mut supervisor = self.supervisor;
 
-        // 更新路由任务
+        // 更新路由任务, 替换路由后停止上一代路由的后台任务
         let future = async move {
             let mut events = pin!(events);
             while let Some(event) = events.next().await {
-                match event {
+                let previous = match event {
                     Event::Set(router) => {
                         info!("refresh routes: {}", router.routes.len());
                         *routes.write().await = router.routes;
+                        std::mem::replace(&mut supervisor, router.supervisor)
                     }
                     Event::Clear => {
                         routes.write().await.clear();
+                        std::mem::take(&mut supervisor)
                     }
-                }
+                };
+                previous.cancel();
             }
         };
         f(Box::pin(future));
@@ -88,7 +116,22 @@ where
 
     fn call(&self, request: Request<ReqBody>) -> Self::Future {
         let router = self.clone();
-        Box::pin(async move {
+        let method = method_label(request.method());
+
+        // 路由span, 父上下文来自请求头中的`traceparent`/`tracestate`
+        let span = info_span!(
+            "route",
+            otel.name = method,
+            otel.kind = "server",
+            route = Empty,
+            http.request.method = method,
+            http.response.status_code = Empty,
+        );
+        set_parent(&span, request.headers());
+
+        let future = async move {
+            let _in_flight = GaugeGuard::new(HTTP_REQUESTS_IN_FLIGHT.clone());
+            let start = Instant::now();
             let (mut parts, body) = request.into_parts();
 
             // 如果是动态路由, 找到匹配的路由后就会释放读锁
@@ -99,7 +142,16 @@ where
                 }
             };
 
-            match route {
+            let route_id = match &route {
+                Ok(Some(route)) => route.id().to_string(),
+                _ => String::new(),
+            };
+            let span = Span::current();
+            if !route_id.is_empty() {
+                span.record("route", route_id.as_str());
+                span.record("otel.name", format!("{} {}", method, route_id));
+            }
+            let result = match route {
                 Ok(Some(mut route)) => {
                     match poll_fn(|ctx| {
                         <Route as Service<Request<ReqBody>>>::poll_ready(&mut route, ctx)
@@ -126,8 +178,24 @@ where
                 }
                 Ok(None) => Ok(Response::new(Body::empty()).with_status(StatusCode::NOT_FOUND)),
                 Err(e) => Err(e),
-            }
-        })
+            };
+
+            // 记录请求指标, 未匹配到路由时路由标签为空
+            let status = match &result {
+                Ok(response) => {
+                    span.record("http.response.status_code", response.status().as_u16());
+                    status_class(response.status())
+                }
+                Err(_) => status_class(StatusCode::INTERNAL_SERVER_ERROR),
+            };
+            let labels = [route_id.as_str(), method, status];
+            HTTP_REQUESTS_TOTAL.with_label_values(&labels).inc();
+            HTTP_REQUEST_DURATION_SECONDS
+                .with_label_values(&labels)
+                .observe(start.elapsed().as_secs_f64());
+            result
+        };
+        Box::pin(future.instrument(span))
     }
 }
 
@@ -1,4 +1,5 @@
 use crate::factory::HttpServiceFactory;
+use crate::real_ip::RealIp;
 use actix_server::Server as ActixServer;
 use actix_service::ServiceFactoryExt;
 use actix_tls::accept::rustls_0_23::reexports::ServerConfig;
@@ -11,6 +12,7 @@ use satex_core::{BoxError, Error};
 use std::fs::File;
 use std::io::BufReader;
 use std::net::ToSocketAddrs;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub enum Builder {
@@ -37,6 +39,11 @@ pub struct RawBuilder {
     /// 每个工作线程允许的最大并发链接数量
     ///
     max_concurrent_connections: Option<usize>,
+
+    ///
+    /// 真实客户端地址提取
+    ///
+    real_ip: Option<RealIp>,
 }
 
 impl RawBuilder {
@@ -59,6 +66,11 @@ impl RawBuilder {
         self
     }
 
+    pub fn real_ip(mut self, real_ip: RealIp) -> Self {
+        self.real_ip = Some(real_ip);
+        self
+    }
+
     pub fn tls(self) -> TlsBuilder {
         TlsBuilder::new(self)
     }
@@ -134,6 +146,11 @@ impl TlsBuilder {
         self
     }
 
+    pub fn real_ip(mut self, real_ip: RealIp) -> Self {
+        self.raw = self.raw.real_ip(real_ip);
+        self
+    }
+
     pub fn make_service<M>(self, make_service: M) -> Server<M> {
         Server {
             builder: Builder::Tls(self),
@@ -184,6 +201,7 @@ where
             builder,
             make_service,
         } = self;
+        let listener = Arc::<str>::from(name.as_ref());
         let (config, tls_acceptor) = match builder {
             Builder::Raw(builder) => (builder, None),
             Builder::Tls(builder) => {
@@ -202,17 +220,24 @@ where
         if let Some(backlog) = config.backlog {
             builder = builder.backlog(backlog);
         }
+        let real_ip = config.real_ip;
         builder
             .bind(name, addrs, move || match &tls_acceptor {
                 Some(tls_acceptor) => actix_service::boxed::factory(
                     tls_acceptor
                         .clone()
                         .map_err(Error::new)
-                        .and_then(HttpServiceFactory::new(make_service.clone())),
+                        .and_then(HttpServiceFactory::new(
+                            listener.clone(),
+                            real_ip.clone(),
+                            make_service.clone(),
+                        )),
                 ),
-                None => {
-                    actix_service::boxed::factory(HttpServiceFactory::new(make_service.clone()))
-                }
+                None => actix_service::boxed::factory(HttpServiceFactory::new(
+                    listener.clone(),
+                    real_ip.clone(),
+                    make_service.clone(),
+                )),
             })
             .map(|builder| builder.run())
             .map_err(Error::new)
@@ -0,0 +1,96 @@
+use http::HeaderMap;
+use satex_core::util::Cidr;
+use satex_server::{RealIp, RealIpHeader};
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+fn real_ip() -> RealIp {
+    RealIp::new(vec![
+        Cidr::from_str("10.0.0.0/8").unwrap(),
+        Cidr::from_str("fd00::/8").unwrap(),
+    ])
+}
+
+fn resolve(real_ip: &RealIp, peer: &str, headers: &[(&'static str, &str)]) -> Option<String> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        map.append(*name, value.parse().unwrap());
+    }
+    real_ip
+        .resolve(peer.parse::<SocketAddr>().unwrap(), &map)
+        .map(|addr| addr.to_string())
+}
+
+#[test]
+fn untrusted_peer() {
+    let real_ip = real_ip();
+    let headers = [("x-forwarded-for", "1.1.1.1"), ("x-real-ip", "1.1.1.1")];
+    assert_eq!(resolve(&real_ip, "192.168.1.1:50000", &headers), None);
+}
+
+#[test]
+fn x_forwarded_for() {
+    let real_ip = real_ip();
+
+    // 从右往左跳过可信代理
+    let headers = [
+        ("x-forwarded-for", "6.6.6.6, 1.1.1.1"),
+        ("x-forwarded-for", "10.0.0.2"),
+    ];
+    assert_eq!(
+        resolve(&real_ip, "10.0.0.1:50000", &headers),
+        Some("1.1.1.1:0".to_string())
+    );
+
+    // 带端口以及IPv6地址
+    let headers = [("x-forwarded-for", "[2001:db8::1]:8080, [fd00::1]")];
+    assert_eq!(
+        resolve(&real_ip, "[fd00::2]:50000", &headers),
+        Some("[2001:db8::1]:8080".to_string())
+    );
+
+    // 全部都是可信代理时取最左边的地址
+    let headers = [("x-forwarded-for", "10.0.0.3, 10.0.0.2")];
+    assert_eq!(
+        resolve(&real_ip, "10.0.0.1:50000", &headers),
+        Some("10.0.0.3:0".to_string())
+    );
+}
+
+#[test]
+fn fallback() {
+    let real_ip = real_ip();
+
+    // `X-Forwarded-For`中存在无效的地址时使用下一个请求头
+    let headers = [
+        ("x-forwarded-for", "1.1.1.1, unknown"),
+        ("x-real-ip", "2.2.2.2"),
+    ];
+    assert_eq!(
+        resolve(&real_ip, "10.0.0.1:50000", &headers),
+        Some("2.2.2.2:0".to_string())
+    );
+
+    let headers = [(
+        "forwarded",
+        "for=3.3.3.3;proto=https, for=\"[fd00::1]:4711\"",
+    )];
+    assert_eq!(
+        resolve(&real_ip, "10.0.0.1:50000", &headers),
+        Some("3.3.3.3:0".to_string())
+    );
+
+    assert_eq!(resolve(&real_ip, "10.0.0.1:50000", &[]), None);
+}
+
+#[test]
+fn headers() {
+    let real_ip = real_ip().with_headers(vec![RealIpHeader::XRealIp]);
+    let headers = [("x-forwarded-for", "1.1.1.1"), ("x-real-ip", "2.2.2.2")];
+    assert_eq!(
+        resolve(&real_ip, "10.0.0.1:50000", &headers),
+        Some("2.2.2.2:0".to_string())
+    );
+    let headers = [("x-forwarded-for", "1.1.1.1")];
+    assert_eq!(resolve(&real_ip, "10.0.0.1:50000", &headers), None);
+}
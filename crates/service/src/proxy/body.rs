@@ -0,0 +1,51 @@
+use bytes::Bytes;
+use http_body::{Body, Frame, SizeHint};
+use pin_project_lite::pin_project;
+use satex_load_balancer::Inflight;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+pin_project! {
+    ///
+    /// 响应体读取完成、出错或者被释放时结束对后端请求的跟踪
+    ///
+    pub(crate) struct InflightBody<B> {
+        #[pin]
+        inner: B,
+        inflight: Option<Inflight>,
+    }
+}
+
+impl<B> InflightBody<B> {
+    pub(crate) fn new(inner: B, inflight: Option<Inflight>) -> Self {
+        Self { inner, inflight }
+    }
+}
+
+impl<B> Body for InflightBody<B>
+where
+    B: Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+        if !matches!(frame, Some(Ok(_))) {
+            this.inflight.take();
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
@@ -1,16 +1,34 @@
-use hyper_rustls::{ConfigBuilderExt, HttpsConnector};
-use hyper_util::client::legacy::connect::HttpConnector;
+use crate::proxy::tls::TlsConfig;
+use futures::future::BoxFuture;
+use http::Uri;
+use hyper::rt::{Read, ReadBufCursor, Write};
+use hyper_rustls::HttpsConnector;
+use hyper_util::client::legacy::connect::{Connected, Connection, HttpConnector};
 use hyper_util::client::legacy::Builder;
-use rustls::ClientConfig as ClientTlsConfig;
+use pin_project_lite::pin_project;
 use satex_core::body::Body;
 use satex_core::executor::SpawnLocalExecutor;
+use satex_core::{BoxError, Error};
+use satex_load_balancer::circuit_breaker::ConnectionPermit;
+use satex_load_balancer::LoadBalancer;
 use serde::{Deserialize, Serialize};
+use std::io::IoSlice;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
 use std::time::Duration;
+use tower::Service;
 
-pub(crate) type Client = hyper_util::client::legacy::Client<HttpsConnector<HttpConnector>, Body>;
+pub(crate) type Client =
+    hyper_util::client::legacy::Client<LimitedConnector<HttpsConnector<HttpConnector>>, Body>;
 
-impl From<ClientConfig> for Client {
-    fn from(config: ClientConfig) -> Self {
+impl ClientConfig {
+    ///
+    /// 创建转发请求的客户端, 负载均衡器设置了熔断器时通过熔断器限制到每个后端的连接数
+    ///
+    pub(crate) fn client(self, load_balancer: Option<Arc<LoadBalancer>>) -> Result<Client, Error> {
+        let config = self;
         let mut builder = Builder::new(SpawnLocalExecutor::new());
 
         // basic
@@ -72,6 +90,9 @@ impl From<ClientConfig> for Client {
         if let Some(http2_only) = config.http2_only {
             builder.http2_only(http2_only);
         }
+        if config.protocol == Protocol::Http2 {
+            builder.http2_only(true);
+        }
         if let Some(http2_keep_alive_timeout_secs) = config.http2_keep_alive_timeout_secs {
             builder.http2_keep_alive_timeout(Duration::from_secs(http2_keep_alive_timeout_secs));
         }
@@ -103,7 +124,12 @@ impl From<ClientConfig> for Client {
             builder.http2_max_send_buf_size(http2_max_send_buf_size);
         }
 
-        builder.build(connector(&config))
+        connector(&config).map(|connector| {
+            builder.build(LimitedConnector {
+                inner: connector,
+                load_balancer,
+            })
+        })
     }
 }
 
@@ -204,9 +230,37 @@ pub struct ClientConfig {
 
     #[serde(default)]
     http2_max_send_buf_size: Option<usize>,
+
+    #[serde(default)]
+    tls: TlsConfig,
+
+    #[serde(default)]
+    protocol: Protocol,
 }
 
-fn connector(config: &ClientConfig) -> HttpsConnector<HttpConnector> {
+///
+/// 转发到后端使用的HTTP协议
+///
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Protocol {
+    ///
+    /// `http`使用HTTP/1.1, `https`通过ALPN协商HTTP/2或者HTTP/1.1
+    ///
+    #[default]
+    Auto,
+
+    ///
+    /// 只使用HTTP/1.1
+    ///
+    Http1,
+
+    ///
+    /// 只使用HTTP/2, `http`使用h2c(prior knowledge), `https`通过ALPN协商`h2`
+    ///
+    Http2,
+}
+
+fn connector(config: &ClientConfig) -> Result<HttpsConnector<HttpConnector>, Error> {
     // http connector 配置
     let mut connector = HttpConnector::new();
     connector.enforce_http(false);
@@ -221,16 +275,123 @@ fn connector(config: &ClientConfig) -> HttpsConnector<HttpConnector> {
     connector
         .set_happy_eyeballs_timeout(config.happy_eyeballs_timeout_secs.map(Duration::from_secs));
 
-    // http tls 配置
-    let tls_config = ClientTlsConfig::builder()
-        .with_native_roots()
-        .map(|builder| builder.with_no_client_auth())
-        .expect("TLS config create with native roots error!");
-
     // https connector 配置
-    HttpsConnector::<HttpConnector>::builder()
-        .with_tls_config(tls_config)
-        .https_or_http()
-        .enable_all_versions()
-        .wrap_connector(connector)
+    config.tls.connector(connector, config.protocol)
+}
+
+///
+/// 限制每个后端连接数的连接器, 建立连接之前通过熔断器占用到后端的连接, 连接关闭时归还,
+/// 被熔断器拒绝时返回[`Rejection`](satex_load_balancer::circuit_breaker::Rejection)错误
+///
+#[derive(Clone)]
+pub struct LimitedConnector<C> {
+    inner: C,
+    load_balancer: Option<Arc<LoadBalancer>>,
+}
+
+impl<C> Service<Uri> for LimitedConnector<C>
+where
+    C: Service<Uri> + Clone + Send + 'static,
+    C::Future: Send,
+    C::Error: Into<BoxError>,
+{
+    type Response = LimitedStream<C::Response>;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        // 使用已经就绪的连接器, 占用连接之后再建立连接
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let load_balancer = self.load_balancer.clone();
+        Box::pin(async move {
+            let circuit_breaker = load_balancer
+                .as_deref()
+                .and_then(LoadBalancer::circuit_breaker);
+            let permit = match (circuit_breaker, socket_addr(&uri)) {
+                (Some(circuit_breaker), Some(addr)) => Some(circuit_breaker.connect(addr).await?),
+                _ => None,
+            };
+            let inner = inner.call(uri).await.map_err(Into::into)?;
+            Ok(LimitedStream {
+                inner,
+                _permit: permit,
+            })
+        })
+    }
+}
+
+///
+/// 连接的后端地址, 主机不是IP地址时返回`None`
+///
+fn socket_addr(uri: &Uri) -> Option<SocketAddr> {
+    let host = uri.host()?.trim_start_matches('[').trim_end_matches(']');
+    let ip = host.parse::<IpAddr>().ok()?;
+    let port = match (uri.port_u16(), uri.scheme_str()) {
+        (Some(port), _) => port,
+        (None, Some("https")) => 443,
+        (None, _) => 80,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+pin_project! {
+    ///
+    /// 占用熔断器连接数的连接, 关闭时归还
+    ///
+    pub struct LimitedStream<T> {
+        #[pin]
+        inner: T,
+        _permit: Option<ConnectionPermit>,
+    }
+}
+
+impl<T: Read> Read for LimitedStream<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: ReadBufCursor<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        self.project().inner.poll_read(cx, buf)
+    }
+}
+
+impl<T: Write> Write for LimitedStream<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.project().inner.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.project().inner.poll_shutdown(cx)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        self.project().inner.poll_write_vectored(cx, bufs)
+    }
+}
+
+impl<T: Connection> Connection for LimitedStream<T> {
+    fn connected(&self) -> Connected {
+        self.inner.connected()
+    }
 }
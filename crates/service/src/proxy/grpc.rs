@@ -0,0 +1,195 @@
+use crate::proxy::retry::Failure;
+use bytes::Bytes;
+use http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use http::{HeaderMap, HeaderName, HeaderValue, Response, StatusCode};
+use http_body::{Body, Frame, SizeHint};
+use percent_encoding::{AsciiSet, CONTROLS, utf8_percent_encode};
+use pin_project_lite::pin_project;
+use satex_core::BoxError;
+use satex_core::body::Body as SatexBody;
+use std::fmt::Display;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tracing::debug;
+
+const GRPC_STATUS: HeaderName = HeaderName::from_static("grpc-status");
+const GRPC_MESSAGE: HeaderName = HeaderName::from_static("grpc-message");
+
+/// `grpc-message`需要编码的字符, 只保留除`%`以外的可打印ASCII字符
+const GRPC_MESSAGE_ENCODE_SET: &AsciiSet = &CONTROLS.add(b'%');
+
+///
+/// gRPC状态码
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Code {
+    Unknown = 2,
+    DeadlineExceeded = 4,
+    PermissionDenied = 7,
+    Unimplemented = 12,
+    Internal = 13,
+    Unavailable = 14,
+    Unauthenticated = 16,
+}
+
+impl Code {
+    ///
+    /// 没有`grpc-status`的HTTP响应状态码对应的gRPC状态码
+    ///
+    fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::BAD_REQUEST => Code::Internal,
+            StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+            StatusCode::FORBIDDEN => Code::PermissionDenied,
+            StatusCode::NOT_FOUND => Code::Unimplemented,
+            StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT => Code::Unavailable,
+            _ => Code::Unknown,
+        }
+    }
+
+    fn from_failure(failure: &Failure) -> Self {
+        match failure {
+            Failure::Timeout => Code::DeadlineExceeded,
+            Failure::Client(_) | Failure::Other(_) => Code::Unavailable,
+        }
+    }
+}
+
+///
+/// 是否是gRPC请求, `Content-Type`为`application/grpc`或者`application/grpc+proto`等
+///
+pub(crate) fn is_grpc(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .strip_prefix("application/grpc")
+                .is_some_and(|suffix| suffix.is_empty() || suffix.starts_with(['+', ';']))
+        })
+}
+
+///
+/// 转发失败时返回只包含状态的gRPC响应
+///
+pub(crate) fn failure(failure: &Failure) -> Response<SatexBody> {
+    debug!("proxy grpc request error: {}", failure);
+    let mut response = Response::new(SatexBody::empty());
+    trailers_only(response.headers_mut(), Code::from_failure(failure), failure);
+    response
+}
+
+///
+/// 上游返回非`200`且没有`grpc-status`的响应时转换为只包含状态的gRPC响应,
+/// 否则在响应体出错或者结束时没有trailers时通过trailers返回状态, 避免直接中断响应流,
+/// 响应头中已经包含`grpc-status`时原样返回
+///
+pub(crate) fn response(response: Response<SatexBody>) -> Response<SatexBody> {
+    let (mut parts, body) = response.into_parts();
+    if parts.headers.contains_key(GRPC_STATUS) {
+        return Response::from_parts(parts, body);
+    }
+    if parts.status != StatusCode::OK {
+        let code = Code::from_status(parts.status);
+        let message = format!("upstream response status: {}", parts.status);
+        parts.status = StatusCode::OK;
+        parts.headers.remove(CONTENT_LENGTH);
+        trailers_only(&mut parts.headers, code, &message);
+        return Response::from_parts(parts, SatexBody::empty());
+    }
+    Response::from_parts(parts, SatexBody::new(GrpcBody::new(body)))
+}
+
+fn trailers_only(headers: &mut HeaderMap, code: Code, message: &dyn Display) {
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+    status(headers, code, message);
+}
+
+fn status(headers: &mut HeaderMap, code: Code, message: &dyn Display) {
+    headers.insert(GRPC_STATUS, HeaderValue::from(code as u16));
+    let message = utf8_percent_encode(&message.to_string(), GRPC_MESSAGE_ENCODE_SET).to_string();
+    if let Ok(message) = HeaderValue::from_str(&message) {
+        headers.insert(GRPC_MESSAGE, message);
+    }
+}
+
+pin_project! {
+    ///
+    /// 响应体出错或者结束时没有trailers时, 返回包含`grpc-status`的trailers并结束响应体
+    ///
+    struct GrpcBody<B> {
+        #[pin]
+        inner: B,
+        trailers: bool,
+        done: bool,
+    }
+}
+
+impl<B> GrpcBody<B> {
+    fn new(inner: B) -> Self {
+        Self {
+            inner,
+            trailers: false,
+            done: false,
+        }
+    }
+}
+
+impl<B> Body for GrpcBody<B>
+where
+    B: Body<Data = Bytes>,
+    B::Error: Into<BoxError>,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        if *this.done {
+            return Poll::Ready(None);
+        }
+        let (code, message) = match ready!(this.inner.poll_frame(cx)) {
+            Some(Ok(frame)) => {
+                *this.trailers |= frame.is_trailers();
+                return Poll::Ready(Some(Ok(frame)));
+            }
+            Some(Err(e)) => {
+                let e = e.into();
+                debug!("proxy grpc response body error: {}", e);
+                (Code::Unavailable, e.to_string())
+            }
+            None if *this.trailers => {
+                *this.done = true;
+                return Poll::Ready(None);
+            }
+            None => (
+                Code::Internal,
+                "upstream response ended without grpc-status".to_string(),
+            ),
+        };
+        *this.done = true;
+        let mut trailers = HeaderMap::new();
+        status(&mut trailers, code, &message);
+        Poll::Ready(Some(Ok(Frame::trailers(trailers))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.done
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        if self.done {
+            SizeHint::with_exact(0)
+        } else {
+            let mut hint = SizeHint::new();
+            hint.set_lower(self.inner.size_hint().lower());
+            hint
+        }
+    }
+}
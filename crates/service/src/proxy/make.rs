@@ -1,6 +1,9 @@
 use crate::make::MakeRouteService;
-use crate::proxy::client::{Client, ClientConfig};
+use crate::proxy::client::ClientConfig;
+use crate::proxy::retry::{Retry, RetryConfig};
 use crate::proxy::service::ProxyRouteService;
+use crate::proxy::subset::{Subset, SubsetConfig};
+use crate::proxy::upgrade::{Upgrade, UpgradeConfig};
 use http::Extensions;
 use satex_core::Error;
 use satex_core::component::{Args, Configurable};
@@ -13,11 +16,20 @@ use std::str::FromStr;
 use std::sync::Arc;
 use url::Url;
 
+/// 负载均衡的地址协议, 例如: `lb://backend/api`
+const LB_SCHEME: &str = "lb";
+
 #[make(kind = Proxy)]
 struct MakeProxyRouteService {
     uri: String,
     #[serde(default)]
     client: ClientConfig,
+    #[serde(default)]
+    retry: Option<RetryConfig>,
+    #[serde(default)]
+    subset: Option<SubsetConfig>,
+    #[serde(default)]
+    upgrade: UpgradeConfig,
 }
 
 impl MakeRouteService for MakeProxyRouteService {
@@ -25,27 +37,57 @@ impl MakeRouteService for MakeProxyRouteService {
 
     fn make(&self, args: Args, extensions: &Extensions) -> Result<Self::Service, Error> {
         Config::with_args(args).and_then(|config| {
-            Url::from_str(remove_end_sep(&config.uri))
-                .map_err(Error::new)
-                .map(|url| {
-                    let load_balancer = extensions
-                        .get::<ArcLoadBalancerResolver>()
-                        .and_then(|registry| get_load_balancer(registry, &url));
-
-                    ProxyRouteService::new(
-                        url,
-                        Client::from(config.client),
-                        DefaultDigester,
-                        load_balancer,
-                    )
-                })
+            let retry = config.retry.map(Retry::try_from).transpose()?;
+            let subset = config.subset.map(Subset::try_from).transpose()?;
+            let url = Url::from_str(remove_end_sep(&config.uri)).map_err(Error::new)?;
+            let resolver = extensions.get::<ArcLoadBalancerResolver>();
+            let (url, load_balancer) = if url.scheme() == LB_SCHEME {
+                let load_balancer = find_load_balancer(resolver, &url)?;
+                // 使用上游配置的协议替换`lb`
+                let url = format!(
+                    "{}{}",
+                    load_balancer.scheme(),
+                    &url.as_str()[LB_SCHEME.len()..]
+                );
+                (
+                    Url::from_str(&url).map_err(Error::new)?,
+                    Some(load_balancer),
+                )
+            } else {
+                let load_balancer =
+                    resolver.and_then(|resolver| resolver.find(url.host_str().unwrap_or_default()));
+                (url, load_balancer)
+            };
+            Ok(ProxyRouteService::new(
+                url,
+                config.client.client(load_balancer.clone())?,
+                DefaultDigester,
+                load_balancer,
+            )
+            .with_retry(retry)
+            .with_subset(subset)
+            .with_upgrade(Upgrade::from_config(config.upgrade)))
         })
     }
 }
 
-fn get_load_balancer(resolver: &ArcLoadBalancerResolver, url: &Url) -> Option<Arc<LoadBalancer>> {
-    if let Some(load_balancer) = resolver.find(url.host_str().unwrap_or_default()) {
-        return Some(load_balancer);
-    }
-    None
+///
+/// 查找`lb://`地址对应的负载均衡器, 上游不存在时返回错误
+///
+fn find_load_balancer(
+    resolver: Option<&ArcLoadBalancerResolver>,
+    url: &Url,
+) -> Result<Arc<LoadBalancer>, Error> {
+    let name = url
+        .host_str()
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| Error::new(format!("missing upstream name in `{}`", url)))?;
+    resolver
+        .and_then(|resolver| resolver.find(name))
+        .ok_or_else(|| {
+            Error::new(format!(
+                "unknown upstream `{}` in `{}`, please check the upstreams of the load balancer resolvers",
+                name, url
+            ))
+        })
 }
@@ -1,8 +1,19 @@
 #![doc = include_str!("../../docs/proxy.md")]
 
+mod body;
 mod client;
+mod grpc;
 mod make;
+mod retry;
 mod service;
+mod subset;
+mod tls;
+mod upgrade;
 
+pub use client::Protocol;
 pub use make::*;
+pub use retry::{BudgetConfig, RetryConfig, RetryOn};
 pub use service::*;
+pub use subset::{SubsetConfig, SubsetLabel};
+pub use tls::{TlsConfig, TlsVersion};
+pub use upgrade::UpgradeConfig;
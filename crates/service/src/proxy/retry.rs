@@ -0,0 +1,406 @@
+use bytes::{Bytes, BytesMut};
+use futures::{StreamExt, stream};
+use http::{HeaderMap, Method, StatusCode};
+use http_body::{Body as _, Frame};
+use http_body_util::{BodyExt, BodyStream, StreamBody};
+use hyper_util::client::legacy::Error as ClientError;
+use satex_core::Error;
+use satex_core::body::Body;
+use satex_load_balancer::circuit_breaker::Rejection;
+use serde::{Deserialize, Serialize};
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+use std::time::Duration;
+use tower::retry::budget::{Budget, TpsBudget};
+
+///
+/// 重试配置
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryConfig {
+    ///
+    /// 最多尝试的次数, 包含第一次请求
+    ///
+    #[serde(default = "RetryConfig::default_attempts")]
+    attempts: usize,
+
+    ///
+    /// 可以重试的失败类型
+    ///
+    #[serde(default = "RetryConfig::default_retry_on")]
+    retry_on: Vec<RetryOn>,
+
+    ///
+    /// 可以重试的响应状态码
+    ///
+    #[serde(default)]
+    statuses: Vec<u16>,
+
+    ///
+    /// 可以重试的请求方法, 默认只重试幂等的请求方法
+    ///
+    #[serde(default)]
+    methods: Option<Vec<String>>,
+
+    ///
+    /// 每次尝试等待响应头的超时时间(毫秒)
+    ///
+    #[serde(default)]
+    per_try_timeout_millis: Option<u64>,
+
+    ///
+    /// 为了重放而缓存的请求体的最大字节数, 超过时不会重试
+    ///
+    #[serde(default = "RetryConfig::default_max_body_size")]
+    max_body_size: usize,
+
+    ///
+    /// 重试预算
+    ///
+    #[serde(default)]
+    budget: BudgetConfig,
+}
+
+impl RetryConfig {
+    fn default_attempts() -> usize {
+        3
+    }
+
+    fn default_retry_on() -> Vec<RetryOn> {
+        vec![RetryOn::ConnectFailure, RetryOn::Reset]
+    }
+
+    fn default_max_body_size() -> usize {
+        64 * 1024
+    }
+}
+
+///
+/// 重试预算, 限制重试请求占全部请求的比例, 避免后端异常时重试放大流量
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetConfig {
+    ///
+    /// 统计请求的时间窗口(秒)
+    ///
+    #[serde(default = "BudgetConfig::default_ttl_secs")]
+    ttl_secs: u64,
+
+    ///
+    /// 每秒最少允许的重试次数
+    ///
+    #[serde(default = "BudgetConfig::default_min_retries_per_sec")]
+    min_retries_per_sec: u32,
+
+    ///
+    /// 在最少重试次数之外, 允许的重试占请求的比例
+    ///
+    #[serde(default = "BudgetConfig::default_retry_ratio")]
+    retry_ratio: f32,
+}
+
+impl BudgetConfig {
+    fn default_ttl_secs() -> u64 {
+        10
+    }
+
+    fn default_min_retries_per_sec() -> u32 {
+        10
+    }
+
+    fn default_retry_ratio() -> f32 {
+        0.2
+    }
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: Self::default_ttl_secs(),
+            min_retries_per_sec: Self::default_min_retries_per_sec(),
+            retry_ratio: Self::default_retry_ratio(),
+        }
+    }
+}
+
+///
+/// 可以重试的失败类型
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RetryOn {
+    ///
+    /// 连接后端失败
+    ///
+    ConnectFailure,
+
+    ///
+    /// 收到响应之前连接被重置或者关闭
+    ///
+    Reset,
+
+    ///
+    /// 单次尝试超时
+    ///
+    Timeout,
+}
+
+///
+/// 单次转发失败的原因
+///
+#[derive(Debug)]
+pub(crate) enum Failure {
+    Timeout,
+    Client(ClientError),
+    Other(Error),
+}
+
+impl Failure {
+    fn kind(&self) -> Option<RetryOn> {
+        match self {
+            Failure::Timeout => Some(RetryOn::Timeout),
+            Failure::Client(e) if e.is_connect() => Some(RetryOn::ConnectFailure),
+            Failure::Client(e) => is_reset(e).then_some(RetryOn::Reset),
+            Failure::Other(_) => None,
+        }
+    }
+
+    ///
+    /// 建立连接时被熔断器拒绝的原因
+    ///
+    pub(crate) fn rejection(&self) -> Option<Rejection> {
+        let Failure::Client(e) = self else {
+            return None;
+        };
+        let mut source = e.source();
+        while let Some(e) = source {
+            if let Some(rejection) = e.downcast_ref::<Rejection>() {
+                return Some(*rejection);
+            }
+            source = e.source();
+        }
+        None
+    }
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Failure::Timeout => f.write_str("upstream request timeout!"),
+            Failure::Client(e) => Display::fmt(e, f),
+            Failure::Other(e) => Display::fmt(e, f),
+        }
+    }
+}
+
+impl From<Error> for Failure {
+    fn from(e: Error) -> Self {
+        Failure::Other(e)
+    }
+}
+
+impl From<Failure> for Error {
+    fn from(failure: Failure) -> Self {
+        match failure {
+            Failure::Timeout => Error::new("upstream request timeout!"),
+            Failure::Client(e) => Error::new(e),
+            Failure::Other(e) => e,
+        }
+    }
+}
+
+///
+/// 遍历错误链, 判断是否是在收到响应之前连接被重置或者关闭
+///
+fn is_reset(e: &ClientError) -> bool {
+    let mut source = e.source();
+    while let Some(e) = source {
+        if let Some(e) = e.downcast_ref::<hyper::Error>()
+            && (e.is_incomplete_message() || e.is_canceled())
+        {
+            return true;
+        }
+        if let Some(e) = e.downcast_ref::<std::io::Error>()
+            && matches!(
+                e.kind(),
+                ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted | ErrorKind::BrokenPipe
+            )
+        {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
+
+///
+/// 重试策略
+///
+pub(crate) struct Retry {
+    attempts: usize,
+    retry_on: Vec<RetryOn>,
+    statuses: Vec<StatusCode>,
+    methods: Vec<Method>,
+    per_try_timeout: Option<Duration>,
+    max_body_size: usize,
+    budget: TpsBudget,
+}
+
+impl TryFrom<RetryConfig> for Retry {
+    type Error = Error;
+
+    fn try_from(config: RetryConfig) -> Result<Self, Self::Error> {
+        let statuses = config
+            .statuses
+            .into_iter()
+            .map(|status| StatusCode::from_u16(status).map_err(Error::new))
+            .collect::<Result<Vec<_>, _>>()?;
+        let methods = match config.methods {
+            Some(methods) => methods
+                .iter()
+                .map(|method| Method::from_bytes(method.to_uppercase().as_bytes()))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(Error::new)?,
+            None => vec![
+                Method::GET,
+                Method::HEAD,
+                Method::OPTIONS,
+                Method::TRACE,
+                Method::PUT,
+                Method::DELETE,
+            ],
+        };
+        let budget = TpsBudget::new(
+            Duration::from_secs(config.budget.ttl_secs.max(1)),
+            config.budget.min_retries_per_sec,
+            config.budget.retry_ratio,
+        );
+        Ok(Self {
+            attempts: config.attempts.max(1),
+            retry_on: config.retry_on,
+            statuses,
+            methods,
+            per_try_timeout: config.per_try_timeout_millis.map(Duration::from_millis),
+            max_body_size: config.max_body_size,
+            budget,
+        })
+    }
+}
+
+impl Retry {
+    pub(crate) fn per_try_timeout(&self) -> Option<Duration> {
+        self.per_try_timeout
+    }
+
+    pub(crate) fn allow_method(&self, method: &Method) -> bool {
+        self.attempts > 1 && self.methods.contains(method)
+    }
+
+    pub(crate) fn max_body_size(&self) -> usize {
+        self.max_body_size
+    }
+
+    ///
+    /// 记录一次请求, 用于计算重试预算
+    ///
+    pub(crate) fn deposit(&self) {
+        self.budget.deposit();
+    }
+
+    ///
+    /// 第`attempt`次尝试得到的状态码是否可以重试
+    ///
+    pub(crate) fn retry_status(&self, attempt: usize, status: StatusCode) -> bool {
+        attempt < self.attempts && self.statuses.contains(&status) && self.budget.withdraw()
+    }
+
+    ///
+    /// 第`attempt`次尝试的失败是否可以重试
+    ///
+    pub(crate) fn retry_failure(&self, attempt: usize, failure: &Failure) -> bool {
+        attempt < self.attempts
+            && failure
+                .kind()
+                .is_some_and(|kind| self.retry_on.contains(&kind))
+            && self.budget.withdraw()
+    }
+}
+
+///
+/// 可以重放的请求体
+///
+pub(crate) enum Replay {
+    Buffered {
+        data: Bytes,
+        trailers: Option<HeaderMap>,
+    },
+    Once(Body),
+}
+
+impl Replay {
+    ///
+    /// 缓存请求体, 超过`limit`时不再缓存, 已经读取的部分会在转发时重新发送
+    ///
+    pub(crate) async fn buffer(mut body: Body, limit: usize) -> Result<Self, Error> {
+        if body.size_hint().lower() > limit as u64 {
+            return Ok(Replay::Once(body));
+        }
+        let mut data = BytesMut::new();
+        while let Some(frame) = body.frame().await {
+            let frame = frame?;
+            if let Some(chunk) = frame.data_ref() {
+                data.extend_from_slice(chunk);
+                if data.len() > limit {
+                    let buffered = stream::iter([Ok(Frame::data(data.freeze()))]);
+                    let body = buffered.chain(BodyStream::new(body));
+                    return Ok(Replay::Once(Body::new(StreamBody::new(body))));
+                }
+            } else if let Ok(trailers) = frame.into_trailers() {
+                return Ok(Replay::Buffered {
+                    data: data.freeze(),
+                    trailers: Some(trailers),
+                });
+            }
+        }
+        Ok(Replay::Buffered {
+            data: data.freeze(),
+            trailers: None,
+        })
+    }
+
+    ///
+    /// 是否可以重放
+    ///
+    pub(crate) fn replayable(&self) -> bool {
+        matches!(self, Replay::Buffered { .. })
+    }
+
+    ///
+    /// 获取一次转发使用的请求体
+    ///
+    pub(crate) fn body(&mut self) -> Body {
+        match self {
+            Replay::Buffered {
+                data,
+                trailers: None,
+            } => {
+                if data.is_empty() {
+                    Body::empty()
+                } else {
+                    Body::from(data.clone())
+                }
+            }
+            Replay::Buffered {
+                data,
+                trailers: Some(trailers),
+            } => {
+                let frames = [
+                    Ok::<_, Error>(Frame::data(data.clone())),
+                    Ok(Frame::trailers(trailers.clone())),
+                ];
+                Body::new(StreamBody::new(stream::iter(frames)))
+            }
+            Replay::Once(body) => std::mem::take(body),
+        }
+    }
+}
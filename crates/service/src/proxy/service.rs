@@ -1,18 +1,32 @@
+use crate::proxy::body::InflightBody;
 use crate::proxy::client::Client;
+use crate::proxy::grpc;
+use crate::proxy::retry::{Failure, Replay, Retry};
+use crate::proxy::subset::Subset;
+use crate::proxy::upgrade::{Tunnel, Upgrade, UpgradeConfig};
 use futures::future::LocalBoxFuture;
-use http::{HeaderName, Request, Response, Uri};
+use http::header::{SET_COOKIE, TE};
+use http::{HeaderMap, HeaderName, HeaderValue, Request, Response, StatusCode, Uri};
 use satex_core::Error;
 use satex_core::body::Body;
 use satex_core::digest::Digester;
-use satex_load_balancer::LoadBalancer;
-use std::future::ready;
+use satex_core::extension::{UpstreamAddr, UpstreamLatency};
+use satex_core::metrics::{UPSTREAM_ERRORS_TOTAL, UPSTREAM_REQUEST_DURATION_SECONDS};
+use satex_core::propagation::inject;
+use satex_load_balancer::circuit_breaker::Rejection;
+use satex_load_balancer::{Backend, Inflight, LoadBalancer};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 use tower::Service;
-use tracing::debug;
+use tracing::field::Empty;
+use tracing::{Instrument, Span, debug, info_span};
 use url::Url;
 
+/// 请求被熔断器拒绝时, 说明拒绝原因的响应头
+pub const X_SATEX_CIRCUIT_BREAKER: HeaderName = HeaderName::from_static("x-satex-circuit-breaker");
+
 const REMOVE_HEADERS: [HeaderName; 9] = [
     HeaderName::from_static("connection"),
     HeaderName::from_static("keep-alive"),
@@ -27,10 +41,11 @@ const REMOVE_HEADERS: [HeaderName; 9] = [
 
 #[derive(Clone)]
 pub struct ProxyRouteService<D> {
-    url: Url,
-    client: Client,
+    upstream: Upstream,
     digester: Arc<D>,
-    load_balancer: Option<Arc<LoadBalancer>>,
+    retry: Option<Arc<Retry>>,
+    subset: Option<Arc<Subset>>,
+    upgrade: Option<Upgrade>,
 }
 
 impl<D> ProxyRouteService<D> {
@@ -41,12 +56,32 @@ impl<D> ProxyRouteService<D> {
         load_balancer: Option<Arc<LoadBalancer>>,
     ) -> Self {
         Self {
-            url,
-            client,
+            upstream: Upstream {
+                url,
+                client,
+                load_balancer,
+            },
             digester: Arc::new(digester),
-            load_balancer,
+            retry: None,
+            subset: None,
+            upgrade: Upgrade::from_config(UpgradeConfig::default()),
         }
     }
+
+    pub(crate) fn with_retry(mut self, retry: Option<Retry>) -> Self {
+        self.retry = retry.map(Arc::new);
+        self
+    }
+
+    pub(crate) fn with_subset(mut self, subset: Option<Subset>) -> Self {
+        self.subset = subset.map(Arc::new);
+        self
+    }
+
+    pub(crate) fn with_upgrade(mut self, upgrade: Option<Upgrade>) -> Self {
+        self.upgrade = upgrade;
+        self
+    }
 }
 
 impl<D> Service<Request<Body>> for ProxyRouteService<D>
@@ -58,47 +93,385 @@ where
     type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        self.client.poll_ready(cx).map_err(Error::new)
+        self.upstream.client.poll_ready(cx).map_err(Error::new)
     }
 
     fn call(&mut self, mut request: Request<Body>) -> Self::Future {
-        let addr = self.load_balancer.as_deref().and_then(|load_balancer| {
-            let key = self.digester.digest(&request);
-            load_balancer.select(&key).map(|backend| backend.addr)
-        });
+        let upstream = self.upstream.clone();
+        let tunnel = self
+            .upgrade
+            .and_then(|upgrade| upgrade.tunnel(&mut request));
+        let retry = self
+            .retry
+            .clone()
+            .filter(|retry| retry.allow_method(request.method()));
+        let selection = Selection {
+            key: self.digester.digest(&request).into_owned(),
+            pinned: upstream.pinned(request.headers()),
+            subset: self
+                .subset
+                .as_ref()
+                .map(|subset| subset.labels(&request))
+                .unwrap_or_default(),
+            fallback: self.subset.as_ref().is_none_or(|subset| subset.fallback()),
+        };
+        let grpc = grpc::is_grpc(request.headers());
+        Box::pin(async move {
+            let result = upstream.proxy(request, selection, retry, tunnel).await;
+            // gRPC请求的转发失败以及上游错误转换为gRPC状态
+            match result {
+                Ok(response) if grpc => Ok(grpc::response(response)),
+                Err(failure) if grpc => Ok(grpc::failure(&failure)),
+                result => result.map_err(Error::from),
+            }
+        })
+    }
+}
 
-        // 重新构造请求的uri
-        let uri = request.uri();
-        let path = uri.path();
-        let query = uri.query();
-        match reconstruct(self.url.clone(), addr, path, query) {
-            Ok(uri) => {
-                *request.uri_mut() = uri;
+///
+/// 一次请求选择后端的条件
+///
+struct Selection {
+    /// 基于哈希的选择算法使用的键
+    key: Vec<u8>,
+    /// 会话保持Cookie记录的后端
+    pinned: Option<Backend>,
+    /// 需要匹配的后端标签
+    subset: Vec<(String, String)>,
+    /// 没有标签匹配的后端时是否在所有后端中选择
+    fallback: bool,
+}
+
+///
+/// 转发的目标上游
+///
+#[derive(Clone)]
+struct Upstream {
+    url: Url,
+    client: Client,
+    load_balancer: Option<Arc<LoadBalancer>>,
+}
+
+impl Upstream {
+    ///
+    /// 选择后端并转发请求, 设置了重试时按照重试配置重新选择后端并重放请求
+    ///
+    async fn proxy(
+        &self,
+        request: Request<Body>,
+        selection: Selection,
+        retry: Option<Arc<Retry>>,
+        tunnel: Option<Tunnel>,
+    ) -> Result<Response<Body>, Failure> {
+        // 协议升级的请求不会重试
+        let Some(retry) = retry.filter(|_| tunnel.is_none()) else {
+            let backend = self.select(&selection, &[]);
+            let (mut response, backend) = self.forward(request, backend, None, tunnel).await?;
+            self.stick(&mut response, backend.as_ref(), &selection);
+            return Ok(response);
+        };
+
+        // 缓存请求体以便重放, 请求体过大时只转发一次
+        let (parts, body) = request.into_parts();
+        let mut replay = Replay::buffer(body, retry.max_body_size()).await?;
+        retry.deposit();
+
+        // 重试时跳过已经尝试过的后端, 所有后端都尝试过时返回最后一次转发的结果
+        let mut tried = Vec::new();
+        let mut attempt = 0;
+        let mut last = None;
+        let result = loop {
+            let backend = self.select(&selection, &tried);
+            if backend.is_none()
+                && self.load_balancer.is_some()
+                && let Some(result) = last.take()
+            {
+                debug!("proxy retry stopped, no untried backend left");
+                break result;
             }
-            Err(e) => {
-                return Box::pin(ready(Err(Error::new(e))));
+            attempt += 1;
+            tried.extend(backend.clone());
+            let request = Request::from_parts(parts.clone(), replay.body());
+            let result = self
+                .forward(request, backend, retry.per_try_timeout(), None)
+                .await;
+            let retryable = replay.replayable()
+                && match &result {
+                    Ok((response, _)) => retry.retry_status(attempt, response.status()),
+                    Err(failure) => retry.retry_failure(attempt, failure),
+                };
+            if !retryable {
+                break result;
             }
+            debug!("proxy retry request, attempt: {}", attempt + 1);
+            last = Some(result);
+        };
+        let (mut response, backend) = result?;
+        self.stick(&mut response, backend.as_ref(), &selection);
+        Ok(response)
+    }
+
+    ///
+    /// 会话保持Cookie记录的可以接收流量的后端
+    ///
+    fn pinned(&self, headers: &HeaderMap) -> Option<Backend> {
+        self.load_balancer
+            .as_ref()
+            .and_then(|load_balancer| load_balancer.select_sticky(headers))
+    }
+
+    ///
+    /// 选择一个没有尝试过的后端, 优先使用会话保持的后端, 设置了子集时只选择标签匹配的后端,
+    /// 设置了熔断器时优先选择熔断器允许的后端
+    ///
+    fn select(&self, selection: &Selection, tried: &[Backend]) -> Option<Backend> {
+        let load_balancer = self.load_balancer.as_ref()?;
+        let accept = |backend: &Backend, health: bool| health && !tried.contains(backend);
+        match load_balancer.circuit_breaker() {
+            Some(circuit_breaker) => select_by(load_balancer, selection, |backend, health| {
+                accept(backend, health) && circuit_breaker.check(backend).is_ok()
+            })
+            // 所有后端都会被熔断器拒绝时, 由熔断器快速拒绝请求
+            .or_else(|| select_by(load_balancer, selection, accept)),
+            None => select_by(load_balancer, selection, accept),
+        }
+    }
+
+    ///
+    /// 转发一次请求到选中的后端, 返回响应和实际转发到的后端,
+    /// 选中的后端会被跟踪到响应体结束, 被熔断器拒绝时直接返回`503`, 没有可以选择的后端时返回错误
+    ///
+    async fn forward(
+        &self,
+        request: Request<Body>,
+        backend: Option<Backend>,
+        timeout: Option<Duration>,
+        tunnel: Option<Tunnel>,
+    ) -> Result<(Response<Body>, Option<Backend>), Failure> {
+        let inflight = match (self.load_balancer.as_ref(), backend) {
+            (Some(load_balancer), Some(backend)) => match load_balancer.acquire(&backend).await {
+                Ok(inflight) => Some(inflight),
+                Err(rejection) => {
+                    debug!(
+                        "proxy request to {} is rejected by circuit breaker: {}",
+                        backend.addr, rejection
+                    );
+                    return Ok((rejected(rejection), None));
+                }
+            },
+            (Some(load_balancer), None) => {
+                return Err(Failure::Other(Error::new(format!(
+                    "no available backend in upstream `{}`!",
+                    load_balancer.name()
+                ))));
+            }
+            (None, _) => None,
+        };
+        let backend = inflight.as_ref().map(|inflight| inflight.backend().clone());
+        let response = self.send(request, inflight, timeout, tunnel).await?;
+        Ok((response, backend))
+    }
+
+    ///
+    /// 转发到的后端和会话保持Cookie记录的后端不同时, 设置新的会话保持Cookie
+    ///
+    fn stick(
+        &self,
+        response: &mut Response<Body>,
+        backend: Option<&Backend>,
+        selection: &Selection,
+    ) {
+        let Some(sticky_session) = self
+            .load_balancer
+            .as_deref()
+            .and_then(|load_balancer| load_balancer.sticky_session())
+        else {
+            return;
+        };
+        if let Some(backend) = backend
+            && selection
+                .pinned
+                .as_ref()
+                .is_none_or(|pinned| pinned.addr != backend.addr)
+            && let Ok(value) = HeaderValue::from_str(&sticky_session.set_cookie(backend))
+        {
+            response.headers_mut().append(SET_COOKIE, value);
         }
+    }
 
-        // 删除不应该转到后端的请求头
+    ///
+    /// 转发一次请求, `timeout`限制等待响应头的时间, 后端同意协议升级时转发升级后的连接
+    ///
+    async fn send(
+        &self,
+        mut request: Request<Body>,
+        inflight: Option<Inflight>,
+        timeout: Option<Duration>,
+        tunnel: Option<Tunnel>,
+    ) -> Result<Response<Body>, Failure> {
+        let addr = inflight.as_ref().map(|inflight| inflight.backend().addr);
+
+        // 上游指标标签, 使用负载均衡器名称和选中的后端地址, 未使用负载均衡时使用配置的地址
+        let upstream = match self.load_balancer.as_deref() {
+            Some(load_balancer) => load_balancer.name(),
+            None => self.url.host_str().unwrap_or_default(),
+        };
+        let backend = match addr {
+            Some(addr) => addr.to_string(),
+            None => format!(
+                "{}:{}",
+                self.url.host_str().unwrap_or_default(),
+                self.url.port_or_known_default().unwrap_or_default()
+            ),
+        };
+        let labels = [upstream, backend.as_str()];
+        let duration = UPSTREAM_REQUEST_DURATION_SECONDS.with_label_values(&labels);
+        let errors = UPSTREAM_ERRORS_TOTAL.with_label_values(&labels);
+
+        // 重新构造请求的uri
+        let uri = request.uri();
+        let uri = reconstruct(self.url.clone(), addr, uri.path(), uri.query())?;
+        *request.uri_mut() = uri;
+
+        // 删除不应该转到后端的请求头, gRPC等依赖trailers的请求保留`TE: trailers`
         let headers = request.headers_mut();
+        let trailers = accept_trailers(headers);
         REMOVE_HEADERS.iter().for_each(|header| {
             headers.remove(header);
         });
+        if trailers {
+            headers.insert(TE, HeaderValue::from_static("trailers"));
+        }
+        if let Some(tunnel) = &tunnel {
+            tunnel.headers(headers);
+        }
+
+        // 上游span, 并将追踪上下文注入到转发的请求头中
+        let span = info_span!(
+            "upstream",
+            otel.name = upstream,
+            otel.kind = "client",
+            upstream = upstream,
+            backend = backend.as_str(),
+            http.response.status_code = Empty,
+            error = Empty,
+        );
+        inject(&span, request.headers_mut());
 
         debug!("proxy send request:\n{:#?}", request);
 
         // 发送请求到后端
         let future = self.client.request(request);
-        Box::pin(async move {
-            future
-                .await
-                .map_err(Error::new)
-                .map(|response| response.map(Body::new))
-        })
+        let future = async move {
+            let start = Instant::now();
+            let result = match timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, future).await {
+                    Ok(result) => result.map_err(Failure::Client),
+                    Err(_) => Err(Failure::Timeout),
+                },
+                None => future.await.map_err(Failure::Client),
+            };
+            let elapsed = start.elapsed();
+            duration.observe(elapsed.as_secs_f64());
+            let span = Span::current();
+            match result {
+                Ok(mut response) => {
+                    span.record("http.response.status_code", response.status().as_u16());
+                    if let Some(inflight) = &inflight {
+                        inflight.observe_latency(elapsed);
+                        inflight.report(!response.status().is_server_error()).await;
+                    }
+                    // 记录选中的后端地址和上游延迟, 供访问日志等使用
+                    let extensions = response.extensions_mut();
+                    extensions.insert(UpstreamAddr::new(backend));
+                    extensions.insert(UpstreamLatency::new(elapsed));
+                    if response.status() == StatusCode::SWITCHING_PROTOCOLS
+                        && let Some(tunnel) = tunnel
+                    {
+                        tunnel.splice(hyper::upgrade::on(&mut response), inflight);
+                        return Ok(response.map(|_| Body::empty()));
+                    }
+                    Ok(response.map(|body| Body::new(InflightBody::new(body, inflight))))
+                }
+                Err(e) => {
+                    // 连接数达到上限时由熔断器快速拒绝请求, 不记为后端的失败
+                    if let Some(rejection) = e.rejection() {
+                        debug!(
+                            "proxy connection to {} is rejected by circuit breaker: {}",
+                            backend, rejection
+                        );
+                        return Ok(rejected(rejection));
+                    }
+                    errors.inc();
+                    span.record("error", e.to_string());
+                    // 连接错误以及超时等传输错误
+                    if let Some(inflight) = &inflight {
+                        inflight.report(false).await;
+                    }
+                    Err(e)
+                }
+            }
+        };
+        future.instrument(span).await
     }
 }
 
+///
+/// 按照会话保持、子集以及`accept`选择后端
+///
+fn select_by<F>(load_balancer: &LoadBalancer, selection: &Selection, accept: F) -> Option<Backend>
+where
+    F: Fn(&Backend, bool) -> bool,
+{
+    let subset = selection.subset.as_slice();
+    selection
+        .pinned
+        .as_ref()
+        .filter(|backend| accept(backend, true) && backend.matches(subset))
+        .cloned()
+        .or_else(|| {
+            load_balancer.select_with(&selection.key, |backend, health| {
+                accept(backend, health) && backend.matches(subset)
+            })
+        })
+        .or_else(|| {
+            // 没有标签匹配的后端时在所有后端中选择
+            (selection.fallback && !subset.is_empty())
+                .then(|| load_balancer.select_with(&selection.key, &accept))
+                .flatten()
+        })
+}
+
+///
+/// 客户端是否通过`TE`请求头声明接受trailers
+///
+fn accept_trailers(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(TE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|value| {
+            value
+                .split(';')
+                .next()
+                .is_some_and(|token| token.trim().eq_ignore_ascii_case("trailers"))
+        })
+}
+
+///
+/// 被熔断器拒绝的响应, 通过响应头说明拒绝的原因
+///
+fn rejected(rejection: Rejection) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+    response.headers_mut().insert(
+        X_SATEX_CIRCUIT_BREAKER,
+        HeaderValue::from_static(rejection.as_str()),
+    );
+    response
+}
+
 fn reconstruct(
     mut url: Url,
     addr: Option<SocketAddr>,
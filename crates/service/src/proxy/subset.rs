@@ -0,0 +1,134 @@
+use http::{HeaderName, Request};
+use satex_core::Error;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use url::form_urlencoded;
+
+///
+/// 子集负载均衡配置, 只在标签匹配的后端中选择
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubsetConfig {
+    ///
+    /// 需要匹配的标签
+    ///
+    #[serde(default)]
+    labels: Vec<SubsetLabel>,
+
+    ///
+    /// 没有标签匹配的后端时是否在所有后端中选择
+    ///
+    #[serde(default = "SubsetConfig::default_fallback")]
+    fallback: bool,
+}
+
+impl SubsetConfig {
+    fn default_fallback() -> bool {
+        true
+    }
+}
+
+///
+/// 需要匹配的标签, 标签的值可以是固定值, 也可以来自请求头或者URL参数, `value`、`header`、`query`只能设置一个
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubsetLabel {
+    ///
+    /// 标签名称
+    ///
+    name: String,
+
+    ///
+    /// 固定的标签值
+    ///
+    #[serde(default)]
+    value: Option<String>,
+
+    ///
+    /// 从请求头中获取标签值, 请求头不存在时不匹配该标签
+    ///
+    #[serde(default)]
+    header: Option<String>,
+
+    ///
+    /// 从URL参数中获取标签值, 参数不存在时不匹配该标签
+    ///
+    #[serde(default)]
+    query: Option<String>,
+}
+
+enum Source {
+    Value(String),
+    Header(HeaderName),
+    Query(String),
+}
+
+///
+/// 子集负载均衡
+///
+pub(crate) struct Subset {
+    labels: Vec<(String, Source)>,
+    fallback: bool,
+}
+
+impl TryFrom<SubsetConfig> for Subset {
+    type Error = Error;
+
+    fn try_from(config: SubsetConfig) -> Result<Self, Self::Error> {
+        let labels = config
+            .labels
+            .into_iter()
+            .map(|label| {
+                let source = match (label.value, label.header, label.query) {
+                    (Some(value), None, None) => Source::Value(value),
+                    (None, Some(header), None) => {
+                        Source::Header(HeaderName::from_str(&header).map_err(Error::new)?)
+                    }
+                    (None, None, Some(query)) => Source::Query(query),
+                    _ => {
+                        return Err(Error::new(format!(
+                            "subset label `{}` must set exactly one of `value`, `header` or `query`",
+                            label.name
+                        )));
+                    }
+                };
+                Ok((label.name, source))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            labels,
+            fallback: config.fallback,
+        })
+    }
+}
+
+impl Subset {
+    ///
+    /// 根据请求计算需要匹配的标签
+    ///
+    pub(crate) fn labels<B>(&self, request: &Request<B>) -> Vec<(String, String)> {
+        self.labels
+            .iter()
+            .filter_map(|(name, source)| {
+                let value = match source {
+                    Source::Value(value) => Some(value.clone()),
+                    Source::Header(header) => request
+                        .headers()
+                        .get(header)
+                        .and_then(|value| value.to_str().ok())
+                        .map(String::from),
+                    Source::Query(query) => request.uri().query().and_then(|params| {
+                        form_urlencoded::parse(params.as_bytes())
+                            .find(|(key, _)| key == query)
+                            .map(|(_, value)| value.into_owned())
+                    }),
+                };
+                value.map(|value| (name.clone(), value))
+            })
+            .collect()
+    }
+
+    pub(crate) fn fallback(&self) -> bool {
+        self.fallback
+    }
+}
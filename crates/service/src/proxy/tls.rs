@@ -0,0 +1,245 @@
+use crate::proxy::client::Protocol;
+use hyper_rustls::{FixedServerNameResolver, HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::client::legacy::connect::HttpConnector;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{CryptoProvider, verify_tls12_signature, verify_tls13_signature};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{
+    ClientConfig as ClientTlsConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+    SupportedProtocolVersion,
+};
+use satex_core::Error;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+///
+/// 反向代理连接后端时的TLS配置
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TlsConfig {
+    ///
+    /// CA证书文件(PEM格式), 用于验证后端的证书
+    ///
+    #[serde(default)]
+    ca_certs: Option<String>,
+
+    ///
+    /// 是否信任系统的根证书, 默认信任
+    ///
+    #[serde(default)]
+    native_roots: Option<bool>,
+
+    ///
+    /// 双向认证时发送给后端的客户端证书文件(PEM格式)
+    ///
+    #[serde(default)]
+    client_certs: Option<String>,
+
+    ///
+    /// 客户端证书的私钥文件(PEM格式)
+    ///
+    #[serde(default)]
+    client_key: Option<String>,
+
+    ///
+    /// 发送的SNI以及验证证书使用的服务器名称, 默认使用请求地址的主机名,
+    /// 负载均衡选中的后端为IP地址时需要设置
+    ///
+    #[serde(default)]
+    server_name: Option<String>,
+
+    ///
+    /// 最低的TLS版本
+    ///
+    #[serde(default)]
+    min_version: Option<TlsVersion>,
+
+    ///
+    /// 跳过后端证书的验证, 只能用于测试环境
+    ///
+    #[serde(default)]
+    insecure_skip_verify: bool,
+}
+
+///
+/// TLS版本
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TlsVersion {
+    Tls12,
+    Tls13,
+}
+
+static TLS13_VERSIONS: &[&SupportedProtocolVersion] = &[&rustls::version::TLS13];
+
+impl TlsVersion {
+    fn versions(&self) -> &'static [&'static SupportedProtocolVersion] {
+        match self {
+            TlsVersion::Tls12 => rustls::DEFAULT_VERSIONS,
+            TlsVersion::Tls13 => TLS13_VERSIONS,
+        }
+    }
+}
+
+impl TlsConfig {
+    ///
+    /// 创建支持HTTPS的连接器
+    ///
+    pub(crate) fn connector(
+        &self,
+        connector: HttpConnector,
+        protocol: Protocol,
+    ) -> Result<HttpsConnector<HttpConnector>, Error> {
+        let builder = HttpsConnectorBuilder::new()
+            .with_tls_config(self.client_config()?)
+            .https_or_http();
+        let builder = match self.server_name.as_deref() {
+            Some(server_name) => {
+                let server_name = ServerName::try_from(server_name.to_string()).map_err(|e| {
+                    Error::new(format!(
+                        "TLS server name `{}` is invalid: {}",
+                        server_name, e
+                    ))
+                })?;
+                builder.with_server_name_resolver(FixedServerNameResolver::new(server_name))
+            }
+            None => builder,
+        };
+        // 通过ALPN协商的协议
+        Ok(match protocol {
+            Protocol::Auto => builder.enable_all_versions().wrap_connector(connector),
+            Protocol::Http1 => builder.enable_http1().wrap_connector(connector),
+            Protocol::Http2 => builder.enable_http2().wrap_connector(connector),
+        })
+    }
+
+    fn client_config(&self) -> Result<ClientTlsConfig, Error> {
+        let builder = ClientTlsConfig::builder_with_protocol_versions(
+            self.min_version.unwrap_or(TlsVersion::Tls12).versions(),
+        );
+        let builder = if self.insecure_skip_verify {
+            warn!(
+                "TLS certificate verification of the proxy upstream is disabled, DO NOT use `insecure_skip_verify` in production!"
+            );
+            let verifier = InsecureVerifier(builder.crypto_provider().clone());
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(verifier))
+        } else {
+            builder.with_root_certificates(self.root_cert_store()?)
+        };
+        match (self.client_certs.as_deref(), self.client_key.as_deref()) {
+            (Some(certs), Some(key)) => builder
+                .with_client_auth_cert(load_certs(certs)?, load_private_key(key)?)
+                .map_err(Error::new),
+            (None, None) => Ok(builder.with_no_client_auth()),
+            _ => Err(Error::new(
+                "TLS client auth requires both `client_certs` and `client_key`!",
+            )),
+        }
+    }
+
+    fn root_cert_store(&self) -> Result<RootCertStore, Error> {
+        let mut store = RootCertStore::empty();
+        if self.native_roots.unwrap_or(true) {
+            let native = rustls_native_certs::load_native_certs();
+            for error in native.errors {
+                debug!("Load native root certs error: {}", error);
+            }
+            store.add_parsable_certificates(native.certs);
+        }
+        if let Some(ca_certs) = self.ca_certs.as_deref() {
+            for cert in load_certs(ca_certs)? {
+                store.add(cert).map_err(Error::new)?;
+            }
+        }
+        if store.is_empty() {
+            return Err(Error::new(
+                "TLS root certs is empty, please check `ca_certs` or the native root certs!",
+            ));
+        }
+        Ok(store)
+    }
+}
+
+///
+/// 加载PEM格式的证书文件
+///
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, Error> {
+    let file = File::open(path)
+        .map_err(|e| Error::new(format!("Load TLS certs `{}` error: {}", path, e)))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(Error::new)?;
+    if certs.is_empty() {
+        return Err(Error::new(format!("TLS certs is empty: {}", path)));
+    }
+    Ok(certs)
+}
+
+///
+/// 加载PEM格式的私钥文件
+///
+fn load_private_key(path: &str) -> Result<PrivateKeyDer<'static>, Error> {
+    let file = File::open(path)
+        .map_err(|e| Error::new(format!("Load TLS private key `{}` error: {}", path, e)))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .map_err(Error::new)
+        .and_then(|key| {
+            key.ok_or_else(|| Error::new(format!("TLS private key is invalid: {}", path)))
+        })
+}
+
+///
+/// 不验证后端证书, 只验证握手的签名
+///
+#[derive(Debug)]
+struct InsecureVerifier(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for InsecureVerifier {
+    fn verify_server_cert(
+        &self,
+        _: &CertificateDer<'_>,
+        _: &[CertificateDer<'_>],
+        _: &ServerName<'_>,
+        _: &[u8],
+        _: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
@@ -0,0 +1,211 @@
+use futures::future::{Either, select};
+use http::header::{CONNECTION, UPGRADE};
+use http::{HeaderMap, HeaderValue, Request, Version};
+use hyper::upgrade::OnUpgrade;
+use hyper_util::rt::TokioIo;
+use pin_project_lite::pin_project;
+use satex_core::body::Body;
+use satex_load_balancer::Inflight;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::io;
+use std::pin::{Pin, pin};
+use std::rc::Rc;
+use std::task::{Context, Poll, ready};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf, copy_bidirectional};
+use tokio::task::spawn_local;
+use tokio::time::{Instant, sleep_until};
+use tracing::debug;
+
+///
+/// 协议升级配置, 转发`WebSocket`、`h2c`等`Connection: upgrade`请求
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradeConfig {
+    ///
+    /// 是否转发协议升级请求
+    ///
+    #[serde(default = "UpgradeConfig::default_enabled")]
+    enabled: bool,
+
+    ///
+    /// 升级后的连接在两个方向上都没有数据时关闭连接的超时时间(秒), `0`表示不限制
+    ///
+    #[serde(default = "UpgradeConfig::default_idle_timeout_secs")]
+    idle_timeout_secs: u64,
+}
+
+impl UpgradeConfig {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_idle_timeout_secs() -> u64 {
+        300
+    }
+}
+
+impl Default for UpgradeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            idle_timeout_secs: Self::default_idle_timeout_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Upgrade {
+    idle_timeout: Option<Duration>,
+}
+
+impl Upgrade {
+    pub(crate) fn from_config(config: UpgradeConfig) -> Option<Self> {
+        config.enabled.then(|| Self {
+            idle_timeout: (config.idle_timeout_secs > 0)
+                .then(|| Duration::from_secs(config.idle_timeout_secs)),
+        })
+    }
+
+    ///
+    /// 客户端请求协议升级时, 取出客户端连接的升级, 只支持HTTP/1.1的协议升级
+    ///
+    pub(crate) fn tunnel(&self, request: &mut Request<Body>) -> Option<Tunnel> {
+        if request.version() != Version::HTTP_11 {
+            return None;
+        }
+        let headers = request.headers();
+        let upgrade = headers
+            .get_all(CONNECTION)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|token| token.trim().eq_ignore_ascii_case("upgrade"));
+        let protocol = headers.get(UPGRADE).filter(|_| upgrade)?.clone();
+        Some(Tunnel {
+            protocol,
+            client: hyper::upgrade::on(request),
+            idle_timeout: self.idle_timeout,
+        })
+    }
+}
+
+///
+/// 协议升级的请求, 后端返回`101`后在客户端和后端之间双向转发数据
+///
+pub(crate) struct Tunnel {
+    protocol: HeaderValue,
+    client: OnUpgrade,
+    idle_timeout: Option<Duration>,
+}
+
+impl Tunnel {
+    ///
+    /// 恢复被删除的协议升级请求头
+    ///
+    pub(crate) fn headers(&self, headers: &mut HeaderMap) {
+        headers.insert(CONNECTION, HeaderValue::from_static("upgrade"));
+        headers.insert(UPGRADE, self.protocol.clone());
+    }
+
+    ///
+    /// 后台转发升级后的连接, 连接关闭前一直跟踪选中的后端
+    ///
+    pub(crate) fn splice(self, upstream: OnUpgrade, inflight: Option<Inflight>) {
+        spawn_local(async move {
+            let _inflight = inflight;
+            let (client, upstream) = match futures::try_join!(self.client, upstream) {
+                Ok(upgraded) => upgraded,
+                Err(e) => {
+                    debug!("proxy upgrade error: {}", e);
+                    return;
+                }
+            };
+            let active = Rc::new(Cell::new(Instant::now()));
+            let mut client = Active::new(TokioIo::new(client), active.clone());
+            let mut upstream = Active::new(TokioIo::new(upstream), active.clone());
+            let copy = pin!(copy_bidirectional(&mut client, &mut upstream));
+            let result = match self.idle_timeout {
+                Some(idle_timeout) => match select(copy, pin!(idle(&active, idle_timeout))).await {
+                    Either::Left((result, _)) => result,
+                    Either::Right(_) => {
+                        Err(io::Error::new(io::ErrorKind::TimedOut, "idle timeout"))
+                    }
+                },
+                None => copy.await,
+            };
+            match result {
+                Ok((sent, received)) => debug!(
+                    "proxy upgraded connection closed, sent: {}, received: {}",
+                    sent, received
+                ),
+                Err(e) => debug!("proxy upgraded connection closed: {}", e),
+            }
+        });
+    }
+}
+
+///
+/// 等待连接空闲超过`timeout`
+///
+async fn idle(active: &Cell<Instant>, timeout: Duration) {
+    loop {
+        let deadline = active.get() + timeout;
+        if Instant::now() >= deadline {
+            return;
+        }
+        sleep_until(deadline).await;
+    }
+}
+
+pin_project! {
+    ///
+    /// 读取到数据时记录连接的活跃时间
+    ///
+    struct Active<T> {
+        #[pin]
+        inner: T,
+        active: Rc<Cell<Instant>>,
+    }
+}
+
+impl<T> Active<T> {
+    fn new(inner: T, active: Rc<Cell<Instant>>) -> Self {
+        Self { inner, active }
+    }
+}
+
+impl<T: AsyncRead> AsyncRead for Active<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.project();
+        let filled = buf.filled().len();
+        ready!(this.inner.poll_read(cx, buf))?;
+        if buf.filled().len() > filled {
+            this.active.set(Instant::now());
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncWrite> AsyncWrite for Active<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.project().inner.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_shutdown(cx)
+    }
+}
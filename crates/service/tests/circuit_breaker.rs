@@ -0,0 +1,186 @@
+use http::{Extensions, Request, Response, StatusCode};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use hyper_util::server::conn::auto::Builder;
+use satex_core::body::Body;
+use satex_core::component::Args;
+use satex_core::digest::DefaultDigester;
+use satex_core::executor::SpawnLocalExecutor;
+use satex_load_balancer::circuit_breaker::CircuitBreaker;
+use satex_load_balancer::discovery::StaticFixedDiscovery;
+use satex_load_balancer::resolver::{ArcLoadBalancerResolver, LoadBalancerResolver};
+use satex_load_balancer::selector::RoundRobin;
+use satex_load_balancer::{Backend, Backends, LoadBalancer};
+use satex_service::make::MakeRouteService;
+use satex_service::proxy::{MakeProxyRouteService, ProxyRouteService, X_SATEX_CIRCUIT_BREAKER};
+use serde_yaml::Value;
+use std::collections::BTreeSet;
+use std::convert::Infallible;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::{LocalSet, spawn_local};
+use tokio::time::sleep;
+use tower::Service;
+
+struct Upstreams(Arc<LoadBalancer>);
+
+impl LoadBalancerResolver for Upstreams {
+    fn find(&self, name: &str) -> Option<Arc<LoadBalancer>> {
+        (name == "backend").then(|| self.0.clone())
+    }
+}
+
+///
+/// 启动后端服务, 返回固定的状态码
+///
+async fn start_server(status: StatusCode) -> SocketAddr {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    spawn_local(async move {
+        let builder = Builder::new(SpawnLocalExecutor::new());
+        while let Ok((stream, _)) = listener.accept().await {
+            let connection = builder
+                .serve_connection(
+                    TokioIo::new(stream),
+                    service_fn(move |_: Request<Incoming>| async move {
+                        let mut response = Response::new(Body::empty());
+                        *response.status_mut() = status;
+                        Ok::<_, Infallible>(response)
+                    }),
+                )
+                .into_owned();
+            spawn_local(connection);
+        }
+    });
+    addr
+}
+
+async fn make(
+    addr: SocketAddr,
+    circuit_breaker: CircuitBreaker,
+) -> ProxyRouteService<DefaultDigester> {
+    let backends = BTreeSet::from([Backend::new(addr)]);
+    let selector = RoundRobin::new(&backends);
+    let load_balancer =
+        LoadBalancer::new(Backends::new(StaticFixedDiscovery::new(backends)), selector)
+            .with_name("backend")
+            .with_circuit_breaker(circuit_breaker);
+    load_balancer.update().await.unwrap();
+
+    let mut extensions = Extensions::new();
+    extensions.insert(ArcLoadBalancerResolver::new(Upstreams(Arc::new(
+        load_balancer,
+    ))));
+    let value = serde_yaml::from_str::<Value>("uri: http://backend").unwrap();
+    MakeProxyRouteService
+        .make(Args::Full(&value), &extensions)
+        .unwrap()
+}
+
+async fn call(service: &mut ProxyRouteService<DefaultDigester>) -> (StatusCode, Option<String>) {
+    let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+    let response = service.call(request).await.unwrap();
+    let rejection = response
+        .headers()
+        .get(X_SATEX_CIRCUIT_BREAKER)
+        .map(|value| value.to_str().unwrap().to_string());
+    (response.status(), rejection)
+}
+
+#[tokio::test]
+async fn reject_when_open() {
+    LocalSet::new()
+        .run_until(async {
+            let addr = start_server(StatusCode::INTERNAL_SERVER_ERROR).await;
+            let mut service = make(
+                addr,
+                CircuitBreaker::default().with_consecutive_failures(Some(2)),
+            )
+            .await;
+            for _ in 0..2 {
+                assert_eq!(
+                    call(&mut service).await,
+                    (StatusCode::INTERNAL_SERVER_ERROR, None)
+                );
+            }
+            for _ in 0..2 {
+                assert_eq!(
+                    call(&mut service).await,
+                    (StatusCode::SERVICE_UNAVAILABLE, Some("open".to_string()))
+                );
+            }
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn reject_max_requests() {
+    LocalSet::new()
+        .run_until(async {
+            let addr = start_server(StatusCode::OK).await;
+            let mut service =
+                make(addr, CircuitBreaker::default().with_max_requests(Some(1))).await;
+
+            // 响应体读取完成之前一直占用请求数
+            let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+            let response = service.call(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(
+                call(&mut service).await,
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Some("max-requests".to_string())
+                )
+            );
+            drop(response);
+            assert_eq!(call(&mut service).await, (StatusCode::OK, None));
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn reject_max_connections() {
+    LocalSet::new()
+        .run_until(async {
+            let addr = start_server(StatusCode::OK).await;
+            let backends = BTreeSet::from([Backend::new(addr)]);
+            let selector = RoundRobin::new(&backends);
+            let load_balancer =
+                LoadBalancer::new(Backends::new(StaticFixedDiscovery::new(backends)), selector)
+                    .with_name("backend")
+                    .with_circuit_breaker(CircuitBreaker::default().with_max_connections(Some(1)));
+            load_balancer.update().await.unwrap();
+            let mut extensions = Extensions::new();
+            extensions.insert(ArcLoadBalancerResolver::new(Upstreams(Arc::new(
+                load_balancer,
+            ))));
+            let value = serde_yaml::from_str::<Value>("uri: http://backend").unwrap();
+            let make = || {
+                MakeProxyRouteService
+                    .make(Args::Full(&value), &extensions)
+                    .unwrap()
+            };
+
+            // 每个路由使用独立的连接池, 连接池中空闲的连接同样占用后端的连接数
+            let mut first = make();
+            let mut second = make();
+            assert_eq!(call(&mut first).await, (StatusCode::OK, None));
+            assert_eq!(
+                call(&mut second).await,
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Some("max-connections".to_string())
+                )
+            );
+
+            // 复用空闲的连接不受影响
+            assert_eq!(call(&mut first).await, (StatusCode::OK, None));
+            drop(first);
+            sleep(Duration::from_millis(50)).await;
+            assert_eq!(call(&mut second).await, (StatusCode::OK, None));
+        })
+        .await;
+}
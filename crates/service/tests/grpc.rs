@@ -0,0 +1,181 @@
+use bytes::Bytes;
+use futures::{StreamExt, stream};
+use http::header::TE;
+use http::{Extensions, HeaderMap, Request, Response, StatusCode, Version};
+use http_body::Frame;
+use http_body_util::{BodyExt, StreamBody};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use hyper_util::server::conn::auto::Builder;
+use satex_core::body::Body;
+use satex_core::component::Args;
+use satex_core::digest::DefaultDigester;
+use satex_core::executor::SpawnLocalExecutor;
+use satex_service::make::MakeRouteService;
+use satex_service::proxy::{MakeProxyRouteService, ProxyRouteService};
+use serde_yaml::Value;
+use std::convert::Infallible;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::{LocalSet, spawn_local};
+use tokio::time::sleep;
+use tower::Service;
+
+///
+/// 启动gRPC后端服务, 返回请求的协议版本以及是否收到`TE: trailers`
+///
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    spawn_local(async move {
+        let builder = Builder::new(SpawnLocalExecutor::new());
+        while let Ok((stream, _)) = listener.accept().await {
+            let connection = builder
+                .serve_connection(
+                    TokioIo::new(stream),
+                    service_fn(|request: Request<Incoming>| async move {
+                        let response = Response::builder()
+                            .header("x-version", format!("{:?}", request.version()))
+                            .header("x-te", request.headers().contains_key(TE).to_string());
+                        let response = match request.uri().path() {
+                            "/unimplemented" => response
+                                .status(StatusCode::NOT_FOUND)
+                                .body(Body::empty())
+                                .unwrap(),
+                            "/reset" => {
+                                // 发送响应头以及部分数据后中断
+                                let frames =
+                                    stream::iter([Ok(Frame::data(Bytes::from_static(b"hello")))])
+                                        .chain(stream::once(async {
+                                            sleep(Duration::from_millis(50)).await;
+                                            Err::<Frame<Bytes>, _>(io::Error::other("reset"))
+                                        }));
+                                response.body(Body::new(StreamBody::new(frames))).unwrap()
+                            }
+                            _ => {
+                                let mut trailers = HeaderMap::new();
+                                trailers.insert("grpc-status", "0".parse().unwrap());
+                                let frames: [Result<Frame<Bytes>, Infallible>; 2] = [
+                                    Ok(Frame::data(Bytes::from_static(b"hello"))),
+                                    Ok(Frame::trailers(trailers)),
+                                ];
+                                response
+                                    .header("content-type", "application/grpc")
+                                    .body(Body::new(StreamBody::new(stream::iter(frames))))
+                                    .unwrap()
+                            }
+                        };
+                        Ok::<_, Infallible>(response)
+                    }),
+                )
+                .into_owned();
+            spawn_local(connection);
+        }
+    });
+    addr
+}
+
+fn make(addr: SocketAddr) -> ProxyRouteService<DefaultDigester> {
+    let value =
+        serde_yaml::from_str::<Value>(&format!("uri: http://{}\nclient:\n  protocol: Http2", addr))
+            .unwrap();
+    MakeProxyRouteService
+        .make(Args::Full(&value), &Extensions::new())
+        .unwrap()
+}
+
+fn grpc_request(path: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(path)
+        .header("content-type", "application/grpc")
+        .header(TE, "trailers")
+        .body(Body::from("hello"))
+        .unwrap()
+}
+
+///
+/// 读取响应体, 返回数据以及trailers
+///
+async fn collect(response: Response<Body>) -> (Bytes, Option<HeaderMap>) {
+    let collected = response.into_body().collect().await.unwrap();
+    let trailers = collected.trailers().cloned();
+    (collected.to_bytes(), trailers)
+}
+
+fn get<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).map(|value| value.to_str().unwrap())
+}
+
+#[tokio::test]
+async fn h2c_trailers() {
+    LocalSet::new()
+        .run_until(async {
+            let addr = start_server().await;
+            let mut service = make(addr);
+            let response = service.call(grpc_request("/echo")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(
+                get(response.headers(), "x-version"),
+                Some(format!("{:?}", Version::HTTP_2).as_str())
+            );
+            assert_eq!(get(response.headers(), "x-te"), Some("true"));
+            let (data, trailers) = collect(response).await;
+            assert_eq!(data, "hello");
+            assert_eq!(get(&trailers.unwrap(), "grpc-status"), Some("0"));
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn upstream_status() {
+    LocalSet::new()
+        .run_until(async {
+            let addr = start_server().await;
+            let mut service = make(addr);
+
+            // 没有`grpc-status`的HTTP错误状态码
+            let response = service.call(grpc_request("/unimplemented")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(get(response.headers(), "grpc-status"), Some("12"));
+            assert_eq!(
+                get(response.headers(), "content-type"),
+                Some("application/grpc")
+            );
+
+            // 响应体中断, 没有收到`grpc-status`
+            let response = service.call(grpc_request("/reset")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let (data, trailers) = collect(response).await;
+            assert_eq!(data, "hello");
+            let trailers = trailers.unwrap();
+            assert!(matches!(get(&trailers, "grpc-status"), Some("13" | "14")));
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn upstream_unavailable() {
+    LocalSet::new()
+        .run_until(async {
+            let addr = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+                .await
+                .unwrap()
+                .local_addr()
+                .unwrap();
+            let mut service = make(addr);
+
+            let response = service.call(grpc_request("/echo")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(get(response.headers(), "grpc-status"), Some("14"));
+            assert!(response.headers().contains_key("grpc-message"));
+
+            // 非gRPC请求仍然返回错误
+            let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+            assert!(service.call(request).await.is_err());
+        })
+        .await;
+}
@@ -0,0 +1,134 @@
+use http::uri::Scheme;
+use http::{Extensions, Request, Response};
+use http_body_util::BodyExt;
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use hyper_util::server::conn::auto::Builder;
+use satex_core::body::Body;
+use satex_core::component::Args;
+use satex_core::digest::DefaultDigester;
+use satex_core::executor::SpawnLocalExecutor;
+use satex_load_balancer::discovery::StaticFixedDiscovery;
+use satex_load_balancer::resolver::{ArcLoadBalancerResolver, LoadBalancerResolver};
+use satex_load_balancer::selector::RoundRobin;
+use satex_load_balancer::{Backend, Backends, LoadBalancer};
+use satex_service::make::MakeRouteService;
+use satex_service::proxy::{MakeProxyRouteService, ProxyRouteService};
+use std::collections::BTreeSet;
+use std::convert::Infallible;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::task::{LocalSet, spawn_local};
+use tower::Service;
+
+struct Upstreams(Arc<LoadBalancer>);
+
+impl LoadBalancerResolver for Upstreams {
+    fn find(&self, name: &str) -> Option<Arc<LoadBalancer>> {
+        (name == "backend").then(|| self.0.clone())
+    }
+}
+
+///
+/// 启动后端服务, 返回请求的路径
+///
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    spawn_local(async move {
+        let builder = Builder::new(SpawnLocalExecutor::new());
+        while let Ok((stream, _)) = listener.accept().await {
+            let connection = builder
+                .serve_connection(
+                    TokioIo::new(stream),
+                    service_fn(move |request: Request<Incoming>| async move {
+                        let path = request.uri().path().to_string();
+                        Ok::<_, Infallible>(Response::new(Body::from(path)))
+                    }),
+                )
+                .into_owned();
+            spawn_local(connection);
+        }
+    });
+    addr
+}
+
+async fn resolver_extensions(addrs: &[SocketAddr], scheme: Scheme) -> Extensions {
+    let backends = addrs
+        .iter()
+        .copied()
+        .map(Backend::new)
+        .collect::<BTreeSet<_>>();
+    let selector = RoundRobin::new(&backends);
+    let load_balancer =
+        LoadBalancer::new(Backends::new(StaticFixedDiscovery::new(backends)), selector)
+            .with_name("backend")
+            .with_scheme(scheme);
+    load_balancer.update().await.unwrap();
+
+    let mut extensions = Extensions::new();
+    extensions.insert(ArcLoadBalancerResolver::new(Upstreams(Arc::new(
+        load_balancer,
+    ))));
+    extensions
+}
+
+async fn call(service: &mut ProxyRouteService<DefaultDigester>, uri: &str) -> Option<String> {
+    let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+    let response = service.call(request).await.ok()?;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    Some(String::from_utf8_lossy(&body).into_owned())
+}
+
+#[tokio::test]
+async fn unknown_upstream() {
+    let extensions = resolver_extensions(&[], Scheme::HTTP).await;
+    let error = MakeProxyRouteService
+        .make(Args::Shortcut(Some("lb://backnd/api")), &extensions)
+        .err()
+        .unwrap();
+    assert!(error.to_string().contains("unknown upstream `backnd`"));
+
+    // 没有配置负载均衡解析器
+    assert!(
+        MakeProxyRouteService
+            .make(Args::Shortcut(Some("lb://backend")), &Extensions::new())
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn forward_to_upstream() {
+    LocalSet::new()
+        .run_until(async {
+            let addr = start_server().await;
+            let extensions = resolver_extensions(&[addr], Scheme::HTTP).await;
+            let mut service = MakeProxyRouteService
+                .make(Args::Shortcut(Some("lb://backend")), &extensions)
+                .unwrap();
+            assert_eq!(call(&mut service, "/api").await.as_deref(), Some("/api"));
+
+            // 使用上游配置的协议转发, 后端不支持https时转发失败
+            let extensions = resolver_extensions(&[addr], Scheme::HTTPS).await;
+            let mut service = MakeProxyRouteService
+                .make(Args::Shortcut(Some("lb://backend")), &extensions)
+                .unwrap();
+            assert_eq!(call(&mut service, "/api").await, None);
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn no_available_backend() {
+    LocalSet::new()
+        .run_until(async {
+            let extensions = resolver_extensions(&[], Scheme::HTTP).await;
+            let mut service = MakeProxyRouteService
+                .make(Args::Shortcut(Some("lb://backend")), &extensions)
+                .unwrap();
+            assert_eq!(call(&mut service, "/").await, None);
+        })
+        .await;
+}
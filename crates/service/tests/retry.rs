@@ -0,0 +1,200 @@
+use http::{Extensions, Method, Request, Response, StatusCode};
+use http_body_util::BodyExt;
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use hyper_util::server::conn::auto::Builder;
+use satex_core::body::Body;
+use satex_core::component::Args;
+use satex_core::digest::DefaultDigester;
+use satex_core::executor::SpawnLocalExecutor;
+use satex_load_balancer::discovery::StaticFixedDiscovery;
+use satex_load_balancer::resolver::{ArcLoadBalancerResolver, LoadBalancerResolver};
+use satex_load_balancer::selector::RoundRobin;
+use satex_load_balancer::{Backend, Backends, LoadBalancer};
+use satex_service::make::MakeRouteService;
+use satex_service::proxy::{MakeProxyRouteService, ProxyRouteService};
+use serde_yaml::Value;
+use std::collections::BTreeSet;
+use std::convert::Infallible;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::task::{LocalSet, spawn_local};
+use tower::Service;
+
+struct Upstreams(Arc<LoadBalancer>);
+
+impl LoadBalancerResolver for Upstreams {
+    fn find(&self, name: &str) -> Option<Arc<LoadBalancer>> {
+        (name == "backend").then(|| self.0.clone())
+    }
+}
+
+///
+/// 启动后端服务, 返回请求方法和请求体, 或者固定的状态码
+///
+async fn start_server(status: StatusCode) -> SocketAddr {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    spawn_local(async move {
+        let builder = Builder::new(SpawnLocalExecutor::new());
+        while let Ok((stream, _)) = listener.accept().await {
+            let connection = builder
+                .serve_connection(
+                    TokioIo::new(stream),
+                    service_fn(move |request: Request<Incoming>| async move {
+                        let method = request.method().to_string();
+                        let body = request.into_body().collect().await.unwrap().to_bytes();
+                        let mut response = Response::new(Body::from(format!(
+                            "{} {}",
+                            method,
+                            String::from_utf8_lossy(&body)
+                        )));
+                        *response.status_mut() = status;
+                        Ok::<_, Infallible>(response)
+                    }),
+                )
+                .into_owned();
+            spawn_local(connection);
+        }
+    });
+    addr
+}
+
+///
+/// 没有监听的地址, 连接会被拒绝
+///
+async fn refused_addr() -> SocketAddr {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    listener.local_addr().unwrap()
+}
+
+async fn make(addrs: [SocketAddr; 2], retry: &str) -> ProxyRouteService<DefaultDigester> {
+    let backends = addrs.into_iter().map(Backend::new).collect::<BTreeSet<_>>();
+    let selector = RoundRobin::new(&backends);
+    let load_balancer =
+        LoadBalancer::new(Backends::new(StaticFixedDiscovery::new(backends)), selector)
+            .with_name("backend");
+    load_balancer.update().await.unwrap();
+
+    let mut extensions = Extensions::new();
+    extensions.insert(ArcLoadBalancerResolver::new(Upstreams(Arc::new(
+        load_balancer,
+    ))));
+    let value = serde_yaml::from_str::<Value>(&format!("uri: http://backend\n{}", retry)).unwrap();
+    MakeProxyRouteService
+        .make(Args::Full(&value), &extensions)
+        .unwrap()
+}
+
+async fn call(
+    service: &mut ProxyRouteService<DefaultDigester>,
+    method: Method,
+    body: &'static str,
+) -> Option<(StatusCode, String)> {
+    let request = Request::builder()
+        .uri("/")
+        .method(method)
+        .body(Body::from(body))
+        .unwrap();
+    let response = service.call(request).await.ok()?;
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    Some((status, String::from_utf8_lossy(&body).into_owned()))
+}
+
+#[tokio::test]
+async fn retry_connect_failure() {
+    LocalSet::new()
+        .run_until(async {
+            let addrs = [refused_addr().await, start_server(StatusCode::OK).await];
+            let mut service = make(addrs, "retry:\n  attempts: 2").await;
+            for _ in 0..4 {
+                assert_eq!(
+                    call(&mut service, Method::GET, "").await,
+                    Some((StatusCode::OK, "GET ".to_string()))
+                );
+                assert_eq!(
+                    call(&mut service, Method::PUT, "hello").await,
+                    Some((StatusCode::OK, "PUT hello".to_string()))
+                );
+            }
+
+            // 默认不重试非幂等的请求方法
+            let mut failures = 0;
+            for _ in 0..4 {
+                if call(&mut service, Method::POST, "hello").await.is_none() {
+                    failures += 1;
+                }
+            }
+            assert_eq!(failures, 2);
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn retry_status() {
+    LocalSet::new()
+        .run_until(async {
+            let addrs = [
+                start_server(StatusCode::SERVICE_UNAVAILABLE).await,
+                start_server(StatusCode::OK).await,
+            ];
+            let mut service = make(
+                addrs,
+                "retry:\n  attempts: 2\n  statuses: [503]\n  methods: [GET, POST]",
+            )
+            .await;
+            for _ in 0..4 {
+                assert_eq!(
+                    call(&mut service, Method::POST, "hello").await,
+                    Some((StatusCode::OK, "POST hello".to_string()))
+                );
+            }
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn retry_large_body() {
+    LocalSet::new()
+        .run_until(async {
+            let addrs = [refused_addr().await, start_server(StatusCode::OK).await];
+            let mut service = make(addrs, "retry:\n  attempts: 2\n  max_body_size: 4").await;
+
+            // 请求体超过缓存大小时只转发一次
+            let mut failures = 0;
+            for _ in 0..4 {
+                match call(&mut service, Method::PUT, "hello").await {
+                    Some(response) => {
+                        assert_eq!(response, (StatusCode::OK, "PUT hello".to_string()))
+                    }
+                    None => failures += 1,
+                }
+            }
+            assert_eq!(failures, 2);
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn retry_exhausted_backends() {
+    LocalSet::new()
+        .run_until(async {
+            let addrs = [
+                start_server(StatusCode::SERVICE_UNAVAILABLE).await,
+                start_server(StatusCode::SERVICE_UNAVAILABLE).await,
+            ];
+            let mut service = make(addrs, "retry:\n  attempts: 5\n  statuses: [503]").await;
+
+            // 所有后端都尝试过时不再重试, 返回最后一次转发的结果
+            for _ in 0..4 {
+                assert_eq!(
+                    call(&mut service, Method::GET, "").await,
+                    Some((StatusCode::SERVICE_UNAVAILABLE, "GET ".to_string()))
+                );
+            }
+        })
+        .await;
+}
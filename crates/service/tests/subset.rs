@@ -0,0 +1,191 @@
+use http::{Extensions, Request, Response};
+use http_body_util::BodyExt;
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use hyper_util::server::conn::auto::Builder;
+use satex_core::body::Body;
+use satex_core::component::Args;
+use satex_core::digest::DefaultDigester;
+use satex_core::executor::SpawnLocalExecutor;
+use satex_load_balancer::discovery::StaticFixedDiscovery;
+use satex_load_balancer::resolver::{ArcLoadBalancerResolver, LoadBalancerResolver};
+use satex_load_balancer::selector::RoundRobin;
+use satex_load_balancer::{Backend, Backends, Labels, LoadBalancer};
+use satex_service::make::MakeRouteService;
+use satex_service::proxy::{MakeProxyRouteService, ProxyRouteService};
+use serde_yaml::Value;
+use std::collections::BTreeSet;
+use std::convert::Infallible;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::task::{LocalSet, spawn_local};
+use tower::Service;
+
+struct Upstreams(Arc<LoadBalancer>);
+
+impl LoadBalancerResolver for Upstreams {
+    fn find(&self, name: &str) -> Option<Arc<LoadBalancer>> {
+        (name == "backend").then(|| self.0.clone())
+    }
+}
+
+///
+/// 启动后端服务, 响应固定的名称
+///
+async fn start_server(name: &'static str) -> SocketAddr {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    spawn_local(async move {
+        let builder = Builder::new(SpawnLocalExecutor::new());
+        while let Ok((stream, _)) = listener.accept().await {
+            let connection = builder
+                .serve_connection(
+                    TokioIo::new(stream),
+                    service_fn(move |_: Request<Incoming>| async move {
+                        Ok::<_, Infallible>(Response::new(Body::from(name)))
+                    }),
+                )
+                .into_owned();
+            spawn_local(connection);
+        }
+    });
+    addr
+}
+
+async fn make(subset: &str) -> ProxyRouteService<DefaultDigester> {
+    let mut backends = BTreeSet::new();
+    for version in ["v1", "v2"] {
+        let labels = Labels::from_iter([("version".to_string(), version.to_string())]);
+        backends.insert(Backend::new(start_server(version).await).with_labels(labels));
+    }
+    let selector = RoundRobin::new(&backends);
+    let load_balancer =
+        LoadBalancer::new(Backends::new(StaticFixedDiscovery::new(backends)), selector)
+            .with_name("backend");
+    load_balancer.update().await.unwrap();
+
+    let mut extensions = Extensions::new();
+    extensions.insert(ArcLoadBalancerResolver::new(Upstreams(Arc::new(
+        load_balancer,
+    ))));
+    let value = serde_yaml::from_str::<Value>(&format!("uri: http://backend\n{}", subset)).unwrap();
+    MakeProxyRouteService
+        .make(Args::Full(&value), &extensions)
+        .unwrap()
+}
+
+async fn call(
+    service: &mut ProxyRouteService<DefaultDigester>,
+    uri: &str,
+    version: Option<&str>,
+) -> Option<String> {
+    let mut request = Request::builder().uri(uri);
+    if let Some(version) = version {
+        request = request.header("x-version", version);
+    }
+    let response = service
+        .call(request.body(Body::empty()).unwrap())
+        .await
+        .ok()?;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    Some(String::from_utf8_lossy(&body).into_owned())
+}
+
+#[tokio::test]
+async fn subset_by_header_and_query() {
+    LocalSet::new()
+        .run_until(async {
+            let mut service = make(
+                r#"
+subset:
+  labels:
+    - name: version
+      header: x-version
+"#,
+            )
+            .await;
+            for version in ["v1", "v2", "v1", "v2"] {
+                assert_eq!(
+                    call(&mut service, "/", Some(version)).await.as_deref(),
+                    Some(version)
+                );
+            }
+            // 没有请求头时不匹配该标签, 在所有后端中选择
+            let mut selected = BTreeSet::new();
+            for _ in 0..4 {
+                selected.insert(call(&mut service, "/", None).await.unwrap());
+            }
+            assert_eq!(selected.len(), 2);
+
+            let mut service = make(
+                r#"
+subset:
+  labels:
+    - name: version
+      query: version
+"#,
+            )
+            .await;
+            for _ in 0..4 {
+                assert_eq!(
+                    call(&mut service, "/?a=b&version=v2", None)
+                        .await
+                        .as_deref(),
+                    Some("v2")
+                );
+            }
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn subset_fallback() {
+    LocalSet::new()
+        .run_until(async {
+            let mut service = make(
+                r#"
+subset:
+  labels:
+    - name: version
+      value: v3
+"#,
+            )
+            .await;
+            assert!(call(&mut service, "/", None).await.is_some());
+
+            let mut service = make(
+                r#"
+subset:
+  labels:
+    - name: version
+      value: v3
+  fallback: false
+"#,
+            )
+            .await;
+            assert_eq!(call(&mut service, "/", None).await, None);
+        })
+        .await;
+}
+
+#[test]
+fn subset_invalid_label() {
+    let value = serde_yaml::from_str::<Value>(
+        r#"
+uri: http://backend
+subset:
+  labels:
+    - name: version
+      value: v1
+      header: x-version
+"#,
+    )
+    .unwrap();
+    assert!(
+        MakeProxyRouteService
+            .make(Args::Full(&value), &Extensions::new())
+            .is_err()
+    );
+}
@@ -0,0 +1,213 @@
+use http::uri::Scheme;
+use http::{Extensions, Request, Response, StatusCode};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use hyper_util::server::conn::auto::Builder;
+use rustls::RootCertStore;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use satex_core::body::Body;
+use satex_core::component::Args;
+use satex_core::digest::DefaultDigester;
+use satex_core::executor::SpawnLocalExecutor;
+use satex_load_balancer::discovery::StaticFixedDiscovery;
+use satex_load_balancer::resolver::{ArcLoadBalancerResolver, LoadBalancerResolver};
+use satex_load_balancer::selector::RoundRobin;
+use satex_load_balancer::{Backend, Backends, LoadBalancer};
+use satex_service::make::MakeRouteService;
+use satex_service::proxy::{MakeProxyRouteService, ProxyRouteService};
+use serde_yaml::Value;
+use std::collections::BTreeSet;
+use std::convert::Infallible;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::task::{LocalSet, spawn_local};
+use tokio_rustls::TlsAcceptor;
+use tower::Service;
+
+struct Upstreams(Arc<LoadBalancer>);
+
+impl LoadBalancerResolver for Upstreams {
+    fn find(&self, name: &str) -> Option<Arc<LoadBalancer>> {
+        (name == "backend").then(|| self.0.clone())
+    }
+}
+
+fn file(name: &str) -> String {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/file/tls")
+        .join(name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+///
+/// 启动要求客户端证书的HTTPS后端服务
+///
+async fn start_server() -> SocketAddr {
+    let mut roots = RootCertStore::empty();
+    roots
+        .add(CertificateDer::from_pem_file(file("ca.pem")).unwrap())
+        .unwrap();
+    let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+        .build()
+        .unwrap();
+    let certs = CertificateDer::pem_file_iter(file("server.pem"))
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let key = PrivateKeyDer::from_pem_file(file("server.key")).unwrap();
+    let config = rustls::ServerConfig::builder()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(certs, key)
+        .unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    spawn_local(async move {
+        let builder = Builder::new(SpawnLocalExecutor::new());
+        while let Ok((stream, _)) = listener.accept().await {
+            let Ok(stream) = acceptor.accept(stream).await else {
+                continue;
+            };
+            let connection = builder
+                .serve_connection(
+                    TokioIo::new(stream),
+                    service_fn(move |_: Request<Incoming>| async move {
+                        Ok::<_, Infallible>(Response::new(Body::from("tls")))
+                    }),
+                )
+                .into_owned();
+            spawn_local(connection);
+        }
+    });
+    addr
+}
+
+async fn make(addr: SocketAddr, tls: &str) -> Result<ProxyRouteService<DefaultDigester>, String> {
+    let backends = BTreeSet::from([Backend::new(addr)]);
+    let selector = RoundRobin::new(&backends);
+    let load_balancer =
+        LoadBalancer::new(Backends::new(StaticFixedDiscovery::new(backends)), selector)
+            .with_name("backend")
+            .with_scheme(Scheme::HTTPS);
+    load_balancer.update().await.unwrap();
+    let mut extensions = Extensions::new();
+    extensions.insert(ArcLoadBalancerResolver::new(Upstreams(Arc::new(
+        load_balancer,
+    ))));
+
+    let value =
+        serde_yaml::from_str::<Value>(&format!("uri: lb://backend\nclient:\n  tls:\n{}", tls))
+            .unwrap();
+    MakeProxyRouteService
+        .make(Args::Full(&value), &extensions)
+        .map_err(|e| e.to_string())
+}
+
+async fn call(service: &mut ProxyRouteService<DefaultDigester>) -> Option<StatusCode> {
+    let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+    service
+        .call(request)
+        .await
+        .ok()
+        .map(|response| response.status())
+}
+
+#[tokio::test]
+async fn mutual_tls() {
+    LocalSet::new()
+        .run_until(async {
+            let addr = start_server().await;
+            let mut service = make(
+                addr,
+                &format!(
+                    r#"
+    ca_certs: {}
+    native_roots: false
+    client_certs: {}
+    client_key: {}
+    server_name: backend.satex.test
+    min_version: Tls13
+"#,
+                    file("ca.pem"),
+                    file("client.pem"),
+                    file("client.key")
+                ),
+            )
+            .await
+            .unwrap();
+            assert_eq!(call(&mut service).await, Some(StatusCode::OK));
+
+            // 缺少客户端证书
+            let mut service = make(
+                addr,
+                &format!(
+                    "    ca_certs: {}\n    server_name: backend.satex.test\n",
+                    file("ca.pem")
+                ),
+            )
+            .await
+            .unwrap();
+            assert_eq!(call(&mut service).await, None);
+
+            // 服务器名称与证书不匹配
+            let mut service = make(
+                addr,
+                &format!(
+                    "    ca_certs: {}\n    client_certs: {}\n    client_key: {}\n",
+                    file("ca.pem"),
+                    file("client.pem"),
+                    file("client.key")
+                ),
+            )
+            .await
+            .unwrap();
+            assert_eq!(call(&mut service).await, None);
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn insecure_skip_verify() {
+    LocalSet::new()
+        .run_until(async {
+            let addr = start_server().await;
+            let mut service = make(
+                addr,
+                &format!(
+                    "    insecure_skip_verify: true\n    client_certs: {}\n    client_key: {}\n",
+                    file("client.pem"),
+                    file("client.key")
+                ),
+            )
+            .await
+            .unwrap();
+            assert_eq!(call(&mut service).await, Some(StatusCode::OK));
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn invalid_config() {
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+    let error = make(addr, &format!("    client_certs: {}\n", file("client.pem")))
+        .await
+        .err()
+        .unwrap();
+    assert!(error.contains("client_key"), "{}", error);
+
+    let error = make(addr, "    ca_certs: /not/exists.pem\n")
+        .await
+        .err()
+        .unwrap();
+    assert!(error.contains("/not/exists.pem"), "{}", error);
+
+    let error = make(addr, "    native_roots: false\n").await.err().unwrap();
+    assert!(error.contains("root certs is empty"), "{}", error);
+}
@@ -0,0 +1,161 @@
+use http::header::{CONNECTION, UPGRADE};
+use http::{Extensions, Request, Response, StatusCode};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use hyper_util::server::conn::auto::Builder;
+use satex_core::body::Body;
+use satex_core::component::Args;
+use satex_core::executor::SpawnLocalExecutor;
+use satex_service::make::MakeRouteService;
+use satex_service::proxy::MakeProxyRouteService;
+use serde_yaml::Value;
+use std::convert::Infallible;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::{LocalSet, spawn_local};
+use tower::Service;
+
+///
+/// 启动后端服务, 同意`echo`协议升级并回显升级后连接上收到的数据
+///
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    spawn_local(async move {
+        let builder = Builder::new(SpawnLocalExecutor::new());
+        while let Ok((stream, _)) = listener.accept().await {
+            let connection = builder
+                .serve_connection_with_upgrades(
+                    TokioIo::new(stream),
+                    service_fn(|mut request: Request<Incoming>| async move {
+                        if request.headers().get(UPGRADE).is_none() {
+                            return Ok::<_, Infallible>(Response::new(Body::from("http")));
+                        }
+                        let upgrade = hyper::upgrade::on(&mut request);
+                        spawn_local(async move {
+                            let mut upgraded = TokioIo::new(upgrade.await.unwrap());
+                            let mut buf = [0; 64];
+                            while let Ok(n) = upgraded.read(&mut buf).await {
+                                if n == 0 || upgraded.write_all(&buf[..n]).await.is_err() {
+                                    break;
+                                }
+                            }
+                        });
+                        let response = Response::builder()
+                            .status(StatusCode::SWITCHING_PROTOCOLS)
+                            .header(CONNECTION, "upgrade")
+                            .header(UPGRADE, "echo")
+                            .body(Body::empty())
+                            .unwrap();
+                        Ok(response)
+                    }),
+                )
+                .into_owned();
+            spawn_local(connection);
+        }
+    });
+    addr
+}
+
+///
+/// 启动代理服务, 转发到后端服务
+///
+async fn start_proxy(backend: SocketAddr, upgrade: &str) -> SocketAddr {
+    let value =
+        serde_yaml::from_str::<Value>(&format!("uri: http://{}\nupgrade:\n{}", backend, upgrade))
+            .unwrap();
+    let service = MakeProxyRouteService
+        .make(Args::Full(&value), &Extensions::new())
+        .unwrap();
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    spawn_local(async move {
+        let builder = Builder::new(SpawnLocalExecutor::new());
+        while let Ok((stream, _)) = listener.accept().await {
+            let service = service.clone();
+            let connection = builder
+                .serve_connection_with_upgrades(
+                    TokioIo::new(stream),
+                    service_fn(move |request: Request<Incoming>| {
+                        let mut service = service.clone();
+                        async move { service.call(request.map(Body::new)).await }
+                    }),
+                )
+                .into_owned();
+            spawn_local(connection);
+        }
+    });
+    addr
+}
+
+///
+/// 发送协议升级请求, 返回响应头
+///
+async fn handshake(stream: &mut TcpStream, upgrade: bool) -> String {
+    let headers = if upgrade {
+        "Connection: Upgrade\r\nUpgrade: echo\r\n"
+    } else {
+        ""
+    };
+    stream
+        .write_all(format!("GET / HTTP/1.1\r\nHost: localhost\r\n{}\r\n", headers).as_bytes())
+        .await
+        .unwrap();
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        head.push(stream.read_u8().await.unwrap());
+    }
+    String::from_utf8(head).unwrap()
+}
+
+async fn echo(stream: &mut TcpStream, message: &[u8]) -> Vec<u8> {
+    stream.write_all(message).await.unwrap();
+    let mut buf = vec![0; message.len()];
+    stream.read_exact(&mut buf).await.unwrap();
+    buf
+}
+
+#[tokio::test]
+async fn websocket_upgrade() {
+    LocalSet::new()
+        .run_until(async {
+            let backend = start_server().await;
+            let proxy = start_proxy(backend, "  idle_timeout_secs: 1").await;
+
+            let mut stream = TcpStream::connect(proxy).await.unwrap();
+            let head = handshake(&mut stream, true).await;
+            assert!(head.starts_with("HTTP/1.1 101"), "{}", head);
+            assert!(head.to_lowercase().contains("upgrade: echo"), "{}", head);
+            assert_eq!(echo(&mut stream, b"hello").await, b"hello");
+            assert_eq!(echo(&mut stream, b"world").await, b"world");
+
+            // 连接空闲超时后关闭
+            tokio::time::sleep(Duration::from_millis(1500)).await;
+            let mut buf = [0; 8];
+            assert_eq!(stream.read(&mut buf).await.unwrap_or_default(), 0);
+
+            // 普通请求不受影响
+            let mut stream = TcpStream::connect(proxy).await.unwrap();
+            let head = handshake(&mut stream, false).await;
+            assert!(head.starts_with("HTTP/1.1 200"), "{}", head);
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn upgrade_disabled() {
+    LocalSet::new()
+        .run_until(async {
+            let backend = start_server().await;
+            let proxy = start_proxy(backend, "  enabled: false").await;
+
+            // 不转发协议升级请求头
+            let mut stream = TcpStream::connect(proxy).await.unwrap();
+            let head = handshake(&mut stream, true).await;
+            assert!(head.starts_with("HTTP/1.1 200"), "{}", head);
+        })
+        .await;
+}
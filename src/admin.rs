@@ -0,0 +1,368 @@
+//!
+//! 管理接口
+//!
+//! | 方法       | 路径             | 描述               |
+//! |----------|----------------|------------------|
+//! | `GET`    | `/routes`      | 查询当前的路由表         |
+//! | `PUT`    | `/routes`      | 使用请求体中的路由列表替换全部路由 |
+//! | `GET`    | `/routes/{id}` | 查询指定的路由          |
+//! | `PUT`    | `/routes/{id}` | 新增或者替换指定的路由      |
+//! | `DELETE` | `/routes/{id}` | 删除指定的路由          |
+//! | `GET`    | `/metrics`     | 查询`Prometheus`指标 |
+//! | `GET`    | `/logging`     | 查询当前的日志过滤指令      |
+//! | `PUT`    | `/logging`     | 修改日志过滤指令         |
+//!
+//! 请求体支持`YAML`和`JSON`格式，结构与配置文件中的路由配置一致。
+//! 每次变更都会通过[`MakeRouter`]重新构建完整的路由，构建失败时返回错误信息，当前生效的路由不受影响。
+//!
+//! 修改日志过滤指令的请求体可以是指令字符串，也可以是`{"filter": "..."}`格式的对象。
+//!
+//! 通过管理接口做出的变更只保存在内存中，配置文件重新加载后将以配置文件为准，
+//! 管理接口与配置文件重新加载共享同一份[`SharedConfig`]，变更总是基于最新生效的配置。
+//!
+use crate::config::{Config, SharedConfig};
+use crate::config::router::Route;
+use crate::logging::LogFilterHandle;
+use crate::make_router::MakeRouter;
+use async_stream::stream;
+use bytes::Bytes;
+use futures::Stream;
+use http::header::CONTENT_TYPE;
+use http::{HeaderValue, Method, Request, Response, StatusCode};
+use http_body_util::BodyExt;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use satex_core::body::Body;
+use satex_core::metrics::{encode, record_reload};
+use satex_core::util::ResponseExt;
+use satex_core::{BoxError, Error};
+use satex_server::router::Event;
+use serde::Serialize;
+use serde_json::json;
+use serde_yaml::Value;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tokio::spawn;
+use tokio::sync::mpsc::{Sender, channel};
+use tracing::{error, info};
+
+const ROUTES: &str = "routes";
+
+const METRICS: &str = "metrics";
+
+const LOGGING: &str = "logging";
+
+const FILTER: &str = "filter";
+
+const SOURCE: &str = "admin";
+
+const ID: &str = "id";
+
+const APPLICATION_JSON: HeaderValue = HeaderValue::from_static("application/json");
+
+const TEXT_PLAIN: HeaderValue = HeaderValue::from_static("text/plain; version=0.0.4");
+
+#[derive(Clone)]
+pub struct Admin {
+    make_router: MakeRouter,
+    config: SharedConfig,
+    sender: Sender<Event>,
+    log_filter: Option<LogFilterHandle>,
+}
+
+impl Admin {
+    ///
+    /// 创建管理接口以及对应的路由事件流
+    ///
+    /// # Arguments
+    ///
+    /// * `make_router`: 创建路由的[`MakeRouter`]
+    /// * `config`: 当前生效的配置, 与配置文件重新加载共享
+    ///
+    /// returns: (Admin, impl Stream<Item=Event>)
+    ///
+    pub fn new(make_router: MakeRouter, config: SharedConfig) -> (Self, impl Stream<Item = Event>) {
+        let (sender, mut receiver) = channel(16);
+        let admin = Self {
+            make_router,
+            config,
+            sender,
+            log_filter: None,
+        };
+        let events = stream! {
+            while let Some(event) = receiver.recv().await {
+                yield event;
+            }
+        };
+        (admin, events)
+    }
+
+    ///
+    /// 设置日志过滤器的重新加载句柄, 用于在运行时修改日志级别
+    ///
+    pub fn with_log_filter(mut self, log_filter: LogFilterHandle) -> Self {
+        self.log_filter = Some(log_filter);
+        self
+    }
+
+    ///
+    /// 启动管理接口服务
+    ///
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), Error> {
+        let listener = TcpListener::bind(addr).await.map_err(Error::new)?;
+        info!("admin server listening on: {}", addr);
+        loop {
+            let (stream, _) = listener.accept().await.map_err(Error::new)?;
+            let admin = self.clone();
+            spawn(async move {
+                let service = service_fn(move |request| {
+                    let admin = admin.clone();
+                    async move { Ok::<_, Infallible>(admin.handle(request).await) }
+                });
+                if let Err(e) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    error!("admin serve connection error: {}", e);
+                }
+            });
+        }
+    }
+
+    ///
+    /// 处理管理接口请求
+    ///
+    pub async fn handle<B>(&self, request: Request<B>) -> Response<Body>
+    where
+        B: http_body::Body<Data = Bytes>,
+        B::Error: Into<BoxError>,
+    {
+        let (parts, body) = request.into_parts();
+        let segments = parts
+            .uri
+            .path()
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect::<Vec<_>>();
+
+        match (&parts.method, segments.as_slice()) {
+            (&Method::GET, [ROUTES]) => self.routes().await,
+            (&Method::PUT, [ROUTES]) => match read(body).await {
+                Ok(value) => self.set_routes(value).await,
+                Err(e) => failure(StatusCode::BAD_REQUEST, e),
+            },
+            (&Method::GET, [ROUTES, id]) => self.route(id).await,
+            (&Method::PUT, [ROUTES, id]) => match read(body).await {
+                Ok(value) => self.put_route(id, value).await,
+                Err(e) => failure(StatusCode::BAD_REQUEST, e),
+            },
+            (&Method::DELETE, [ROUTES, id]) => self.delete_route(id).await,
+            (&Method::GET, [METRICS]) => metrics(),
+            (&Method::GET, [LOGGING]) => self.log_filter(),
+            (&Method::PUT, [LOGGING]) => match read(body).await {
+                Ok(value) => self.set_log_filter(value),
+                Err(e) => failure(StatusCode::BAD_REQUEST, e),
+            },
+            (_, [ROUTES]) | (_, [ROUTES, _]) | (_, [METRICS]) | (_, [LOGGING]) => {
+                Response::new(Body::empty()).with_status(StatusCode::METHOD_NOT_ALLOWED)
+            }
+            _ => Response::new(Body::empty()).with_status(StatusCode::NOT_FOUND),
+        }
+    }
+
+    async fn routes(&self) -> Response<Body> {
+        let config = self.config.lock().await;
+        success(&config.router.routes)
+    }
+
+    async fn route(&self, id: &str) -> Response<Body> {
+        let config = self.config.lock().await;
+        match config.router.routes.iter().find(|route| route.id == id) {
+            Some(route) => success(route),
+            None => failure(
+                StatusCode::NOT_FOUND,
+                Error::new(format!("route not found: {}", id)),
+            ),
+        }
+    }
+
+    async fn set_routes(&self, value: Value) -> Response<Body> {
+        match serde_yaml::from_value::<Vec<Route>>(value) {
+            Ok(routes) => {
+                self.apply(|config| {
+                    config.router.routes = routes;
+                    Ok(())
+                })
+                .await
+            }
+            Err(e) => failure(StatusCode::BAD_REQUEST, Error::new(e)),
+        }
+    }
+
+    async fn put_route(&self, id: &str, mut value: Value) -> Response<Body> {
+        if let Some(mapping) = value.as_mapping_mut() {
+            match mapping.get(ID).and_then(Value::as_str) {
+                Some(value) if value != id => {
+                    return failure(
+                        StatusCode::BAD_REQUEST,
+                        Error::new(format!("route id mismatch: {} != {}", value, id)),
+                    );
+                }
+                Some(_) => {}
+                None => {
+                    mapping.insert(Value::from(ID), Value::from(id));
+                }
+            }
+        }
+        match serde_yaml::from_value::<Route>(value) {
+            Ok(route) => {
+                self.apply(move |config| {
+                    let routes = &mut config.router.routes;
+                    match routes.iter_mut().find(|item| item.id == route.id) {
+                        Some(item) => *item = route,
+                        None => routes.push(route),
+                    }
+                    Ok(())
+                })
+                .await
+            }
+            Err(e) => failure(StatusCode::BAD_REQUEST, Error::new(e)),
+        }
+    }
+
+    async fn delete_route(&self, id: &str) -> Response<Body> {
+        self.apply(|config| {
+            let routes = &mut config.router.routes;
+            match routes.iter().position(|route| route.id == id) {
+                Some(index) => {
+                    routes.remove(index);
+                    Ok(())
+                }
+                None => Err((
+                    StatusCode::NOT_FOUND,
+                    Error::new(format!("route not found: {}", id)),
+                )),
+            }
+        })
+        .await
+    }
+
+    fn log_filter(&self) -> Response<Body> {
+        match self.log_filter.as_ref().map(LogFilterHandle::current) {
+            Some(Ok(filter)) => success(&json!({ FILTER: filter })),
+            Some(Err(e)) => failure(StatusCode::INTERNAL_SERVER_ERROR, e),
+            None => failure(
+                StatusCode::NOT_FOUND,
+                Error::new("log filter reload is not available"),
+            ),
+        }
+    }
+
+    fn set_log_filter(&self, value: Value) -> Response<Body> {
+        let Some(log_filter) = self.log_filter.as_ref() else {
+            return failure(
+                StatusCode::NOT_FOUND,
+                Error::new("log filter reload is not available"),
+            );
+        };
+        let directives = value
+            .as_str()
+            .or_else(|| value.get(FILTER).and_then(Value::as_str));
+        match directives {
+            Some(directives) => match log_filter.reload(directives) {
+                Ok(_) => {
+                    info!("admin reload log filter: {}", directives);
+                    Response::new(Body::empty()).with_status(StatusCode::NO_CONTENT)
+                }
+                Err(e) => failure(StatusCode::BAD_REQUEST, e),
+            },
+            None => failure(
+                StatusCode::BAD_REQUEST,
+                Error::new("miss log filter directives"),
+            ),
+        }
+    }
+
+    ///
+    /// 在配置副本上应用变更，构建新的路由成功后才会推送路由事件并替换当前配置
+    ///
+    async fn apply<F>(&self, f: F) -> Response<Body>
+    where
+        F: FnOnce(&mut Config) -> Result<(), (StatusCode, Error)>,
+    {
+        let mut config = self.config.lock().await;
+        let mut new_config = config.clone();
+        if let Err((status, e)) = f(&mut new_config) {
+            return failure(status, e);
+        }
+        match self.make_router.make(&new_config) {
+            Ok((router, generation)) => match self.sender.send(Event::Set(router)).await {
+                Ok(_) => {
+                    record_reload(SOURCE, true);
+                    generation.commit();
+                    *config = new_config;
+                    info!("admin refresh routes: {}", config.router.routes.len());
+                    Response::new(Body::empty()).with_status(StatusCode::NO_CONTENT)
+                }
+                Err(e) => {
+                    // 路由没有生效, 停止新的路由已经启动的后台任务
+                    record_reload(SOURCE, false);
+                    if let Event::Set(router) = &e.0 {
+                        router.supervisor().cancel();
+                    }
+                    failure(StatusCode::SERVICE_UNAVAILABLE, Error::new(e))
+                }
+            },
+            Err(e) => {
+                record_reload(SOURCE, false);
+                failure(StatusCode::BAD_REQUEST, e)
+            }
+        }
+    }
+}
+
+async fn read<B>(body: B) -> Result<Value, Error>
+where
+    B: http_body::Body<Data = Bytes>,
+    B::Error: Into<BoxError>,
+{
+    let bytes = body
+        .collect()
+        .await
+        .map_err(|e| Error::new(e.into()))?
+        .to_bytes();
+    serde_yaml::from_slice(&bytes).map_err(Error::new)
+}
+
+fn metrics() -> Response<Body> {
+    match encode() {
+        Ok(text) => {
+            let mut response = Response::new(Body::from(text));
+            response.headers_mut().insert(CONTENT_TYPE, TEXT_PLAIN);
+            response
+        }
+        Err(e) => failure(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+fn success<T: Serialize>(value: &T) -> Response<Body> {
+    match serde_json::to_vec(value) {
+        Ok(bytes) => json_response(StatusCode::OK, bytes),
+        Err(e) => failure(StatusCode::INTERNAL_SERVER_ERROR, Error::new(e)),
+    }
+}
+
+fn failure(status: StatusCode, error: Error) -> Response<Body> {
+    let bytes = json!({ "error": error.to_string() }).to_string();
+    json_response(status, bytes)
+}
+
+fn json_response(status: StatusCode, bytes: impl Into<Body>) -> Response<Body> {
+    let mut response = Response::new(bytes.into()).with_status(status);
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, APPLICATION_JSON);
+    response
+}
@@ -1,21 +1,36 @@
-use crate::config::Config;
+use crate::admin::Admin;
+use crate::config::{Config, SharedConfig};
+use crate::logging;
+use crate::logging::Logging;
 use crate::make_router::MakeRouter;
 use crate::registry::Registry;
-use futures::stream::Empty;
-use futures::Stream;
+use crate::telemetry::tracer_provider;
+use futures::stream::{select, Empty};
+use futures::{Stream, StreamExt};
+use opentelemetry::global;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use satex_core::util::Cidr;
 use satex_core::Error;
+use satex_layer::access_log::AccessLogWriter;
 use satex_server::router::{Event, MakeRouterService};
-use satex_server::Server;
+use satex_server::{RealIp, Server};
 use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
 use tokio::spawn;
-use tracing::Level;
+use tokio::sync::Mutex;
+use tracing::error;
+use tracing_subscriber::Layer;
 
 type Unit = Empty<Event>;
 
 pub struct App<S = Unit> {
     name: String,
     config: Config,
-    registry: Registry,
+    shared_config: SharedConfig,
+    make_router: MakeRouter,
     events: Option<S>,
 }
 
@@ -23,8 +38,9 @@ impl App {
     pub fn new(name: impl Into<String>, config: Config, registry: Registry) -> Self {
         Self {
             name: name.into(),
+            shared_config: Arc::new(Mutex::new(config.clone())),
             config,
-            registry,
+            make_router: MakeRouter::new(registry),
             events: None,
         }
     }
@@ -33,12 +49,29 @@ impl App {
         App {
             name: self.name,
             config: self.config,
-            registry: self.registry,
+            shared_config: self.shared_config,
+            make_router: self.make_router,
             events: Some(events),
         }
     }
 }
 
+impl<S> App<S> {
+    ///
+    /// 创建路由的[MakeRouter], 重新加载配置时使用同一个[MakeRouter]以继承上游的后端健康状态
+    ///
+    pub fn make_router(&self) -> MakeRouter {
+        self.make_router.clone()
+    }
+
+    ///
+    /// 当前生效的配置, 重新加载配置文件时更新该配置, 管理接口基于该配置做出变更
+    ///
+    pub fn shared_config(&self) -> SharedConfig {
+        self.shared_config.clone()
+    }
+}
+
 impl<S> App<S>
 where
     S: Stream<Item=Event> + Send + 'static,
@@ -47,17 +80,37 @@ where
         let App {
             name,
             config,
-            registry,
+            shared_config,
+            make_router,
             events,
-            ..
         } = self;
 
         // 初始化Tracing
-        setup(&config);
+        let (logging, tracer_provider) = setup(&config)?;
 
         // 创建路由
-        let make_router = MakeRouter::new(registry);
-        let router = make_router.make(&config)?;
+        let (router, generation) = make_router.make(&config)?;
+        generation.commit();
+
+        // 启动管理接口
+        let events = match config.admin.enabled {
+            true => {
+                let (admin, admin_events) = Admin::new(make_router, shared_config);
+                let admin = admin.with_log_filter(logging.handle.clone());
+                let addr = SocketAddr::new(config.admin.host, config.admin.port);
+                spawn(async move {
+                    if let Err(e) = admin.serve(addr).await {
+                        error!("Admin server error: {}", e);
+                    }
+                });
+                Some(match events {
+                    Some(events) => select(events, admin_events).boxed(),
+                    None => admin_events.boxed(),
+                })
+            }
+            false => events.map(StreamExt::boxed),
+        };
+
         let make_service = match events {
             Some(events) => router.into_dynamic_service(events, |future| {
                 spawn(future);
@@ -67,28 +120,46 @@ where
 
         // 启动服务
         let addr = SocketAddr::new(config.server.host, config.server.port);
-        serve(&config, make_service)
+        let result = serve(&config, make_service)?
             .bind(name, addr)?
             .await
-            .map_err(Error::new)
+            .map_err(Error::new);
+
+        // 写入剩余的访问日志
+        AccessLogWriter::shutdown();
+
+        // 导出剩余的span
+        if let Some(tracer_provider) = tracer_provider
+            && let Err(e) = tracer_provider.shutdown()
+        {
+            error!("Shutdown tracer provider error: {}", e);
+        }
+        result
     }
 }
 
-fn setup(config: &Config) {
+fn setup(config: &Config) -> Result<(Logging, Option<SdkTracerProvider>), Error> {
     let tracing = &config.tracing;
-    tracing_subscriber::fmt()
-        .with_max_level(Level::from(tracing.max_level))
-        .with_ansi(tracing.display_ansi)
-        .with_file(tracing.display_file)
-        .with_thread_names(tracing.display_thread_names)
-        .with_thread_ids(tracing.display_thread_ids)
-        .with_line_number(tracing.display_line_number)
-        .with_level(tracing.display_level)
-        .with_target(tracing.display_target)
-        .init();
+    let (otel, tracer_provider) = match tracing.otlp.enabled {
+        true => {
+            let tracer_provider = tracer_provider(&tracing.otlp, &tracing.sampler)?;
+            global::set_text_map_propagator(TraceContextPropagator::new());
+            let tracer = tracer_provider.tracer(tracing.otlp.service_name.clone());
+            (
+                Some(tracing_opentelemetry::layer().with_tracer(tracer).boxed()),
+                Some(tracer_provider),
+            )
+        }
+        false => (None, None),
+    };
+    let logging = logging::init(tracing, otel)?;
+    Ok((logging, tracer_provider))
 }
 
-fn serve(config: &Config, make_service: MakeRouterService) -> Server<MakeRouterService> {
+fn serve(
+    config: &Config,
+    make_service: MakeRouterService,
+) -> Result<Server<MakeRouterService>, Error> {
     let mut builder = Server::builder();
     if let Some(workers) = config.server.workers {
         builder = builder.workers(workers);
@@ -99,6 +170,15 @@ fn serve(config: &Config, make_service: MakeRouterService) -> Server<MakeRouterS
     if let Some(backlog) = config.server.backlog {
         builder = builder.backlog(backlog);
     }
+    if let Some(real_ip) = &config.server.real_ip {
+        let trusted_proxies = real_ip
+            .trusted_proxies
+            .iter()
+            .map(|cidr| Cidr::from_str(cidr))
+            .collect::<Result<Vec<_>, _>>()?;
+        builder = builder
+            .real_ip(RealIp::new(trusted_proxies).with_headers(real_ip.headers.clone()));
+    }
     if config.server.tls.enabled {
         let mut builder = builder
             .tls()
@@ -109,8 +189,8 @@ fn serve(config: &Config, make_service: MakeRouterService) -> Server<MakeRouterS
         if let Some(private_key) = &config.server.tls.private_key {
             builder = builder.private_key(private_key);
         }
-        builder.make_service(make_service)
+        Ok(builder.make_service(make_service))
     } else {
-        builder.make_service(make_service)
+        Ok(builder.make_service(make_service))
     }
 }
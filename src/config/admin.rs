@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr};
+
+const DEFAULT_ADMIN_PORT: u16 = 3001;
+
+///
+/// 管理接口配置
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Admin {
+    ///
+    /// 是否开启管理接口
+    ///
+    #[serde(default)]
+    pub enabled: bool,
+
+    ///
+    /// 监听端口
+    ///
+    #[serde(default = "default_port")]
+    pub port: u16,
+
+    ///
+    /// 监听地址
+    ///
+    #[serde(default = "default_host")]
+    pub host: IpAddr,
+}
+
+fn default_port() -> u16 {
+    DEFAULT_ADMIN_PORT
+}
+
+fn default_host() -> IpAddr {
+    IpAddr::V4(Ipv4Addr::LOCALHOST)
+}
+
+impl Default for Admin {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_port(),
+            host: default_host(),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::config::admin::Admin;
 use crate::config::router::Router;
 use crate::config::server::Server;
 use crate::config::tracing::Tracing;
@@ -5,11 +6,19 @@ use satex_core::component::Component;
 use satex_core::Error;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Mutex;
 
+pub mod admin;
 pub mod router;
 pub mod server;
 pub mod tracing;
 
+///
+/// 当前生效的配置, 管理接口和配置文件重新加载共享同一份配置
+///
+pub type SharedConfig = Arc<Mutex<Config>>;
+
 ///
 /// 配置文件
 ///
@@ -38,6 +47,12 @@ pub struct Config {
     ///
     #[serde(default)]
     pub tracing: Tracing,
+
+    ///
+    /// 管理接口配置
+    ///
+    #[serde(default)]
+    pub admin: Admin,
 }
 
 impl Config {
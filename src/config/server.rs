@@ -1,3 +1,4 @@
+use satex_server::RealIpHeader;
 use serde::{Deserialize, Serialize};
 use std::net::{IpAddr, Ipv4Addr};
 
@@ -40,6 +41,12 @@ pub struct Server {
     /// 每个工作线程允许的最大排队数量
     ///
     pub backlog: Option<u32>,
+
+    ///
+    /// 真实客户端地址配置
+    ///
+    #[serde(default)]
+    pub real_ip: Option<RealIp>,
 }
 
 fn default_port() -> u16 {
@@ -59,6 +66,7 @@ impl Default for Server {
             workers: None,
             max_concurrent_connections: None,
             backlog: None,
+            real_ip: None,
         }
     }
 }
@@ -103,3 +111,29 @@ impl Default for Tls {
         }
     }
 }
+
+///
+/// 真实客户端地址配置, 连接来自可信代理时从请求头中获取客户端地址,
+/// 替换`ClientAddr`, 原始的连接地址保存在`PeerAddr`中
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RealIp {
+    ///
+    /// 可信代理的地址段, 例如: `10.0.0.0/8`、`fd00::/8`
+    ///
+    pub trusted_proxies: Vec<String>,
+
+    ///
+    /// 获取客户端地址的请求头, 按照顺序使用第一个有效的请求头
+    ///
+    #[serde(default = "default_real_ip_headers")]
+    pub headers: Vec<RealIpHeader>,
+}
+
+fn default_real_ip_headers() -> Vec<RealIpHeader> {
+    vec![
+        RealIpHeader::XForwardedFor,
+        RealIpHeader::XRealIp,
+        RealIpHeader::Forwarded,
+    ]
+}
@@ -1,6 +1,9 @@
+use satex_core::Error;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::path::PathBuf;
 use std::str::FromStr;
 use tracing::Level;
+use tracing_subscriber::EnvFilter;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tracing {
@@ -43,6 +46,42 @@ pub struct Tracing {
     /// 日志输出最大级别
     ///
     pub max_level: MaxLevel,
+
+    ///
+    /// `EnvFilter`格式的过滤指令, 例如: `satex_load_balancer=debug,hyper=warn`, 设置后将忽略`max_level`
+    ///
+    #[serde(default)]
+    pub filter: Option<String>,
+
+    ///
+    /// 日志格式
+    ///
+    #[serde(default)]
+    pub format: Format,
+
+    ///
+    /// 是否输出到标准输出
+    ///
+    #[serde(default = "default_stdout")]
+    pub stdout: bool,
+
+    ///
+    /// 日志文件配置
+    ///
+    #[serde(default)]
+    pub file: Option<LogFile>,
+
+    ///
+    /// 采样配置
+    ///
+    #[serde(default)]
+    pub sampler: Sampler,
+
+    ///
+    /// OTLP导出配置
+    ///
+    #[serde(default)]
+    pub otlp: Otlp,
 }
 
 impl Default for Tracing {
@@ -56,10 +95,185 @@ impl Default for Tracing {
             display_thread_names: true,
             display_thread_ids: true,
             max_level: Default::default(),
+            filter: None,
+            format: Default::default(),
+            stdout: default_stdout(),
+            file: None,
+            sampler: Default::default(),
+            otlp: Default::default(),
+        }
+    }
+}
+
+impl Tracing {
+    ///
+    /// 根据过滤指令或者最大级别创建日志过滤器
+    ///
+    pub fn env_filter(&self) -> Result<EnvFilter, Error> {
+        match &self.filter {
+            Some(filter) => EnvFilter::try_new(filter).map_err(Error::new),
+            None => Ok(EnvFilter::new(Level::from(self.max_level).as_str())),
         }
     }
 }
 
+fn default_stdout() -> bool {
+    true
+}
+
+///
+/// 日志格式
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Format {
+    #[default]
+    Full,
+    Compact,
+    Json,
+}
+
+///
+/// 日志文件配置
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogFile {
+    ///
+    /// 日志文件目录
+    ///
+    pub directory: PathBuf,
+
+    ///
+    /// 日志文件名称前缀
+    ///
+    #[serde(default = "default_prefix")]
+    pub prefix: String,
+
+    ///
+    /// 轮转周期
+    ///
+    #[serde(default)]
+    pub rotation: Rotation,
+
+    ///
+    /// 保留的日志文件数量
+    ///
+    #[serde(default)]
+    pub max_files: Option<usize>,
+}
+
+fn default_prefix() -> String {
+    String::from("satex")
+}
+
+///
+/// 日志文件轮转周期
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Rotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+///
+/// 采样配置
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sampler {
+    ///
+    /// 采样比例, 取值范围`[0.0, 1.0]`
+    ///
+    #[serde(default = "default_ratio")]
+    pub ratio: f64,
+
+    ///
+    /// 是否优先使用上游的采样决策
+    ///
+    #[serde(default = "default_parent_based")]
+    pub parent_based: bool,
+}
+
+fn default_ratio() -> f64 {
+    1.0
+}
+
+fn default_parent_based() -> bool {
+    true
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Self {
+            ratio: default_ratio(),
+            parent_based: default_parent_based(),
+        }
+    }
+}
+
+///
+/// OTLP导出配置
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Otlp {
+    ///
+    /// 是否开启导出
+    ///
+    #[serde(default)]
+    pub enabled: bool,
+
+    ///
+    /// 导出协议
+    ///
+    #[serde(default)]
+    pub protocol: Protocol,
+
+    ///
+    /// 采集器地址, 未设置时使用`OpenTelemetry`的默认地址
+    ///
+    #[serde(default)]
+    pub endpoint: Option<String>,
+
+    ///
+    /// 服务名称
+    ///
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+
+    ///
+    /// 导出超时时间
+    ///
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+fn default_service_name() -> String {
+    String::from("satex")
+}
+
+impl Default for Otlp {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            protocol: Protocol::default(),
+            endpoint: None,
+            service_name: default_service_name(),
+            timeout_secs: None,
+        }
+    }
+}
+
+///
+/// OTLP导出协议
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Protocol {
+    #[default]
+    Grpc,
+    Http,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct MaxLevel(Level);
 
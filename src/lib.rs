@@ -3,7 +3,10 @@
 mod app;
 pub use app::App;
 
+pub mod admin;
 pub mod config;
+pub mod logging;
 pub mod make_router;
 pub mod registry;
+pub mod telemetry;
 pub mod watch;
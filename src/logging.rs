@@ -0,0 +1,135 @@
+//!
+//! 日志输出
+//!
+use crate::config::tracing::{Format, LogFile, Rotation, Tracing};
+use satex_core::Error;
+use std::io::Write;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{Builder, Rotation as RollingRotation};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{Layered, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry, fmt, reload};
+
+///
+/// 支持动态调整过滤器的订阅者
+///
+pub type LogSubscriber = Layered<reload::Layer<EnvFilter, Registry>, Registry>;
+
+///
+/// 日志输出层
+///
+pub type BoxLayer = Box<dyn Layer<LogSubscriber> + Send + Sync + 'static>;
+
+///
+/// 日志过滤器的重新加载句柄
+///
+#[derive(Clone)]
+pub struct LogFilterHandle(reload::Handle<EnvFilter, Registry>);
+
+impl LogFilterHandle {
+    ///
+    /// 创建可以重新加载的过滤层以及对应的句柄
+    ///
+    pub fn layer(filter: EnvFilter) -> (reload::Layer<EnvFilter, Registry>, Self) {
+        let (layer, handle) = reload::Layer::new(filter);
+        (layer, Self(handle))
+    }
+
+    ///
+    /// 当前生效的过滤指令
+    ///
+    pub fn current(&self) -> Result<String, Error> {
+        self.0
+            .with_current(|filter| filter.to_string())
+            .map_err(Error::new)
+    }
+
+    ///
+    /// 使用新的过滤指令替换当前的过滤器
+    ///
+    pub fn reload(&self, directives: &str) -> Result<(), Error> {
+        let filter = EnvFilter::try_new(directives).map_err(Error::new)?;
+        self.0.reload(filter).map_err(Error::new)
+    }
+}
+
+///
+/// 已初始化的日志, 释放时会停止文件日志的后台写入线程
+///
+pub struct Logging {
+    pub handle: LogFilterHandle,
+    _guards: Vec<WorkerGuard>,
+}
+
+///
+/// 根据配置初始化全局的日志订阅者
+///
+/// # Arguments
+///
+/// * `tracing`: 日志配置
+/// * `extra`: 额外的输出层, 例如: `OpenTelemetry`
+///
+/// returns: Result<Logging, Error>
+///
+pub fn init(tracing: &Tracing, extra: Option<BoxLayer>) -> Result<Logging, Error> {
+    let (filter, handle) = LogFilterHandle::layer(tracing.env_filter()?);
+    let mut layers = Vec::<BoxLayer>::new();
+    let mut guards = Vec::new();
+    if tracing.stdout {
+        layers.push(fmt_layer(tracing, std::io::stdout, tracing.display_ansi));
+    }
+    if let Some(file) = &tracing.file {
+        let (writer, guard) = tracing_appender::non_blocking(rolling_file(file)?);
+        layers.push(fmt_layer(tracing, writer, false));
+        guards.push(guard);
+    }
+    layers.extend(extra);
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(layers)
+        .try_init()
+        .map_err(Error::new)?;
+    Ok(Logging {
+        handle,
+        _guards: guards,
+    })
+}
+
+fn fmt_layer<W>(tracing: &Tracing, writer: W, ansi: bool) -> BoxLayer
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi)
+        .with_file(tracing.display_file)
+        .with_thread_names(tracing.display_thread_names)
+        .with_thread_ids(tracing.display_thread_ids)
+        .with_line_number(tracing.display_line_number)
+        .with_level(tracing.display_level)
+        .with_target(tracing.display_target);
+    match tracing.format {
+        Format::Full => layer.boxed(),
+        Format::Compact => layer.compact().boxed(),
+        Format::Json => layer.json().boxed(),
+    }
+}
+
+fn rolling_file(file: &LogFile) -> Result<impl Write + Send + 'static, Error> {
+    let rotation = match file.rotation {
+        Rotation::Minutely => RollingRotation::MINUTELY,
+        Rotation::Hourly => RollingRotation::HOURLY,
+        Rotation::Daily => RollingRotation::DAILY,
+        Rotation::Never => RollingRotation::NEVER,
+    };
+    let mut builder = Builder::new()
+        .rotation(rotation)
+        .filename_prefix(&file.prefix)
+        .filename_suffix("log");
+    if let Some(max_files) = file.max_files {
+        builder = builder.max_log_files(max_files);
+    }
+    builder.build(&file.directory).map_err(Error::new)
+}
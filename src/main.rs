@@ -27,12 +27,15 @@ const WATCH_FILE_INTERVAL: Duration = Duration::from_secs(10);
 async fn main() -> Result<(), Error> {
     let registry = Registry::default();
     let path = get_config_path()?;
-    let events = ConfigFileWatchEvents::events(registry.clone(), path.clone(), WATCH_FILE_INTERVAL);
-    let config = Config::from_yaml(path)?;
-    App::new(SATEX, config, registry)
-        .with_events(events)
-        .run()
-        .await
+    let config = Config::from_yaml(&path)?;
+    let app = App::new(SATEX, config, registry);
+    let events = ConfigFileWatchEvents::events(
+        app.make_router(),
+        app.shared_config(),
+        path,
+        WATCH_FILE_INTERVAL,
+    );
+    app.with_events(events).run().await
 }
 
 /// 获取配置文件路径
@@ -3,6 +3,7 @@
 use crate::config::Config;
 use crate::registry::Registry;
 use http::Extensions;
+use satex_core::background::Supervisor;
 use satex_core::util::With;
 use satex_core::Error;
 use satex_layer::make::MakeRouteLayer;
@@ -13,22 +14,59 @@ use satex_load_balancer::resolver::{
 use satex_matcher::make::MakeRouteMatcher;
 use satex_server::router::{Route, Router};
 use satex_service::make::MakeRouteService;
+use std::sync::{Arc, Mutex};
 use tower::{Layer, Service};
 
+///
+/// 根据配置创建路由
+///
+/// 每次创建的路由是新的一代, 负载均衡器等后台任务由这一代路由的[Supervisor]启动;
+/// 同一个[MakeRouter]创建的新一代上游会继承当前一代同名上游的后端健康状态,
+/// 新一代路由生效后通过[Generation::commit]成为当前一代。
+///
 #[derive(Clone, Default)]
 pub struct MakeRouter {
     registry: Registry,
+    resolver: Arc<Mutex<Option<ArcLoadBalancerResolver>>>,
 }
 
 impl MakeRouter {
     pub fn new(registry: Registry) -> Self {
-        Self { registry }
+        Self {
+            registry,
+            resolver: Arc::default(),
+        }
     }
 
-    pub fn make(&self, config: &Config) -> Result<Router, Error> {
-        let resolver = self.make_resolver(config)?;
+    pub fn make(&self, config: &Config) -> Result<(Router, Generation), Error> {
+        let supervisor = Supervisor::new();
+        match self.make_with(config, &supervisor) {
+            Ok((router, resolver)) => {
+                if let Some(current) = self.resolver.lock().unwrap().as_ref() {
+                    resolver.inherit(current);
+                }
+                let generation = Generation {
+                    resolver,
+                    current: self.resolver.clone(),
+                };
+                Ok((router.with_supervisor(supervisor), generation))
+            }
+            // 创建失败时停止已经启动的后台任务
+            Err(e) => {
+                supervisor.cancel();
+                Err(e)
+            }
+        }
+    }
+
+    fn make_with(
+        &self,
+        config: &Config,
+        supervisor: &Supervisor,
+    ) -> Result<(Router, ArcLoadBalancerResolver), Error> {
+        let resolver = self.make_resolver(config, supervisor)?;
         let mut extensions = Extensions::default();
-        extensions.insert(resolver);
+        extensions.insert(resolver.clone());
 
         config
             .router
@@ -38,10 +76,14 @@ impl MakeRouter {
                 self.make_route(route, &config.router.global, &extensions)
                     .map(|route| routes.with(|routes| routes.push(route)))
             })
-            .map(Router::new)
+            .map(|routes| (Router::new(routes), resolver))
     }
 
-    fn make_resolver(&self, config: &Config) -> Result<ArcLoadBalancerResolver, Error> {
+    fn make_resolver(
+        &self,
+        config: &Config,
+        supervisor: &Supervisor,
+    ) -> Result<ArcLoadBalancerResolver, Error> {
         config
             .resolvers
             .iter()
@@ -49,7 +91,7 @@ impl MakeRouter {
                 CompositeLoadBalancerResolver::default(),
                 |mut composite, component| match self.registry.get_resolver(component.kind()) {
                     Some(make) => make
-                        .make(component.args())
+                        .make(component.args(), supervisor)
                         .map(|resolver| composite.push(resolver)),
                     None => Err(Error::new(format!(
                         "Miss load balancer resolver: {}",
@@ -120,3 +162,28 @@ impl MakeRouter {
         Ok(builder.build())
     }
 }
+
+///
+/// 新一代路由的上游, 路由生效之前不会影响之后创建的路由
+///
+#[must_use]
+pub struct Generation {
+    resolver: ArcLoadBalancerResolver,
+    current: Arc<Mutex<Option<ArcLoadBalancerResolver>>>,
+}
+
+impl Generation {
+    ///
+    /// 这一代路由使用的上游
+    ///
+    pub fn resolver(&self) -> &ArcLoadBalancerResolver {
+        &self.resolver
+    }
+
+    ///
+    /// 路由生效后调用, 之后创建的路由继承这一代上游的后端健康状态
+    ///
+    pub fn commit(self) {
+        *self.current.lock().unwrap() = Some(self.resolver);
+    }
+}
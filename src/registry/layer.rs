@@ -3,8 +3,10 @@ use bytes::Bytes;
 use http::{Request, Response};
 use satex_core::body::Body;
 use satex_core::BoxError;
+use satex_layer::access_log::MakeAccessLogRouteLayer;
 use satex_layer::concurrency_limit::MakeConcurrencyLimitRouteLayer;
 use satex_layer::cors::MakeCorsRouteLayer;
+use satex_layer::forwarded::MakeForwardedRouteLayer;
 use satex_layer::make::{ArcMakeRouteLayer, MakeRouteLayer};
 use satex_layer::remove_header::{
     MakeRemoveRequestHeaderRouteLayer, MakeRemoveResponseHeaderRouteLayer,
@@ -43,7 +45,9 @@ impl MakeRouteLayerRegistry {
             MakeTimeoutRouteLayer,
             MakeConcurrencyLimitRouteLayer,
             MakeSetPrefixRouteLayer,
-            MakeCorsRouteLayer
+            MakeCorsRouteLayer,
+            MakeAccessLogRouteLayer,
+            MakeForwardedRouteLayer
         }
         registry
     }
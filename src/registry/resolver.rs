@@ -1,6 +1,9 @@
 use crate::registry::push;
-use satex_load_balancer::resolver::MakeStaticLoadBalancerResolver;
 use satex_load_balancer::resolver::{ArcMakeLoadBalancerResolver, MakeLoadBalancerResolver};
+use satex_load_balancer::resolver::{
+    MakeConsulLoadBalancerResolver, MakeDnsLoadBalancerResolver, MakeFileLoadBalancerResolver,
+    MakeStaticLoadBalancerResolver,
+};
 use std::collections::HashMap;
 
 #[derive(Clone)]
@@ -15,7 +18,10 @@ impl MakeLoadBalancerResolverRegistry {
         let mut registry = Self::without_default();
         push! {
             registry,
-            MakeStaticLoadBalancerResolver
+            MakeStaticLoadBalancerResolver,
+            MakeDnsLoadBalancerResolver,
+            MakeFileLoadBalancerResolver,
+            MakeConsulLoadBalancerResolver
         }
         registry
     }
@@ -0,0 +1,70 @@
+//!
+//! OpenTelemetry 分布式追踪
+//!
+use crate::config::tracing::{Otlp, Protocol, Sampler};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::trace::{Sampler as SdkSampler, SdkTracerProvider};
+use satex_core::Error;
+use std::time::Duration;
+
+///
+/// 根据配置创建通过OTLP导出span的[`SdkTracerProvider`]
+///
+/// # Arguments
+///
+/// * `otlp`: OTLP导出配置
+/// * `sampler`: 采样配置
+///
+/// returns: Result<SdkTracerProvider, Error>
+///
+pub fn tracer_provider(otlp: &Otlp, sampler: &Sampler) -> Result<SdkTracerProvider, Error> {
+    let exporter = match otlp.protocol {
+        Protocol::Grpc => {
+            let mut builder = SpanExporter::builder().with_tonic();
+            if let Some(endpoint) = &otlp.endpoint {
+                builder = builder.with_endpoint(endpoint);
+            }
+            if let Some(timeout_secs) = otlp.timeout_secs {
+                builder = builder.with_timeout(Duration::from_secs(timeout_secs));
+            }
+            builder.build()
+        }
+        Protocol::Http => {
+            let mut builder = SpanExporter::builder().with_http();
+            if let Some(endpoint) = &otlp.endpoint {
+                builder = builder.with_endpoint(endpoint);
+            }
+            if let Some(timeout_secs) = otlp.timeout_secs {
+                builder = builder.with_timeout(Duration::from_secs(timeout_secs));
+            }
+            builder.build()
+        }
+    }
+    .map_err(Error::new)?;
+
+    let resource = Resource::builder()
+        .with_service_name(otlp.service_name.clone())
+        .build();
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_sampler(make_sampler(sampler))
+        .with_resource(resource)
+        .build())
+}
+
+fn make_sampler(sampler: &Sampler) -> SdkSampler {
+    let root = if sampler.ratio >= 1.0 {
+        SdkSampler::AlwaysOn
+    } else if sampler.ratio <= 0.0 {
+        SdkSampler::AlwaysOff
+    } else {
+        SdkSampler::TraceIdRatioBased(sampler.ratio)
+    };
+    if sampler.parent_based {
+        SdkSampler::ParentBased(Box::new(root))
+    } else {
+        root
+    }
+}
@@ -1,9 +1,9 @@
-use crate::config::Config;
+use crate::config::{Config, SharedConfig};
 use crate::make_router::MakeRouter;
-use crate::registry::Registry;
 use async_stream::stream;
 use futures::Stream;
 use satex_core::Error;
+use satex_core::metrics::record_reload;
 use satex_server::router::Event;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime};
@@ -13,11 +13,17 @@ use tokio::sync::mpsc::{channel, Sender};
 use tokio::time::sleep;
 use tracing::error;
 
+const SOURCE: &str = "file";
+
 pub struct ConfigFileWatchEvents;
 
 impl ConfigFileWatchEvents {
+    ///
+    /// 监控配置文件的修改, 重新加载成功后推送路由事件并更新共享的配置
+    ///
     pub fn events(
-        registry: Registry,
+        make_router: MakeRouter,
+        config: SharedConfig,
         file: PathBuf,
         interval: Duration,
     ) -> impl Stream<Item=Event> {
@@ -25,7 +31,7 @@ impl ConfigFileWatchEvents {
 
         // spawn watch task
         spawn(async move {
-            if let Err(e) = watch(tx, registry, file, interval).await {
+            if let Err(e) = watch(tx, make_router, config, file, interval).await {
                 error!("Watch config file error: {}", e);
             }
         });
@@ -40,20 +46,41 @@ impl ConfigFileWatchEvents {
 
 async fn watch(
     tx: Sender<Event>,
-    registry: Registry,
+    make_router: MakeRouter,
+    config: SharedConfig,
     file: impl AsRef<Path>,
     interval: Duration,
 ) -> Result<(), Error> {
-    let make_router = MakeRouter::new(registry);
     let mut modified = get_modified(&file).await?;
     loop {
         sleep(interval).await;
         let last_modified = get_modified(&file).await?;
         if last_modified > modified {
             modified = last_modified;
-            let config = Config::from_yaml(&file)?;
-            let router = make_router.make(&config)?;
-            tx.send(Event::Set(router)).await.map_err(Error::new)?;
+            // 持有配置的锁直到路由生效, 避免与管理接口的变更交错
+            let mut current = config.lock().await;
+            match Config::from_yaml(&file)
+                .and_then(|config| make_router.make(&config).map(|router| (config, router)))
+            {
+                Ok((config, (router, generation))) => {
+                    // 路由没有生效, 停止新的路由已经启动的后台任务
+                    if let Err(e) = tx.send(Event::Set(router)).await {
+                        record_reload(SOURCE, false);
+                        if let Event::Set(router) = &e.0 {
+                            router.supervisor().cancel();
+                        }
+                        return Err(Error::new(e));
+                    }
+                    record_reload(SOURCE, true);
+                    generation.commit();
+                    *current = config;
+                }
+                // 配置错误时保留当前的路由, 等待下一次修改
+                Err(e) => {
+                    record_reload(SOURCE, false);
+                    error!("Reload config file error: {}", e);
+                }
+            }
         }
     }
 }
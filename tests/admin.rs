@@ -0,0 +1,246 @@
+use futures::{FutureExt, Stream, StreamExt};
+use http::{Method, Request, StatusCode};
+use http_body_util::{BodyExt, Full};
+use satex::admin::Admin;
+use satex::config::{Config, SharedConfig};
+use satex::logging::LogFilterHandle;
+use satex::make_router::MakeRouter;
+use satex::registry::Registry;
+use satex::watch::ConfigFileWatchEvents;
+use satex_server::router::Event;
+use std::pin::pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, timeout};
+use tracing_subscriber::EnvFilter;
+
+const CONFIG: &str = r#"
+router:
+  routes:
+    - id: echo
+      service: Echo=Hello
+"#;
+
+fn shared_config() -> SharedConfig {
+    Arc::new(Mutex::new(serde_yaml::from_str::<Config>(CONFIG).unwrap()))
+}
+
+fn request(method: Method, uri: &str, body: &'static str) -> Request<Full<bytes::Bytes>> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .body(Full::from(body))
+        .unwrap()
+}
+
+async fn body(admin: &Admin, request: Request<Full<bytes::Bytes>>) -> (StatusCode, String) {
+    let response = admin.handle(request).await;
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+fn next(events: &mut (impl Stream<Item = Event> + Unpin)) -> Option<Event> {
+    events.next().now_or_never().flatten()
+}
+
+#[tokio::test]
+async fn put_and_delete_route() {
+    let config = shared_config();
+    let (admin, events) = Admin::new(MakeRouter::new(Registry::default()), config);
+    let mut events = pin!(events);
+
+    let (status, _) = body(
+        &admin,
+        request(Method::PUT, "/routes/status", "service: StatusCode=404"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(matches!(next(&mut events), Some(Event::Set(_))));
+
+    let (status, routes) = body(&admin, request(Method::GET, "/routes", "")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(routes.contains(r#""id":"echo""#));
+    assert!(routes.contains(r#""id":"status""#));
+
+    let (status, _) = body(&admin, request(Method::DELETE, "/routes/echo", "")).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(matches!(next(&mut events), Some(Event::Set(_))));
+
+    let (status, _) = body(&admin, request(Method::DELETE, "/routes/echo", "")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(next(&mut events).is_none());
+}
+
+#[tokio::test]
+async fn replace_routes() {
+    let config = shared_config();
+    let (admin, events) = Admin::new(MakeRouter::new(Registry::default()), config);
+    let mut events = pin!(events);
+
+    let (status, _) = body(
+        &admin,
+        request(
+            Method::PUT,
+            "/routes",
+            r#"[{"id": "a", "service": "Echo=A"}, {"id": "b", "service": "Echo=B"}]"#,
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(matches!(next(&mut events), Some(Event::Set(_))));
+
+    let (status, _) = body(&admin, request(Method::GET, "/routes/echo", "")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = body(&admin, request(Method::GET, "/routes/b", "")).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn reject_invalid_route() {
+    let config = shared_config();
+    let (admin, events) = Admin::new(MakeRouter::new(Registry::default()), config);
+    let mut events = pin!(events);
+
+    let (status, error) = body(
+        &admin,
+        request(Method::PUT, "/routes/invalid", "service: Unknown=1"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error.contains("Miss route service: Unknown"));
+
+    let (status, _) = body(
+        &admin,
+        request(Method::PUT, "/routes/invalid", "id: other\nservice: Echo=1"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(next(&mut events).is_none());
+
+    let (_, routes) = body(&admin, request(Method::GET, "/routes", "")).await;
+    assert!(!routes.contains("invalid"));
+}
+
+#[tokio::test]
+async fn reject_when_events_closed() {
+    let config = shared_config();
+    let (admin, events) = Admin::new(MakeRouter::new(Registry::default()), config);
+    drop(events);
+
+    let (status, _) = body(
+        &admin,
+        request(Method::PUT, "/routes/closed", "service: Echo=1"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    let (_, routes) = body(&admin, request(Method::GET, "/routes", "")).await;
+    assert!(!routes.contains("closed"));
+}
+
+#[tokio::test]
+async fn metrics() {
+    let config = shared_config();
+    let (admin, events) = Admin::new(MakeRouter::new(Registry::default()), config);
+    let mut events = pin!(events);
+
+    let (status, _) = body(
+        &admin,
+        request(Method::PUT, "/routes/metrics", "service: Echo=1"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(next(&mut events).is_some());
+
+    let response = admin.handle(request(Method::GET, "/metrics", "")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        response.headers()[http::header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain")
+    );
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(text.contains(r#"satex_config_reloads_total{result="success",source="admin"}"#));
+
+    let (status, _) = body(&admin, request(Method::POST, "/metrics", "")).await;
+    assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn reload_log_filter() {
+    let config = shared_config();
+    let (admin, _) = Admin::new(MakeRouter::new(Registry::default()), config.clone());
+    let (status, _) = body(&admin, request(Method::GET, "/logging", "")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (_layer, handle) = LogFilterHandle::layer(EnvFilter::new("info"));
+    let (admin, _) = Admin::new(MakeRouter::new(Registry::default()), config);
+    let admin = admin.with_log_filter(handle);
+
+    let (status, filter) = body(&admin, request(Method::GET, "/logging", "")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(filter, r#"{"filter":"info"}"#);
+
+    let (status, _) = body(
+        &admin,
+        request(
+            Method::PUT,
+            "/logging",
+            "satex_load_balancer=debug,hyper=warn",
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, filter) = body(&admin, request(Method::GET, "/logging", "")).await;
+    assert!(filter.contains("satex_load_balancer=debug"));
+    assert!(filter.contains("hyper=warn"));
+
+    let (status, _) = body(
+        &admin,
+        request(Method::PUT, "/logging", r#"{"filter": "debug"}"#),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, filter) = body(&admin, request(Method::GET, "/logging", "")).await;
+    assert_eq!(filter, r#"{"filter":"debug"}"#);
+
+    let (status, _) = body(&admin, request(Method::PUT, "/logging", "[1]")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn reload_config_file() {
+    let file = std::env::temp_dir().join(format!("satex-admin-{}.yaml", std::process::id()));
+    std::fs::write(&file, CONFIG).unwrap();
+    let make_router = MakeRouter::new(Registry::default());
+    let config = shared_config();
+    let file_events = ConfigFileWatchEvents::events(
+        make_router.clone(),
+        config.clone(),
+        file.clone(),
+        Duration::from_millis(50),
+    );
+    let mut file_events = pin!(file_events);
+    let (admin, _events) = Admin::new(make_router, config);
+
+    sleep(Duration::from_millis(100)).await;
+    std::fs::write(&file, CONFIG.replace("id: echo", "id: file")).unwrap();
+    let event = timeout(Duration::from_secs(5), file_events.next()).await;
+    assert!(matches!(event, Ok(Some(Event::Set(_)))));
+    std::fs::remove_file(&file).unwrap();
+
+    // 管理接口的变更基于重新加载后的配置
+    let (status, _) = body(
+        &admin,
+        request(Method::PUT, "/routes/admin", "service: Echo=1"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, routes) = body(&admin, request(Method::GET, "/routes", "")).await;
+    assert!(routes.contains(r#""id":"file""#));
+    assert!(routes.contains(r#""id":"admin""#));
+    assert!(!routes.contains(r#""id":"echo""#));
+}
@@ -0,0 +1,54 @@
+use satex::config::tracing::{Format, Rotation, Tracing};
+use satex::config::Config;
+
+#[test]
+fn default_filter_from_max_level() {
+    let tracing = Tracing::default();
+    assert_eq!(tracing.env_filter().unwrap().to_string(), "debug");
+    assert_eq!(tracing.format, Format::Full);
+    assert!(tracing.stdout);
+    assert!(tracing.file.is_none());
+}
+
+#[test]
+fn parse_logging_config() {
+    let yaml = r#"
+tracing:
+  display_ansi: false
+  display_file: false
+  display_target: true
+  display_level: true
+  display_line_number: false
+  display_thread_names: false
+  display_thread_ids: false
+  max_level: info
+  filter: satex_load_balancer=debug,hyper=warn
+  format: Json
+  stdout: false
+  file:
+    directory: logs
+    rotation: Hourly
+    max_files: 24
+"#;
+    let config = serde_yaml::from_str::<Config>(yaml).unwrap();
+    let tracing = config.tracing;
+    assert_eq!(tracing.format, Format::Json);
+    assert!(!tracing.stdout);
+    let filter = tracing.env_filter().unwrap().to_string();
+    assert!(filter.contains("satex_load_balancer=debug"));
+    assert!(filter.contains("hyper=warn"));
+
+    let file = tracing.file.unwrap();
+    assert_eq!(file.prefix, "satex");
+    assert_eq!(file.rotation, Rotation::Hourly);
+    assert_eq!(file.max_files, Some(24));
+}
+
+#[test]
+fn reject_invalid_filter() {
+    let tracing = Tracing {
+        filter: Some(String::from("satex=unknown")),
+        ..Default::default()
+    };
+    assert!(tracing.env_filter().is_err());
+}
@@ -0,0 +1,58 @@
+use satex::config::Config;
+use satex::make_router::MakeRouter;
+use satex::registry::Registry;
+use satex_load_balancer::Backend;
+use satex_load_balancer::resolver::LoadBalancerResolver;
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::time::sleep;
+
+const CONFIG: &str = r#"
+resolvers:
+  - kind: Static
+    args:
+      upstreams:
+        - name: backend
+          health-check:
+            enabled: false
+          outlier-detection:
+            consecutive-errors: 1
+            max-ejection-percent: 100
+          addrs:
+            - 127.0.0.1:8080
+            - 127.0.0.2:8080
+router:
+  routes:
+    - id: echo
+      service: Echo=Hello
+"#;
+
+#[tokio::test]
+async fn inherit_committed_generation() {
+    let make_router = MakeRouter::new(Registry::default());
+    let config = serde_yaml::from_str::<Config>(CONFIG).unwrap();
+
+    let (_current, generation) = make_router.make(&config).unwrap();
+    generation.commit();
+
+    // 没有生效的一代不会被之后创建的路由继承
+    let (_discarded, generation) = make_router.make(&config).unwrap();
+    sleep(Duration::from_millis(100)).await;
+    let load_balancer = generation.resolver().find("backend").unwrap();
+    load_balancer
+        .report(&Backend::from_str("127.0.0.1:8080").unwrap(), false)
+        .await;
+    drop(generation);
+
+    let (_next, generation) = make_router.make(&config).unwrap();
+    sleep(Duration::from_millis(100)).await;
+    let load_balancer = generation.resolver().find("backend").unwrap();
+    let addrs = (0..10)
+        .map(|_| load_balancer.select(b"").unwrap().addr.to_string())
+        .collect::<HashSet<_>>();
+    assert_eq!(
+        addrs,
+        HashSet::from(["127.0.0.1:8080".to_string(), "127.0.0.2:8080".to_string()])
+    );
+}
@@ -0,0 +1,94 @@
+use http::{Request, Response};
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use opentelemetry::trace::{Tracer, TracerProvider};
+use satex::config::tracing::{Otlp, Protocol, Sampler};
+use satex::telemetry::tracer_provider;
+use std::convert::Infallible;
+use tokio::net::TcpListener;
+use tokio::spawn;
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+use tokio::task::spawn_blocking;
+
+///
+/// 模拟OTLP采集器, 记录收到的请求路径和请求体大小
+///
+async fn collector(sender: UnboundedSender<(String, usize)>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let sender = sender.clone();
+            spawn(async move {
+                let service = service_fn(move |request: Request<Incoming>| {
+                    let sender = sender.clone();
+                    async move {
+                        let path = request.uri().path().to_string();
+                        let bytes = request.into_body().collect().await.unwrap().to_bytes();
+                        sender.send((path, bytes.len())).unwrap();
+                        Ok::<_, Infallible>(Response::new(Full::<bytes::Bytes>::default()))
+                    }
+                });
+                let _ = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+            });
+        }
+    });
+    format!("http://{}/v1/traces", addr)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn export_spans_over_http() {
+    let (sender, mut receiver) = unbounded_channel();
+    let endpoint = collector(sender).await;
+    let otlp = serde_yaml::from_str::<Otlp>(&format!(
+        "enabled: true\nprotocol: Http\nendpoint: {}",
+        endpoint
+    ))
+    .unwrap();
+    assert_eq!(otlp.protocol, Protocol::Http);
+
+    let provider = tracer_provider(&otlp, &Sampler::default()).unwrap();
+    provider.tracer("test").in_span("route", |_| {});
+    let provider = spawn_blocking(move || {
+        provider.force_flush().unwrap();
+        provider
+    })
+    .await
+    .unwrap();
+
+    let (path, size) = receiver.recv().await.unwrap();
+    assert_eq!(path, "/v1/traces");
+    assert!(size > 0);
+    spawn_blocking(move || provider.shutdown().unwrap())
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sampler_always_off() {
+    let (sender, mut receiver) = unbounded_channel();
+    let endpoint = collector(sender).await;
+    let otlp = serde_yaml::from_str::<Otlp>(&format!(
+        "enabled: true\nprotocol: Http\nendpoint: {}",
+        endpoint
+    ))
+    .unwrap();
+    let sampler = serde_yaml::from_str::<Sampler>("ratio: 0.0\nparent_based: false").unwrap();
+
+    let provider = tracer_provider(&otlp, &sampler).unwrap();
+    provider.tracer("test").in_span("route", |_| {});
+    spawn_blocking(move || {
+        provider.force_flush().unwrap();
+        provider.shutdown().unwrap();
+    })
+    .await
+    .unwrap();
+
+    assert!(receiver.try_recv().is_err());
+}